@@ -1,8 +1,7 @@
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 mod macros;
 pub mod results;
+pub mod stats;
 
-pub use macros::*;
+#[doc(hidden)]
+pub use tracing;
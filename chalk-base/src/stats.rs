@@ -0,0 +1,61 @@
+/// Counters describing how much work a solver has done. Both the SLG
+/// forest and the recursive solver update these as they go; callers
+/// can read them after a `solve` call and reset them between queries.
+///
+/// Counters that don't apply to a given solver simply stay at zero
+/// (e.g., the recursive solver never creates tables or strands).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SolverStats {
+    /// Number of SLG tables created, not counting those copied from
+    /// a shared store of completed tables.
+    pub tables_created: usize,
+
+    /// Number of completed SLG tables copied from a shared store
+    /// instead of being created (see `Forest::with_completed_tables`).
+    pub tables_reused: usize,
+
+    /// Number of SLG tables evicted to stay within the table budget.
+    pub tables_evicted: usize,
+
+    /// Number of SLG tables, or entries of the recursive solver's
+    /// cache, discarded because an item they depend on has changed.
+    pub invalidations: usize,
+
+    /// Number of SLG strands created, including the initial strands
+    /// of each table and the strands forked off to pursue further
+    /// answers of a subgoal.
+    pub strands_created: usize,
+
+    /// Number of new answers added to SLG tables.
+    pub answers: usize,
+
+    /// Number of times a root search yielded with `QuantumExceeded`.
+    pub quantum_exceeded: usize,
+
+    /// Number of goals or answers that exceeded the maximum size. In
+    /// SLG, this causes a subgoal or table to flounder; the recursive
+    /// solver gives up on the goal with an ambiguous result.
+    pub truncations: usize,
+
+    /// Number of goals answered from the recursive solver's cache.
+    pub cache_hits: usize,
+
+    /// Number of goals that the recursive solver had to solve from
+    /// scratch (i.e., that were not found in the cache).
+    pub cache_misses: usize,
+
+    /// Number of cycles detected by the recursive solver, i.e., goals
+    /// that were found to already be on the stack.
+    pub cycles: usize,
+
+    /// Number of times the recursive solver reached its overflow depth
+    /// and gave up on a goal with an ambiguous result.
+    pub overflows: usize,
+}
+
+impl SolverStats {
+    /// Resets all counters to zero.
+    pub fn reset(&mut self) {
+        *self = SolverStats::default();
+    }
+}
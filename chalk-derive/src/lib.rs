@@ -122,6 +122,7 @@ decl_derive!([Visit, attributes(has_interner)] => derive_visit);
 decl_derive!([SuperVisit, attributes(has_interner)] => derive_super_visit);
 decl_derive!([Fold, attributes(has_interner)] => derive_fold);
 decl_derive!([Zip, attributes(has_interner)] => derive_zip);
+decl_derive!([Encode, attributes(has_interner)] => derive_encode);
 
 fn derive_has_interner(mut s: synstructure::Structure) -> TokenStream {
     let (interner, _) = find_interner(&mut s);
@@ -326,3 +327,85 @@ fn derive_fold(mut s: synstructure::Structure) -> TokenStream {
         },
     )
 }
+
+/// Derives Encode for structs and enums for which one of the following is true:
+/// - It has a `#[has_interner(TheInterner)]` attribute
+/// - There is a single parameter `T: HasInterner` (does not have to be named `T`)
+/// - There is a single parameter `I: Interner` (does not have to be named `I`)
+/// - There are no parameters at all, in which case it can be encoded with any interner
+///
+/// The fields are encoded in order, preceded by the index of the variant for enums.
+fn derive_encode(mut s: synstructure::Structure) -> TokenStream {
+    let input = s.ast();
+
+    let interner = if input.generics.params.is_empty() {
+        s.add_impl_generic(parse_quote! { _I });
+        quote! { _I }
+    } else {
+        let (interner, kind) = find_interner(&mut s);
+        if kind == DeriveKind::FromHasInterner {
+            let param = get_generic_param_name(input).unwrap();
+            s.add_where_predicate(parse_quote! { #param: ::chalk_ir::encode::Encode<#interner> });
+        }
+        interner
+    };
+    s.add_where_predicate(parse_quote! { #interner: ::chalk_ir::encode::EncodeInterner });
+
+    let is_enum = matches!(input.data, syn::Data::Enum(_));
+
+    let mut encode_body = TokenStream::new();
+    let mut decode_body = TokenStream::new();
+    for (index, variant) in s.variants().iter().enumerate() {
+        let index = index as u32;
+        let pat = variant.pat();
+        let tag = if is_enum {
+            quote!(encoder.write_u32(#index);)
+        } else {
+            quote!()
+        };
+        let fields = variant.bindings().iter().map(|bi| {
+            quote! {
+                ::chalk_ir::encode::Encode::encode(#bi, encoder);
+            }
+        });
+        quote!(#pat => { #tag #(#fields)* }).to_tokens(&mut encode_body);
+
+        let construct = variant.construct(|_, _| {
+            quote! {
+                ::chalk_ir::encode::Encode::decode(decoder)?
+            }
+        });
+        if is_enum {
+            quote!(#index => #construct,).to_tokens(&mut decode_body);
+        } else {
+            construct.to_tokens(&mut decode_body);
+        }
+    }
+
+    let decode_body = if is_enum {
+        quote! {
+            match decoder.read_u32()? {
+                #decode_body
+                tag => return Err(::chalk_ir::encode::DecodeError::InvalidTag(tag)),
+            }
+        }
+    } else {
+        decode_body
+    };
+
+    s.add_bounds(synstructure::AddBounds::None);
+    s.bound_impl(
+        quote!(::chalk_ir::encode::Encode<#interner>),
+        quote! {
+            fn encode(&self, encoder: &mut ::chalk_ir::encode::Encoder<'_, #interner>) {
+                match *self { #encode_body }
+            }
+
+            fn decode(
+                decoder: &mut ::chalk_ir::encode::Decoder<'_, #interner>,
+            ) -> ::std::result::Result<Self, ::chalk_ir::encode::DecodeError> {
+                Ok(#decode_body)
+            }
+        },
+    )
+}
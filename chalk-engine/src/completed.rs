@@ -0,0 +1,152 @@
+use crate::context::Context;
+use crate::tables::Tables;
+use crate::{Answer, TableIndex};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::{Arc, RwLock};
+
+/// A store of *completed* tables, which can be shared between several
+/// forests (typically, forests used by different threads to solve
+/// goals against the same program). A table is completed once it has
+/// no strands left, so its set of answers can never change; tables
+/// that are still in progress always stay local to their forest.
+///
+/// Cloning a `CompletedTables` yields a handle to the same store.
+pub struct CompletedTables<C: Context> {
+    tables: Arc<RwLock<CompletedTableMap<C>>>,
+}
+
+type CompletedTableMap<C> =
+    FxHashMap<<C as Context>::UCanonicalGoalInEnvironment, Arc<CompletedTable<C>>>;
+
+/// A completed table in a form that can be kept outside of the engine
+/// (e.g., saved to disk); see `CompletedTables::export`.
+pub struct ExportedTable<C: Context> {
+    pub goal: C::UCanonicalGoalInEnvironment,
+    pub floundered: bool,
+    pub answers: Vec<Answer<C>>,
+
+    /// The items this table depends on, directly or through its
+    /// subgoal tables (see `Forest::invalidate`).
+    pub dependencies: Vec<C::ItemId>,
+}
+
+/// The final state of a table.
+pub(crate) struct CompletedTable<C: Context> {
+    pub(crate) floundered: bool,
+    pub(crate) answers: Vec<Answer<C>>,
+
+    /// The items this table depends on, directly or through its
+    /// subgoal tables.
+    pub(crate) dependencies: FxHashSet<C::ItemId>,
+}
+
+impl<C: Context> CompletedTables<C> {
+    pub fn new() -> Self {
+        CompletedTables {
+            tables: Arc::new(RwLock::new(FxHashMap::default())),
+        }
+    }
+
+    /// Number of completed tables in the store.
+    pub fn len(&self) -> usize {
+        self.tables.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn get(
+        &self,
+        goal: &C::UCanonicalGoalInEnvironment,
+    ) -> Option<Arc<CompletedTable<C>>> {
+        self.tables.read().unwrap().get(goal).cloned()
+    }
+
+    /// Publishes every table of `tables` that is complete and that has
+    /// not been published yet. If another forest published the same
+    /// table in the meantime, its version is kept (both have the same
+    /// answers, though perhaps in a different order).
+    pub(crate) fn publish(&self, tables: &mut Tables<C>) {
+        let unpublished: Vec<TableIndex> = tables
+            .iter()
+            .filter(|(_, table)| !table.published && table.is_complete())
+            .map(|(index, _)| index)
+            .collect();
+        if unpublished.is_empty() {
+            return;
+        }
+
+        let mut store = self.tables.write().unwrap();
+        for index in unpublished {
+            let dependencies = tables.transitive_dependencies(index);
+            let table = &mut tables[index];
+            table.published = true;
+            store
+                .entry(table.table_goal.clone())
+                .or_insert_with(|| Arc::new(table.to_completed(dependencies)));
+        }
+    }
+
+    /// Returns a copy of every table in the store.
+    pub fn export(&self) -> Vec<ExportedTable<C>> {
+        let store = self.tables.read().unwrap();
+        store
+            .iter()
+            .map(|(goal, table)| ExportedTable {
+                goal: goal.clone(),
+                floundered: table.floundered,
+                answers: table.answers.clone(),
+                dependencies: table.dependencies.iter().cloned().collect(),
+            })
+            .collect()
+    }
+
+    /// Adds the given tables to the store, typically tables exported
+    /// by another process for the same program. A table is skipped if
+    /// the store has a table for the same goal already. Returns the
+    /// number of tables added.
+    pub fn import(&self, tables: impl IntoIterator<Item = ExportedTable<C>>) -> usize {
+        let mut store = self.tables.write().unwrap();
+        let len = store.len();
+        for table in tables {
+            let ExportedTable {
+                goal,
+                floundered,
+                answers,
+                dependencies,
+            } = table;
+            store.entry(goal).or_insert_with(|| {
+                Arc::new(CompletedTable {
+                    floundered,
+                    answers,
+                    dependencies: dependencies.into_iter().collect(),
+                })
+            });
+        }
+        store.len() - len
+    }
+
+    /// Removes every table that depends on one of the `changed` items,
+    /// and returns how many were removed.
+    pub(crate) fn invalidate(&self, changed: &FxHashSet<C::ItemId>) -> usize {
+        let mut store = self.tables.write().unwrap();
+        let len = store.len();
+        store.retain(|_, table| table.dependencies.is_disjoint(changed));
+        len - store.len()
+    }
+}
+
+impl<C: Context> Clone for CompletedTables<C> {
+    fn clone(&self) -> Self {
+        CompletedTables {
+            tables: self.tables.clone(),
+        }
+    }
+}
+
+impl<C: Context> Default for CompletedTables<C> {
+    fn default() -> Self {
+        CompletedTables::new()
+    }
+}
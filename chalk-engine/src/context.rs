@@ -8,6 +8,7 @@
 use crate::hh::HhGoal;
 use crate::{CompleteAnswer, ExClause};
 use chalk_base::results::{Fallible, Floundered};
+use rustc_hash::FxHashSet;
 use std::fmt::Debug;
 use std::hash::Hash;
 
@@ -122,6 +123,17 @@ pub trait Context: Clone + Debug {
     /// The type used to store concrete representations of "core types" from chalk-ir.
     type Interner;
 
+    /// Identifies an item of the program (a trait, an impl, ...) that
+    /// program clauses are derived from. Each table remembers the items
+    /// its program clauses came from, so that it can be invalidated
+    /// when one of them changes (see `Forest::invalidate`).
+    type ItemId: Clone + Debug + Eq + Hash;
+
+    /// Tells what a program clause was derived from (an impl, the
+    /// environment, ...). The answers of a table record the source of
+    /// the clause they were derived with.
+    type ClauseSource: Clone + Debug + Eq;
+
     /// Given an environment and a goal, glue them together to create
     /// a `GoalInEnvironment`.
     fn goal_in_environment(
@@ -157,6 +169,11 @@ pub trait Context: Clone + Debug {
     /// Selects the next appropriate subgoal index for evaluation.
     /// Used by: logic
     fn next_subgoal_index(ex_clause: &ExClause<Self>) -> usize;
+
+    /// The number of subgoals of the given ex-clause that are left to
+    /// solve (not counting delayed or floundered subgoals).
+    /// Used by: table
+    fn num_subgoals(canonical_ex_clause: &Self::CanonicalExClause) -> usize;
 }
 
 pub trait ContextOps<C: Context>: Sized + Clone + Debug + AggregateOps<C> {
@@ -170,13 +187,20 @@ pub trait ContextOps<C: Context>: Sized + Clone + Debug + AggregateOps<C> {
     /// of program clauses cannot be enumerated because there are
     /// unresolved type variables that would have to be resolved
     /// first; the goal will be considered floundered.
+    ///
+    /// The items of the program that the clauses were derived from
+    /// are added to `dependencies` (even if the goal flounders).
     fn program_clauses(
         &self,
         environment: &C::Environment,
         goal: &C::DomainGoal,
         infer: &mut C::InferenceTable,
+        dependencies: &mut FxHashSet<C::ItemId>,
     ) -> Result<Vec<C::ProgramClause>, Floundered>;
 
+    /// The source of `clause`; see `Context::ClauseSource`.
+    fn clause_source(&self, clause: &C::ProgramClause) -> C::ClauseSource;
+
     // Used by: simplify
     fn add_clauses(&self, env: &C::Environment, clauses: C::ProgramClauses) -> C::Environment;
 
@@ -251,6 +275,15 @@ pub trait ContextOps<C: Context>: Sized + Clone + Debug + AggregateOps<C> {
     /// Upcast this domain goal into a more general goal.
     fn into_goal(&self, domain_goal: C::DomainGoal) -> C::Goal;
 
+    /// Creates the goal `not { G }` from the goal `G`. Negative
+    /// subgoals whose truth cannot be decided (yet) are delayed in
+    /// this form.
+    fn negate_goal(&self, goal: &C::GoalInEnvironment) -> C::GoalInEnvironment;
+
+    /// The inverse of `negate_goal`: if `goal` is `not { G }`, returns
+    /// `G`.
+    fn negated_goal(&self, goal: &C::GoalInEnvironment) -> Option<C::GoalInEnvironment>;
+
     fn is_trivial_substitution(
         &self,
         u_canon: &C::UCanonicalGoalInEnvironment,
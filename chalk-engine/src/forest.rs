@@ -1,9 +1,15 @@
+use crate::completed::CompletedTables;
 use crate::context::{AnswerResult, AnswerStream, Context, ContextOps};
 use crate::logic::RootSearchFail;
+use crate::schedule::{StrandSchedule, StrandScheduler};
 use crate::table::AnswerIndex;
 use crate::tables::Tables;
-use crate::{TableIndex, TimeStamp};
+use crate::{CompleteAnswer, TableIndex, TimeStamp};
+use chalk_base::stats::SolverStats;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::VecDeque;
 use std::fmt::Display;
+use std::sync::Arc;
 
 pub struct Forest<C: Context> {
     context: C,
@@ -14,6 +20,66 @@ pub struct Forest<C: Context> {
     /// This effectively gives us way to track what depth
     /// and loop a table or strand was last followed.
     pub(crate) clock: TimeStamp,
+
+    /// Counters tracking the work done by this forest.
+    pub(crate) stats: SolverStats,
+
+    /// Goals that were too big to be solved: subgoals that floundered
+    /// because they needed truncation, and the goals of tables that
+    /// floundered because one of their answers did.
+    pub(crate) truncated_goals: Vec<(C::UCanonicalGoalInEnvironment, Truncated)>,
+
+    /// The entries of `truncated_goals`, to keep them unique.
+    pub(crate) truncated_goals_set: FxHashSet<(C::UCanonicalGoalInEnvironment, Truncated)>,
+
+    /// If set, completed tables are taken from (and published to)
+    /// this store, so that they can be shared with other forests.
+    pub(crate) completed: Option<CompletedTables<C>>,
+
+    /// Limits how many tables are kept around; see `TableBudget`.
+    table_budget: TableBudget,
+
+    /// Picks the strands to pursue; see `StrandScheduler`.
+    pub(crate) strand_scheduler: Arc<dyn StrandScheduler>,
+}
+
+/// Limits the memory used by the tables of a `Forest`. Whenever a
+/// solve exceeds the budget, completed tables that no other table
+/// depends on are evicted, least recently used first, until the
+/// forest is within budget again (or until no more tables can be
+/// evicted). Evicted tables are recomputed if they are needed again.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TableBudget {
+    /// Never evict tables.
+    Unlimited,
+
+    /// Keep at most this many tables.
+    Tables(usize),
+
+    /// Keep at most this many answers, summed over all tables (a rough
+    /// estimate of the size of the tables).
+    Answers(usize),
+}
+
+/// What was too big when a goal was truncated; see
+/// `Forest::truncated_goals`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Truncated {
+    /// The goal itself.
+    Goal,
+
+    /// One of the answers to the goal.
+    Answer,
+}
+
+impl TableBudget {
+    fn is_exceeded(self, num_tables: usize, num_answers: usize) -> bool {
+        match self {
+            TableBudget::Unlimited => false,
+            TableBudget::Tables(max) => num_tables > max,
+            TableBudget::Answers(max) => num_answers > max,
+        }
+    }
 }
 
 impl<C: Context> Forest<C> {
@@ -22,6 +88,172 @@ impl<C: Context> Forest<C> {
             context,
             tables: Tables::new(),
             clock: TimeStamp::default(),
+            stats: SolverStats::default(),
+            truncated_goals: vec![],
+            truncated_goals_set: FxHashSet::default(),
+            completed: None,
+            table_budget: TableBudget::Unlimited,
+            strand_scheduler: Arc::new(StrandSchedule::default()),
+        }
+    }
+
+    pub fn table_budget(&self) -> TableBudget {
+        self.table_budget
+    }
+
+    /// Sets the budget for the tables of this forest. The budget is
+    /// enforced at the end of each `solve`.
+    pub fn set_table_budget(&mut self, table_budget: TableBudget) {
+        self.table_budget = table_budget;
+    }
+
+    pub fn strand_scheduler(&self) -> Arc<dyn StrandScheduler> {
+        self.strand_scheduler.clone()
+    }
+
+    /// Sets the scheduler that picks which strand of a table to pursue
+    /// next. This can be changed between solves, since it affects the
+    /// order in which answers are found but not the answers.
+    pub fn set_strand_scheduler(&mut self, strand_scheduler: Arc<dyn StrandScheduler>) {
+        self.strand_scheduler = strand_scheduler;
+    }
+
+    /// Evicts tables until the forest is within its table budget (or
+    /// until no more tables can be evicted).
+    fn evict_tables(&mut self) {
+        let mut num_tables = self.tables.len();
+        let mut num_answers = self.tables.iter().map(|(_, t)| t.num_answers()).sum();
+        if !self.table_budget.is_exceeded(num_tables, num_answers) {
+            return;
+        }
+
+        // Only completed tables can be evicted, and only if no strand
+        // still refers to them. Completed tables have no strands, so
+        // evicting a table never makes another one evictable.
+        let referenced: FxHashSet<TableIndex> = self
+            .tables
+            .iter()
+            .flat_map(|(_, table)| table.strands())
+            .filter_map(|strand| Some(strand.selected_subgoal.as_ref()?.subgoal_table))
+            .collect();
+        let mut candidates: Vec<_> = self
+            .tables
+            .iter()
+            .filter(|(index, table)| table.is_complete() && !referenced.contains(index))
+            .map(|(index, table)| (table.last_used, index))
+            .collect();
+        candidates.sort();
+
+        let mut evicted = FxHashMap::default();
+        for (_, index) in candidates {
+            if !self.table_budget.is_exceeded(num_tables, num_answers) {
+                break;
+            }
+            let table = self.tables.remove(index);
+            debug!(table = ?index, goal = ?table.table_goal, "evicting table");
+            num_tables -= 1;
+            num_answers -= table.num_answers();
+            self.stats.tables_evicted += 1;
+            evicted.insert(index, table);
+        }
+
+        // Tables that used the answers of an evicted table now depend
+        // directly on whatever the evicted table depended on, so that
+        // `invalidate` still reaches them.
+        for table in &mut self.tables {
+            let mut stack: Vec<TableIndex> = table
+                .subgoal_tables
+                .iter()
+                .filter(|index| evicted.contains_key(index))
+                .cloned()
+                .collect();
+            let mut visited = FxHashSet::default();
+            while let Some(index) = stack.pop() {
+                if !visited.insert(index) {
+                    continue;
+                }
+                table.subgoal_tables.remove(&index);
+                let evicted_table = &evicted[&index];
+                table
+                    .dependencies
+                    .extend(evicted_table.dependencies.iter().cloned());
+                for &subgoal_table in &evicted_table.subgoal_tables {
+                    if evicted.contains_key(&subgoal_table) {
+                        stack.push(subgoal_table);
+                    } else {
+                        table.subgoal_tables.insert(subgoal_table);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Discards every table whose answers may depend on one of the
+    /// `changed` items: the tables whose program clauses were derived
+    /// from a changed item, and all tables that used the answers of a
+    /// discarded table. If this forest shares its completed tables,
+    /// they are invalidated in the shared store as well. Returns the
+    /// number of tables discarded from this forest.
+    ///
+    /// This must be called whenever the program changes, before the
+    /// forest is used to solve goals for the new program.
+    pub fn invalidate(&mut self, changed: &FxHashSet<C::ItemId>) -> usize {
+        let mut dependents: FxHashMap<TableIndex, Vec<TableIndex>> = FxHashMap::default();
+        let mut stack = vec![];
+        for (index, table) in self.tables.iter() {
+            for &subgoal_table in &table.subgoal_tables {
+                dependents.entry(subgoal_table).or_default().push(index);
+            }
+            if !table.dependencies.is_disjoint(changed) {
+                stack.push(index);
+            }
+        }
+
+        let mut invalidated = FxHashSet::default();
+        while let Some(index) = stack.pop() {
+            if invalidated.insert(index) {
+                stack.extend(dependents.get(&index).into_iter().flatten().cloned());
+            }
+        }
+        for &index in &invalidated {
+            let table = self.tables.remove(index);
+            debug!(table = ?index, goal = ?table.table_goal, "invalidating table");
+        }
+        self.stats.invalidations += invalidated.len();
+
+        if let Some(completed) = &self.completed {
+            completed.invalidate(changed);
+        }
+        invalidated.len()
+    }
+
+    /// Creates a forest that shares its completed tables with every
+    /// other forest using the same `completed` store.
+    pub fn with_completed_tables(context: C, completed: CompletedTables<C>) -> Self {
+        Forest {
+            completed: Some(completed),
+            ..Forest::new(context)
+        }
+    }
+
+    /// Returns the store of completed tables of this forest, creating
+    /// one first if the forest did not share its tables so far. This
+    /// can be used to create other forests (see
+    /// `with_completed_tables`) that reuse the work done by this one.
+    pub fn completed_tables(&mut self) -> CompletedTables<C> {
+        let completed = self
+            .completed
+            .get_or_insert_with(CompletedTables::new)
+            .clone();
+        completed.publish(&mut self.tables);
+        completed
+    }
+
+    /// Publishes the tables completed by the last search, if this
+    /// forest shares its completed tables.
+    fn publish_completed_tables(&mut self) {
+        if let Some(completed) = &self.completed {
+            completed.publish(&mut self.tables);
         }
     }
 
@@ -35,6 +267,28 @@ impl<C: Context> Forest<C> {
         &self.context
     }
 
+    /// Returns the counters accumulated since the forest was created
+    /// (or since the last call to `reset_stats`).
+    pub fn stats(&self) -> &SolverStats {
+        &self.stats
+    }
+
+    /// Returns the goals that were truncated since the forest was
+    /// created (or since the last call to `reset_stats`), each listed
+    /// once. Whenever a goal is truncated, the results that depend on
+    /// it are ambiguous.
+    pub fn truncated_goals(&self) -> &[(C::UCanonicalGoalInEnvironment, Truncated)] {
+        &self.truncated_goals
+    }
+
+    /// Resets all counters to zero, and forgets the truncated goals.
+    /// Cached tables are unaffected.
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+        self.truncated_goals.clear();
+        self.truncated_goals_set.clear();
+    }
+
     // Gets the next clock TimeStamp. This will never decrease.
     pub(crate) fn increment_clock(&mut self) -> TimeStamp {
         self.clock.increment();
@@ -57,6 +311,7 @@ impl<C: Context> Forest<C> {
             context,
             table,
             answer,
+            undefined: None,
         }
     }
 
@@ -69,7 +324,11 @@ impl<C: Context> Forest<C> {
         goal: &C::UCanonicalGoalInEnvironment,
         should_continue: impl Fn() -> bool,
     ) -> Option<C::Solution> {
-        context.make_solution(&goal, self.iter_answers(context, goal), should_continue)
+        let solution =
+            context.make_solution(&goal, self.iter_answers(context, goal), should_continue);
+        self.publish_completed_tables();
+        self.evict_tables();
+        solution
     }
 
     /// Solves a given goal, producing the solution. This will do only
@@ -81,25 +340,62 @@ impl<C: Context> Forest<C> {
         context: &impl ContextOps<C>,
         goal: &C::UCanonicalGoalInEnvironment,
         mut f: impl FnMut(SubstitutionResult<C::CanonicalConstrainedSubst>, bool) -> bool,
+    ) -> bool {
+        self.solve_multiple_with_source(context, goal, |subst, _, has_next| f(subst, has_next))
+    }
+
+    /// Like `solve_multiple`, but along with each solution, `f` also
+    /// gets the source of the program clause it was derived with (see
+    /// `Answer::source`).
+    pub fn solve_multiple_with_source(
+        &mut self,
+        context: &impl ContextOps<C>,
+        goal: &C::UCanonicalGoalInEnvironment,
+        f: impl FnMut(
+            SubstitutionResult<C::CanonicalConstrainedSubst>,
+            Option<C::ClauseSource>,
+            bool,
+        ) -> bool,
+    ) -> bool {
+        let result = self.solve_multiple_inner(context, goal, f);
+        self.publish_completed_tables();
+        self.evict_tables();
+        result
+    }
+
+    fn solve_multiple_inner(
+        &mut self,
+        context: &impl ContextOps<C>,
+        goal: &C::UCanonicalGoalInEnvironment,
+        mut f: impl FnMut(
+            SubstitutionResult<C::CanonicalConstrainedSubst>,
+            Option<C::ClauseSource>,
+            bool,
+        ) -> bool,
     ) -> bool {
         let mut answers = self.iter_answers(context, goal);
         loop {
-            let subst = match answers.next_answer(|| true) {
+            let (subst, source) = match answers.next_answer(|| true) {
                 AnswerResult::Answer(answer) => {
-                    if !answer.ambiguous {
+                    let source = answer.source.clone();
+                    let subst = if answer.undefined.is_some() {
+                        SubstitutionResult::Undefined(context.constrained_subst_from_answer(answer))
+                    } else if !answer.ambiguous {
                         SubstitutionResult::Definite(context.constrained_subst_from_answer(answer))
                     } else {
                         SubstitutionResult::Ambiguous(context.constrained_subst_from_answer(answer))
-                    }
+                    };
+                    (subst, source)
                 }
-                AnswerResult::Floundered => SubstitutionResult::Floundered,
+                AnswerResult::Floundered => (SubstitutionResult::Floundered, None),
                 AnswerResult::NoMoreSolutions => {
                     return true;
                 }
                 AnswerResult::QuantumExceeded => continue,
             };
 
-            if !f(subst, !answers.peek_answer(|| true).is_no_more_solutions()) {
+            let has_next = !answers.peek_answer(|| true).is_no_more_solutions();
+            if !f(subst, source, has_next) {
                 return false;
             }
         }
@@ -110,6 +406,9 @@ impl<C: Context> Forest<C> {
 pub enum SubstitutionResult<S> {
     Definite(S),
     Ambiguous(S),
+    /// Undefined under the well-founded semantics (see
+    /// `CompleteAnswer::undefined`).
+    Undefined(S),
     Floundered,
 }
 
@@ -118,6 +417,7 @@ impl<S> SubstitutionResult<S> {
         match self {
             SubstitutionResult::Definite(subst) => SubstitutionResult::Definite(subst),
             SubstitutionResult::Ambiguous(subst) => SubstitutionResult::Ambiguous(subst),
+            SubstitutionResult::Undefined(subst) => SubstitutionResult::Undefined(subst),
             SubstitutionResult::Floundered => SubstitutionResult::Floundered,
         }
     }
@@ -125,6 +425,7 @@ impl<S> SubstitutionResult<S> {
         match self {
             SubstitutionResult::Definite(subst) => SubstitutionResult::Definite(f(subst)),
             SubstitutionResult::Ambiguous(subst) => SubstitutionResult::Ambiguous(f(subst)),
+            SubstitutionResult::Undefined(subst) => SubstitutionResult::Undefined(f(subst)),
             SubstitutionResult::Floundered => SubstitutionResult::Floundered,
         }
     }
@@ -135,6 +436,7 @@ impl<S: Display> Display for SubstitutionResult<S> {
         match self {
             SubstitutionResult::Definite(subst) => write!(fmt, "{}", subst),
             SubstitutionResult::Ambiguous(subst) => write!(fmt, "Ambiguous({})", subst),
+            SubstitutionResult::Undefined(subst) => write!(fmt, "Undefined({})", subst),
             SubstitutionResult::Floundered => write!(fmt, "Floundered"),
         }
     }
@@ -145,6 +447,49 @@ struct ForestSolver<'me, C: Context, CO: ContextOps<C>> {
     context: &'me CO,
     table: TableIndex,
     answer: AnswerIndex,
+
+    /// The undefined answers that remain to be returned, once all
+    /// other answers have been.
+    undefined: Option<VecDeque<CompleteAnswer<C>>>,
+}
+
+impl<'me, C: Context, CO: ContextOps<C>> ForestSolver<'me, C, CO> {
+    /// Returns the answers of the (completely evaluated) table that
+    /// are undefined: those that are still conditional on delayed
+    /// negative subgoals, without an unconditional answer with the
+    /// same substitution.
+    fn undefined_answers(&self) -> VecDeque<CompleteAnswer<C>> {
+        let table = &self.forest.tables[self.table];
+        let num_universes = C::num_universes(&table.table_goal);
+        let unconditional: FxHashSet<_> = table
+            .answers()
+            .filter(|answer| !C::has_delayed_subgoals(&answer.subst))
+            .map(|answer| {
+                C::canonical_constrained_subst_from_canonical_constrained_answer(&answer.subst)
+            })
+            .collect();
+        table
+            .answers()
+            .filter(|answer| C::has_delayed_subgoals(&answer.subst))
+            .filter(|answer| {
+                let (_, _, _, delayed_subgoals) = self
+                    .context
+                    .instantiate_answer_subst(num_universes, &answer.subst);
+                delayed_subgoals
+                    .iter()
+                    .all(|goal| self.context.negated_goal(goal).is_some())
+            })
+            .map(|answer| CompleteAnswer {
+                subst: C::canonical_constrained_subst_from_canonical_constrained_answer(
+                    &answer.subst,
+                ),
+                ambiguous: answer.ambiguous,
+                undefined: Some(answer.subst.clone()),
+                source: answer.source.clone(),
+            })
+            .filter(|answer| !unconditional.contains(&answer.subst))
+            .collect()
+    }
 }
 
 impl<'me, C: Context, CO: ContextOps<C>> AnswerStream<C> for ForestSolver<'me, C, CO> {
@@ -169,21 +514,33 @@ impl<'me, C: Context, CO: ContextOps<C>> AnswerStream<C> for ForestSolver<'me, C
                 }
 
                 Err(RootSearchFail::NoMoreSolutions) => {
-                    return AnswerResult::NoMoreSolutions;
+                    // Answers that are conditional on delayed negative
+                    // subgoals come last, as their truth is only known
+                    // once all other answers have been found.
+                    if self.undefined.is_none() {
+                        self.undefined = Some(self.undefined_answers());
+                    }
+                    return match self.undefined.as_ref().unwrap().front() {
+                        Some(answer) => AnswerResult::Answer(answer.clone()),
+                        None => AnswerResult::NoMoreSolutions,
+                    };
                 }
 
                 Err(RootSearchFail::QuantumExceeded) => {
+                    self.forest.stats.quantum_exceeded += 1;
                     if !should_continue() {
                         return AnswerResult::QuantumExceeded;
                     }
                 }
 
                 Err(RootSearchFail::NegativeCycle) => {
-                    // Negative cycles *ought* to be avoided by construction. Hence panic
-                    // if we find one, as that likely indicates a problem in the chalk-solve
-                    // lowering rules. (In principle, we could propagate this error out,
-                    // and let chalk-solve do the asserting, but that seemed like it would
-                    // complicate the function signature more than it's worth.)
+                    // Negative cycles are broken by delaying the negative subgoals
+                    // in them (yielding undefined answers), so we only get here if
+                    // there was nothing to delay. Hence panic, as that likely
+                    // indicates a bug in the engine. (In principle, we could propagate
+                    // this error out, and let chalk-solve do the asserting, but that
+                    // seemed like it would complicate the function signature more than
+                    // it's worth.)
                     panic!("negative cycle was detected");
                 }
             }
@@ -192,11 +549,27 @@ impl<'me, C: Context, CO: ContextOps<C>> AnswerStream<C> for ForestSolver<'me, C
 
     fn next_answer(&mut self, should_continue: impl Fn() -> bool) -> AnswerResult<C> {
         let answer = self.peek_answer(should_continue);
-        self.answer.increment();
+        match &mut self.undefined {
+            Some(undefined) => {
+                undefined.pop_front();
+            }
+            None => self.answer.increment(),
+        }
         answer
     }
 
     fn any_future_answer(&self, test: impl Fn(&C::InferenceNormalizedSubst) -> bool) -> bool {
-        self.forest.any_future_answer(self.table, self.answer, test)
+        if let Some(undefined) = &self.undefined {
+            return undefined.iter().any(|answer| {
+                let subst = answer.undefined.as_ref().unwrap();
+                test(C::inference_normalized_subst_from_subst(subst))
+            });
+        }
+        self.forest
+            .any_future_answer(self.table, self.answer, &test)
+            || self.forest.tables[self.table]
+                .answers()
+                .filter(|answer| C::has_delayed_subgoals(&answer.subst))
+                .any(|answer| test(C::inference_normalized_subst_from_subst(&answer.subst)))
     }
 }
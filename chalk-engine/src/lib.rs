@@ -60,11 +60,13 @@ use crate::context::Context;
 use std::cmp::min;
 use std::usize;
 
+pub mod completed;
 pub mod context;
 mod derived;
 pub mod forest;
 pub mod hh;
 mod logic;
+pub mod schedule;
 mod simplify;
 mod stack;
 mod strand;
@@ -197,6 +199,10 @@ pub struct Answer<C: Context> {
     /// nor disproven. This could be the size of the answer exceeded
     /// `max_size` or because of a negative loop (e.g., `P :- not { P }`).
     pub ambiguous: bool,
+
+    /// The source of the program clause the answer was derived with,
+    /// if the goal of the table is a domain goal.
+    pub source: Option<C::ClauseSource>,
 }
 
 #[derive(Clone, Debug)]
@@ -207,9 +213,20 @@ pub struct CompleteAnswer<C: Context> {
     pub subst: C::CanonicalConstrainedSubst,
 
     /// If this flag is set, then the answer could be neither proven
-    /// nor disproven. This could be the size of the answer exceeded
-    /// `max_size` or because of a negative loop (e.g., `P :- not { P }`).
+    /// nor disproven. This could be because the size of the answer
+    /// exceeded `max_size`.
     pub ambiguous: bool,
+
+    /// Set if the answer is *undefined* under the well-founded
+    /// semantics, because it depends on itself through negation (e.g.,
+    /// `P :- not { P }`). Contains the answer along with the residual
+    /// delayed subgoals: the `not { G }` goals whose truth could not be
+    /// decided.
+    pub undefined: Option<C::CanonicalAnswerSubst>,
+
+    /// The source of the program clause the answer was derived with;
+    /// see `Answer::source`.
+    pub source: Option<C::ClauseSource>,
 }
 
 /// Either `A` or `~A`, where `A` is a `Env |- Goal`.
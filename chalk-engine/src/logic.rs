@@ -1,7 +1,7 @@
 use crate::context::{
     Context, ContextOps, InferenceTable, ResolventOps, TruncateOps, UnificationOps,
 };
-use crate::forest::Forest;
+use crate::forest::{Forest, Truncated};
 use crate::hh::HhGoal;
 use crate::stack::{Stack, StackIndex};
 use crate::strand::{CanonicalStrand, SelectedSubgoal, Strand};
@@ -10,6 +10,8 @@ use crate::{
     Answer, CompleteAnswer, ExClause, FlounderedSubgoal, Literal, Minimums, TableIndex, TimeStamp,
 };
 use chalk_base::results::{Floundered, NoSolution};
+use rustc_hash::FxHashSet;
+use tracing::{debug_span, info_span, instrument};
 
 type RootSearchResult<T> = Result<T, RootSearchFail>;
 
@@ -99,6 +101,8 @@ impl<C: Context> Forest<C> {
                         &answer.subst,
                     ),
                     ambiguous: answer.ambiguous,
+                    undefined: None,
+                    source: answer.source.clone(),
                 })
             }
             Err(err) => Err(err),
@@ -112,7 +116,7 @@ impl<C: Context> Forest<C> {
         mut test: impl FnMut(&C::InferenceNormalizedSubst) -> bool,
     ) -> bool {
         if let Some(answer) = self.tables[table].answer(answer) {
-            info!("answer cached = {:?}", answer);
+            info!(result = ?answer, "answer cached");
             return test(C::inference_normalized_subst_from_subst(&answer.subst));
         }
 
@@ -133,6 +137,8 @@ impl<C: Context> Forest<C> {
             ex_clause,
             selected_subgoal,
             last_pursued_time,
+            depth,
+            source,
         } = strand;
         Forest::canonicalize_strand_from(
             context,
@@ -140,6 +146,8 @@ impl<C: Context> Forest<C> {
             &ex_clause,
             selected_subgoal,
             last_pursued_time,
+            depth,
+            source,
         )
     }
 
@@ -149,12 +157,16 @@ impl<C: Context> Forest<C> {
         ex_clause: &ExClause<C>,
         selected_subgoal: Option<SelectedSubgoal<C>>,
         last_pursued_time: TimeStamp,
+        depth: usize,
+        source: Option<C::ClauseSource>,
     ) -> CanonicalStrand<C> {
         let canonical_ex_clause = infer.canonicalize_ex_clause(context.interner(), &ex_clause);
         CanonicalStrand {
             canonical_ex_clause,
             selected_subgoal,
             last_pursued_time,
+            depth,
+            source,
         }
     }
 
@@ -177,20 +189,19 @@ impl<C: Context> Forest<C> {
         infer: &mut dyn InferenceTable<C>,
         subgoal: &Literal<C>,
     ) -> Option<(TableIndex, C::UniverseMap)> {
-        debug_heading!("get_or_create_table_for_subgoal(subgoal={:?})", subgoal);
+        let _span = debug_span!("get_or_create_table_for_subgoal", goal = ?subgoal).entered();
 
         // Subgoal abstraction:
         let (ucanonical_subgoal, universe_map) = match subgoal {
             Literal::Positive(subgoal) => {
-                Forest::abstract_positive_literal(context, infer, subgoal)?
+                self.abstract_positive_literal(context, infer, subgoal)?
             }
             Literal::Negative(subgoal) => {
-                Forest::abstract_negative_literal(context, infer, subgoal)?
+                self.abstract_negative_literal(context, infer, subgoal)?
             }
         };
 
-        debug!("ucanonical_subgoal={:?}", ucanonical_subgoal);
-        debug!("universe_map={:?}", universe_map);
+        debug!(goal = ?ucanonical_subgoal, ?universe_map, "abstracted subgoal");
 
         let table = self.get_or_create_table_for_ucanonical_goal(context, ucanonical_subgoal);
 
@@ -204,25 +215,35 @@ impl<C: Context> Forest<C> {
     /// In terms of the NFTD paper, creating a new table corresponds
     /// to the *New Subgoal* step as well as the *Program Clause
     /// Resolution* steps.
+    #[instrument(level = "debug", skip(self, context))]
     pub(crate) fn get_or_create_table_for_ucanonical_goal(
         &mut self,
         context: &impl ContextOps<C>,
         goal: C::UCanonicalGoalInEnvironment,
     ) -> TableIndex {
-        debug_heading!("get_or_create_table_for_ucanonical_goal({:?})", goal);
-
         if let Some(table) = self.tables.index_of(&goal) {
-            debug!("found existing table {:?}", table);
+            debug!(table = ?table, "found existing table");
+            self.tables[table].last_used = self.clock;
             return table;
         }
 
-        info_heading!(
-            "creating new table {:?} and goal {:#?}",
-            self.tables.next_index(),
-            goal
-        );
-        let table = Self::build_table(context, self.tables.next_index(), goal);
-        self.tables.insert(table)
+        let _span =
+            info_span!("new_table", table = ?self.tables.next_index(), goal = ?goal).entered();
+        let completed = self.completed.as_ref().and_then(|c| c.get(&goal));
+        let table = if let Some(completed) = completed {
+            info!("copying completed table");
+            let coinductive_goal = context.is_coinductive(&goal);
+            self.stats.tables_reused += 1;
+            Table::from_completed(goal, coinductive_goal, &completed)
+        } else {
+            info!("creating new table");
+            self.stats.tables_created += 1;
+            Self::build_table(context, self.tables.next_index(), goal)
+        };
+        self.stats.strands_created += table.strands().count();
+        let index = self.tables.insert(table);
+        self.tables[index].last_used = self.clock;
+        index
     }
 
     /// When a table is first created, this function is invoked to
@@ -245,10 +266,15 @@ impl<C: Context> Forest<C> {
         let (mut infer, subst, environment, goal) = context.instantiate_ucanonical_goal(&goal);
         match context.into_hh_goal(goal) {
             HhGoal::DomainGoal(domain_goal) => {
-                match context.program_clauses(&environment, &domain_goal, &mut infer) {
+                match context.program_clauses(
+                    &environment,
+                    &domain_goal,
+                    &mut infer,
+                    &mut table.dependencies,
+                ) {
                     Ok(clauses) => {
                         for clause in clauses {
-                            info!("program clause = {:#?}", clause);
+                            info!(clause = ?clause, "program clause");
                             let mut infer = infer.clone();
                             if let Ok(resolvent) = infer.resolvent_clause(
                                 context.interner(),
@@ -257,12 +283,14 @@ impl<C: Context> Forest<C> {
                                 &subst,
                                 &clause,
                             ) {
-                                info!("pushing initial strand with ex-clause: {:#?}", &resolvent,);
+                                info!(clause = ?resolvent, "pushing initial strand");
                                 let strand = Strand {
                                     infer,
                                     ex_clause: resolvent,
                                     selected_subgoal: None,
                                     last_pursued_time: TimeStamp::default(),
+                                    depth: 0,
+                                    source: Some(context.clause_source(&clause)),
                                 };
                                 let canonical_strand = Self::canonicalize_strand(context, strand);
                                 table.enqueue_strand(canonical_strand);
@@ -270,7 +298,7 @@ impl<C: Context> Forest<C> {
                         }
                     }
                     Err(Floundered) => {
-                        debug!("Marking table {:?} as floundered!", table_idx);
+                        debug!(table = ?table_idx, "marking table as floundered");
                         table.mark_floundered();
                     }
                 }
@@ -289,14 +317,16 @@ impl<C: Context> Forest<C> {
                     Self::simplify_hh_goal(context, &mut infer, subst, environment, hh_goal)
                 {
                     info!(
-                        "pushing initial strand with ex-clause: {:#?}",
-                        infer.debug_ex_clause(context.interner(), &ex_clause),
+                        clause = ?infer.debug_ex_clause(context.interner(), &ex_clause),
+                        "pushing initial strand"
                     );
                     let strand = Strand {
                         infer,
                         ex_clause,
                         selected_subgoal: None,
                         last_pursued_time: TimeStamp::default(),
+                        depth: 0,
+                        source: None,
                     };
                     let canonical_strand = Self::canonicalize_strand(context, strand);
                     table.enqueue_strand(canonical_strand);
@@ -314,11 +344,14 @@ impl<C: Context> Forest<C> {
     /// of `subgoal`; but if the subgoal is getting too big, we return
     /// `None`, which causes the subgoal to flounder.
     fn abstract_positive_literal(
+        &mut self,
         context: &impl ContextOps<C>,
         infer: &mut dyn InferenceTable<C>,
         subgoal: &C::GoalInEnvironment,
     ) -> Option<(C::UCanonicalGoalInEnvironment, C::UniverseMap)> {
         if infer.goal_needs_truncation(context.interner(), subgoal) {
+            let (truncated_goal, _) = infer.fully_canonicalize_goal(context.interner(), subgoal);
+            self.record_truncation(truncated_goal, Truncated::Goal);
             None
         } else {
             Some(infer.fully_canonicalize_goal(context.interner(), subgoal))
@@ -333,6 +366,7 @@ impl<C: Context> Forest<C> {
     /// variables appear in `subgoal` (in which case the execution is
     /// said to "flounder").
     fn abstract_negative_literal(
+        &mut self,
         context: &impl ContextOps<C>,
         infer: &mut dyn InferenceTable<C>,
         subgoal: &C::GoalInEnvironment,
@@ -377,11 +411,29 @@ impl<C: Context> Forest<C> {
         let inverted_subgoal = infer.invert_goal(context.interner(), subgoal)?;
 
         if infer.goal_needs_truncation(context.interner(), &inverted_subgoal) {
+            let (truncated_goal, _) =
+                infer.fully_canonicalize_goal(context.interner(), &inverted_subgoal);
+            self.record_truncation(truncated_goal, Truncated::Goal);
             None
         } else {
             Some(infer.fully_canonicalize_goal(context.interner(), &inverted_subgoal))
         }
     }
+
+    /// Records that `goal` was too big to be solved, either because it
+    /// needed truncation itself or because one of its answers did (as
+    /// indicated by `truncated`).
+    pub(crate) fn record_truncation(
+        &mut self,
+        goal: C::UCanonicalGoalInEnvironment,
+        truncated: Truncated,
+    ) {
+        self.stats.truncations += 1;
+        let truncation = (goal, truncated);
+        if self.truncated_goals_set.insert(truncation.clone()) {
+            self.truncated_goals.push(truncation);
+        }
+    }
 }
 
 pub(crate) struct SolveState<'forest, C: Context, CO: ContextOps<C>> {
@@ -413,27 +465,23 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
     /// This function first attempts to fetch answer that is cached in
     /// the table. If none is found, then it will recursively search
     /// to find an answer.
+    #[instrument(
+        level = "info",
+        skip(self),
+        fields(goal = ?self.forest.tables[initial_table].table_goal)
+    )]
     fn ensure_root_answer(
         &mut self,
         initial_table: TableIndex,
         initial_answer: AnswerIndex,
     ) -> RootSearchResult<()> {
-        info_heading!(
-            "ensure_answer(table={:?}, answer={:?})",
-            initial_table,
-            initial_answer
-        );
-        info!(
-            "table goal = {:#?}",
-            self.forest.tables[initial_table].table_goal
-        );
         // Check if this table has floundered.
         if self.forest.tables[initial_table].is_floundered() {
             return Err(RootSearchFail::Floundered);
         }
         // Check for a tabled answer.
         if let Some(answer) = self.forest.tables[initial_table].answer(initial_answer) {
-            info!("answer cached = {:?}", answer);
+            info!(result = ?answer, "answer cached");
             return Ok(());
         }
 
@@ -459,14 +507,17 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
             // then all have. Otherwise, an answer to any strand would have provided an
             // answer for the table.
             let next_strand = self.stack.top().active_strand.take().or_else(|| {
+                let scheduler = &*self.forest.strand_scheduler;
                 self.forest.tables[table]
-                    .dequeue_next_strand_if(|strand| strand.last_pursued_time < clock)
+                    .dequeue_next_strand_if(scheduler, |strand| strand.last_pursued_time < clock)
                     .map(|canonical_strand| {
                         let num_universes = C::num_universes(&self.forest.tables[table].table_goal);
                         let CanonicalStrand {
                             canonical_ex_clause,
                             selected_subgoal,
                             last_pursued_time,
+                            depth,
+                            source,
                         } = canonical_strand;
                         let (infer, ex_clause) = self
                             .context
@@ -476,13 +527,15 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                             ex_clause,
                             selected_subgoal: selected_subgoal.clone(),
                             last_pursued_time,
+                            depth,
+                            source,
                         };
                         strand
                     })
             });
             match next_strand {
                 Some(mut strand) => {
-                    debug!("next strand: {:#?}", strand);
+                    debug!(?strand, "next strand");
 
                     strand.last_pursued_time = clock;
                     match self.select_subgoal(&mut strand) {
@@ -543,14 +596,34 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                 ex_clause: strand.ex_clause.clone(),
                 selected_subgoal: Some(next_subgoal),
                 last_pursued_time: strand.last_pursued_time.clone(),
+                depth: strand.depth,
+                source: strand.source.clone(),
             };
             let table = self.stack.top().table;
             let canonical_next_strand = Forest::canonicalize_strand(self.context, next_strand);
+            self.forest.stats.strands_created += 1;
             self.forest.tables[table].enqueue_strand(canonical_next_strand);
         }
 
+        // A conditional answer (one with delayed subgoals) to a negative
+        // subgoal doesn't decide it: there may still be an unconditional
+        // answer. Move on to the next answer.
+        if let Literal::Negative(_) = strand.ex_clause.subgoals[selected_subgoal.subgoal_index] {
+            let answer = self.forest.answer(
+                selected_subgoal.subgoal_table,
+                selected_subgoal.answer_index,
+            );
+            if C::has_delayed_subgoals(&answer.subst) {
+                debug!("conditional answer to negative literal");
+                let selected_subgoal = strand.selected_subgoal.as_mut().unwrap();
+                selected_subgoal.answer_index.increment();
+                return Ok(());
+            }
+        }
+
         // Deselect and remove the selected subgoal, now that we have an answer for it.
         let selected_subgoal = strand.selected_subgoal.take().unwrap();
+        strand.depth += 1;
         let subgoal = strand
             .ex_clause
             .subgoals
@@ -584,6 +657,8 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                             ex_clause,
                             selected_subgoal: _,
                             last_pursued_time: _,
+                            depth: _,
+                            source: _,
                         } = strand;
 
                         // If the answer had was ambiguous, we have to
@@ -628,15 +703,6 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                 // "conditional" (maybe true, maybe not).
                 let answer = self.forest.answer(subgoal_table, answer_index);
 
-                // By construction, we do not expect negative subgoals
-                // to have delayed subgoals. This is because we do not
-                // need to permit `not { L }` where `L` is a
-                // coinductive goal. We could improve this if needed,
-                // but it keeps things simple.
-                if C::has_delayed_subgoals(&answer.subst) {
-                    panic!("Negative subgoal had delayed_subgoals");
-                }
-
                 if !answer.ambiguous {
                     // We want to disproval the subgoal, but we
                     // have an unconditional answer for the subgoal,
@@ -820,8 +886,9 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         } = *strand.selected_subgoal.as_ref().unwrap();
 
         debug!(
-            "table selection {:?} with goal: {:#?}",
-            subgoal_table, self.forest.tables[subgoal_table].table_goal
+            table = ?subgoal_table,
+            goal = ?self.forest.tables[subgoal_table].table_goal,
+            "table selection"
         );
 
         // This is checked inside select_subgoal
@@ -829,7 +896,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
 
         // Check for a tabled answer.
         if let Some(answer) = self.forest.tables[subgoal_table].answer(answer_index) {
-            info!("answer cached = {:?}", answer);
+            info!(result = ?answer, "answer cached");
 
             // There was a previous answer available for this table
             // We need to check if we can merge it into the current `Strand`.
@@ -857,7 +924,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         // Next, check if the table is already active. If so, then we
         // have a recursive attempt.
         if let Some(cyclic_depth) = self.stack.is_active(subgoal_table) {
-            info!("cycle detected at depth {:?}", cyclic_depth);
+            info!(depth = ?cyclic_depth, "cycle detected");
             let minimums = Minimums {
                 positive: self.stack[cyclic_depth].clock,
                 negative: TimeStamp::MAX,
@@ -912,6 +979,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
 
                         let answer = self.forest.answer(table, answer_index);
                         if let Some(strand) = self.create_refinement_strand(table, answer) {
+                            self.forest.stats.strands_created += 1;
                             self.forest.tables[table].enqueue_strand(strand);
                         }
 
@@ -973,7 +1041,10 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
 
         let delayed_subgoals = delayed_subgoals
             .into_iter()
-            .map(Literal::Positive)
+            .map(|goal| match self.context.negated_goal(&goal) {
+                Some(goal) => Literal::Negative(goal),
+                None => Literal::Positive(goal),
+            })
             .collect();
 
         let strand = Strand {
@@ -989,6 +1060,8 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
             },
             selected_subgoal: None,
             last_pursued_time: TimeStamp::default(),
+            depth: 0,
+            source: answer.source.clone(),
         };
 
         Some(Forest::canonicalize_strand(self.context, strand))
@@ -1007,7 +1080,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         loop {
             // This table is marked as floundered
             let table = self.stack.top().table;
-            debug!("Marking table {:?} as floundered!", table);
+            debug!(?table, "marking table as floundered");
             self.forest.tables[table].mark_floundered();
 
             let mut strand = match self.stack.pop_and_take_caller_strand() {
@@ -1073,16 +1146,27 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
 
                 // T' wanted there to be no answer from T, but none is forthcoming.
                 Literal::Negative(_) => {
-                    debug!("subgoal was proven because negative literal");
-
-                    // There is no solution for this strand. But, this
-                    // is what we want, so can remove this subgoal and
-                    // keep going.
-                    caller_strand
+                    let subgoal = caller_strand
                         .ex_clause
                         .subgoals
                         .remove(caller_selected_subgoal.subgoal_index);
 
+                    // If T only had conditional answers, we don't
+                    // know whether the subgoal holds, so delay it.
+                    // Otherwise there is no solution for T. But, this
+                    // is what we want, so we can just keep going.
+                    if self.forest.tables[caller_selected_subgoal.subgoal_table]
+                        .has_conditional_answers()
+                    {
+                        debug!("negative literal is undecided, delaying it");
+                        if let Literal::Negative(goal) = subgoal {
+                            let negated = self.context.negate_goal(&goal);
+                            caller_strand.ex_clause.delayed_subgoals.push(negated);
+                        }
+                    } else {
+                        debug!("subgoal was proven because negative literal");
+                    }
+
                     // This strand is still active, so continue
                     Ok(())
                 }
@@ -1095,7 +1179,17 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
             debug!("cycle with no new answers");
 
             if cyclic_minimums.negative < TimeStamp::MAX {
-                // This is a negative cycle.
+                // This is a negative cycle: the tables in it depend on
+                // each other through negation, and none of them can
+                // make progress. Under the well-founded semantics,
+                // their answers may be *undefined*; to find out, delay
+                // the negative subgoals and keep going.
+                let table = self.stack.top().table;
+                if self.delay_negative_subgoals(table, &mut FxHashSet::default()) {
+                    self.stack.top().cyclic_minimums = Minimums::MAX;
+                    return Ok(());
+                }
+
                 self.unwind_stack();
                 return Err(RootSearchFail::NegativeCycle);
             }
@@ -1175,6 +1269,64 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         }
     }
 
+    /// Invoked after we have determined that every strand in `table`
+    /// encounters a negative cycle. Delays the negative subgoal that
+    /// each strand of `table` -- or of the tables that its strands
+    /// depend on -- is waiting for: it is replaced by a delayed `not {
+    /// G }` goal, and the strand can go on to produce a *conditional*
+    /// answer. Once the root table has been evaluated,
+    /// refinement strands find out whether the delayed subgoals hold
+    /// after all (see `create_refinement_strand`).
+    ///
+    /// Returns false if there was no negative subgoal to delay.
+    fn delay_negative_subgoals(
+        &mut self,
+        table: TableIndex,
+        visited: &mut FxHashSet<TableIndex>,
+    ) -> bool {
+        if !visited.insert(table) {
+            return false;
+        }
+
+        let num_universes = C::num_universes(&self.forest.tables[table].table_goal);
+        let mut delayed = false;
+        for strand in self.forest.tables[table].take_strands() {
+            let subgoal_index = match &strand.selected_subgoal {
+                Some(selected_subgoal) => selected_subgoal.subgoal_index,
+                None => {
+                    self.forest.tables[table].enqueue_strand(strand);
+                    continue;
+                }
+            };
+            let (infer, mut ex_clause) = self
+                .context
+                .instantiate_ex_clause(num_universes, &strand.canonical_ex_clause);
+            let subgoal_table = strand.selected_subgoal.as_ref().unwrap().subgoal_table;
+            delayed |= self.delay_negative_subgoals(subgoal_table, visited);
+            match ex_clause.subgoals.remove(subgoal_index) {
+                Literal::Negative(goal) => {
+                    let negated = self.context.negate_goal(&goal);
+                    ex_clause.delayed_subgoals.push(negated);
+                    let strand = Strand {
+                        infer,
+                        ex_clause,
+                        selected_subgoal: None,
+                        last_pursued_time: TimeStamp::default(),
+                        depth: strand.depth,
+                        source: strand.source.clone(),
+                    };
+                    let canonical_strand = Forest::canonicalize_strand(self.context, strand);
+                    self.forest.tables[table].enqueue_strand(canonical_strand);
+                    delayed = true;
+                }
+                Literal::Positive(_) => {
+                    self.forest.tables[table].enqueue_strand(strand);
+                }
+            }
+        }
+        delayed
+    }
+
     /// Invoked after we have determined that every strand in `table`
     /// encounters a cycle; `strands` is the set of strands (which
     /// have been moved out of the table). This method then
@@ -1187,6 +1339,8 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                 canonical_ex_clause,
                 selected_subgoal,
                 last_pursued_time: _,
+                depth: _,
+                source: _,
             } = strand;
             let selected_subgoal = selected_subgoal.unwrap_or_else(|| {
                 panic!(
@@ -1229,6 +1383,10 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                     &strand.ex_clause.subgoals[subgoal_index],
                 ) {
                     Some((subgoal_table, universe_map)) => {
+                        let table = self.stack.top().table;
+                        self.forest.tables[table]
+                            .subgoal_tables
+                            .insert(subgoal_table);
                         strand.selected_subgoal = Some(SelectedSubgoal {
                             subgoal_index,
                             subgoal_table,
@@ -1288,6 +1446,8 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
                 },
             selected_subgoal: _,
             last_pursued_time: _,
+            depth: _,
+            source,
         } = strand;
         assert!(subgoals.is_empty());
         assert!(floundered_subgoals.is_empty());
@@ -1310,16 +1470,28 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         // down to "it works as we expect for the current tests". And, we likely don't
         // even *need* the added complexity just for potentially more answers.
         if infer.answer_needs_truncation(self.context.interner(), &subst) {
+            info!(table = ?table, "answer needs truncation; marking table as floundered");
+            let table_goal = self.forest.tables[table].table_goal.clone();
+            self.forest.record_truncation(table_goal, Truncated::Answer);
             self.forest.tables[table].mark_floundered();
             return None;
         }
 
         let table_goal = &self.forest.tables[table].table_goal;
 
+        // Delayed negative subgoals are never trivial self-cycles, so
+        // they are kept.
+        let has_negative_delays = delayed_subgoals
+            .iter()
+            .any(|delayed_subgoal| self.context.negated_goal(delayed_subgoal).is_some());
+
         //FIXME: Avoid double canonicalization
         let filtered_delayed_subgoals = delayed_subgoals
             .into_iter()
             .filter(|delayed_subgoal| {
+                if self.context.negated_goal(delayed_subgoal).is_some() {
+                    return true;
+                }
                 let (canonicalized, _) =
                     infer.fully_canonicalize_goal(self.context.interner(), delayed_subgoal);
                 *table_goal != canonicalized
@@ -1332,9 +1504,13 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
             constraints,
             filtered_delayed_subgoals,
         );
-        debug!("answer: table={:?}, subst={:?}", table, subst);
+        debug!(table = ?table, result = ?subst, "answer");
 
-        let answer = Answer { subst, ambiguous };
+        let answer = Answer {
+            subst,
+            ambiguous,
+            source,
+        };
 
         // A "trivial" answer is one that is 'just true for all cases'
         // -- in other words, it gives no information back to the
@@ -1394,8 +1570,12 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         // of proving things from the environment (though the latter
         // is a *bit* suspect; e.g., those things in the environment
         // must be backed by an impl *eventually*).
+        // An answer that depends on delayed negative subgoals may turn
+        // out to be undefined, so it mustn't cut off the strands that
+        // could prove it outright.
         let is_trivial_answer = {
             !answer.ambiguous
+                && !has_negative_delays
                 && self
                     .context
                     .is_trivial_substitution(&self.forest.tables[table].table_goal, &answer.subst)
@@ -1403,6 +1583,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
         };
 
         if let Some(answer_index) = self.forest.tables[table].push_answer(answer) {
+            self.forest.stats.answers += 1;
             if is_trivial_answer {
                 self.forest.tables[table].take_strands();
             }
@@ -1415,7 +1596,7 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
     }
 
     fn reconsider_floundered_subgoals(&mut self, ex_clause: &mut ExClause<impl Context>) {
-        info!("reconsider_floundered_subgoals(ex_clause={:#?})", ex_clause,);
+        info!(clause = ?ex_clause, "reconsider_floundered_subgoals");
         let ExClause {
             answer_time,
             subgoals,
@@ -1434,18 +1615,19 @@ impl<'forest, C: Context + 'forest, CO: ContextOps<C> + 'forest> SolveState<'for
     /// subgoal list and adds it to the strand's floundered subgoal
     /// list.
     fn flounder_subgoal(&self, ex_clause: &mut ExClause<impl Context>, subgoal_index: usize) {
-        info_heading!(
-            "flounder_subgoal(answer_time={:?}, subgoal={:?})",
-            ex_clause.answer_time,
-            ex_clause.subgoals[subgoal_index],
-        );
+        let _span = info_span!(
+            "flounder_subgoal",
+            answer_time = ?ex_clause.answer_time,
+            goal = ?ex_clause.subgoals[subgoal_index],
+        )
+        .entered();
         let floundered_time = ex_clause.answer_time;
         let floundered_literal = ex_clause.subgoals.remove(subgoal_index);
         ex_clause.floundered_subgoals.push(FlounderedSubgoal {
             floundered_literal,
             floundered_time,
         });
-        debug!("flounder_subgoal: ex_clause={:#?}", ex_clause);
+        debug!(clause = ?ex_clause, "floundered subgoal");
     }
 
     /// True if all the tables on the stack starting from `depth` and
@@ -0,0 +1,78 @@
+//! Strategies for picking which strand of a table to pursue next.
+//!
+//! Whenever the solver needs a new strand for a table, it asks the
+//! forest's `StrandScheduler` to pick one among the strands that have
+//! not been pursued yet at the current depth. The choice affects how
+//! quickly answers are found, and in which order, but never which
+//! answers are found.
+
+use std::fmt::Debug;
+
+/// What a `StrandScheduler` knows about a strand waiting in a table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StrandInfo {
+    /// The number of subgoals that the strand (or the strands it was
+    /// forked from) has solved so far.
+    pub depth: usize,
+
+    /// The number of subgoals left to solve.
+    pub subgoals: usize,
+}
+
+/// Picks the next strand of a table to pursue.
+pub trait StrandScheduler: Debug + Send + Sync {
+    /// Returns the index of the strand to pursue next among `strands`,
+    /// which are listed in the order in which they were enqueued.
+    /// `strands` is never empty.
+    fn select_strand(&self, strands: &[StrandInfo]) -> usize;
+
+    /// Returns true if `select_strand` always picks the first strand. The
+    /// engine then takes the strand at the front of the queue without
+    /// listing the others, which saves work on every strand it pursues.
+    fn always_selects_first(&self) -> bool {
+        false
+    }
+}
+
+/// The strand schedulers that come with the engine.
+#[derive(Copy, Clone, Debug, Default, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub enum StrandSchedule {
+    /// Pursues strands in the order in which they were enqueued, so
+    /// that the program clauses of a table are tried in order and each
+    /// strand is followed as deep as it goes before the next one is
+    /// started.
+    #[default]
+    DepthFirst,
+
+    /// Pursues the strand that has solved the fewest subgoals, so that
+    /// all the alternatives of a table make progress in lockstep.
+    BreadthFirst,
+
+    /// Pursues the strand with the fewest subgoals left, so that facts
+    /// and impls without where clauses are tried first.
+    CostBased,
+}
+
+impl StrandScheduler for StrandSchedule {
+    fn select_strand(&self, strands: &[StrandInfo]) -> usize {
+        // `min_by_key` returns the first of several minimal elements, so
+        // ties are broken in favor of the oldest strand.
+        let cheapest = |key: fn(&StrandInfo) -> usize| {
+            strands
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, info)| key(info))
+                .map(|(index, _)| index)
+                .unwrap()
+        };
+        match self {
+            StrandSchedule::DepthFirst => 0,
+            StrandSchedule::BreadthFirst => cheapest(|info| info.depth),
+            StrandSchedule::CostBased => cheapest(|info| info.subgoals),
+        }
+    }
+
+    fn always_selects_first(&self) -> bool {
+        *self == StrandSchedule::DepthFirst
+    }
+}
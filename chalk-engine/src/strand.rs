@@ -1,4 +1,5 @@
 use crate::context::Context;
+use crate::schedule::StrandInfo;
 use crate::table::AnswerIndex;
 use crate::{ExClause, TableIndex, TimeStamp};
 use std::fmt::{Debug, Error, Formatter};
@@ -11,6 +12,13 @@ pub(crate) struct CanonicalStrand<C: Context> {
     pub(crate) selected_subgoal: Option<SelectedSubgoal<C>>,
 
     pub(crate) last_pursued_time: TimeStamp,
+
+    /// The number of subgoals solved so far; see `StrandInfo`.
+    pub(crate) depth: usize,
+
+    /// The source of the program clause this strand was created from,
+    /// if any; it becomes the source of the answer of the strand.
+    pub(crate) source: Option<C::ClauseSource>,
 }
 
 pub(crate) struct Strand<C: Context> {
@@ -22,6 +30,13 @@ pub(crate) struct Strand<C: Context> {
     pub(crate) selected_subgoal: Option<SelectedSubgoal<C>>,
 
     pub(crate) last_pursued_time: TimeStamp,
+
+    /// The number of subgoals solved so far; see `StrandInfo`.
+    pub(crate) depth: usize,
+
+    /// The source of the program clause this strand was created from,
+    /// if any; it becomes the source of the answer of the strand.
+    pub(crate) source: Option<C::ClauseSource>,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +55,15 @@ pub(crate) struct SelectedSubgoal<C: Context> {
     pub(crate) universe_map: C::UniverseMap,
 }
 
+impl<C: Context> CanonicalStrand<C> {
+    pub(crate) fn info(&self) -> StrandInfo {
+        StrandInfo {
+            depth: self.depth,
+            subgoals: C::num_subgoals(&self.canonical_ex_clause),
+        }
+    }
+}
+
 impl<C: Context> Debug for Strand<C> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
         fmt.debug_struct("Strand")
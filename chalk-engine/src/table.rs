@@ -1,10 +1,13 @@
+use crate::completed::CompletedTable;
 use crate::context::Context;
+use crate::schedule::{StrandInfo, StrandScheduler};
 use crate::strand::CanonicalStrand;
-use crate::Answer;
-use rustc_hash::FxHashMap;
+use crate::{Answer, TableIndex, TimeStamp};
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::collections::hash_map::Entry;
 use std::collections::VecDeque;
 use std::mem;
+use tracing::debug_span;
 
 pub(crate) struct Table<C: Context> {
     /// The goal this table is trying to solve (also the key to look
@@ -38,6 +41,23 @@ pub(crate) struct Table<C: Context> {
     /// Stores the active strands that we can "pull on" to find more
     /// answers.
     strands: VecDeque<CanonicalStrand<C>>,
+
+    /// True if this table was copied from, or has been published to,
+    /// a shared `CompletedTables` store.
+    pub(crate) published: bool,
+
+    /// The value of the forest's clock when this table was last looked
+    /// up; used to evict the least recently used tables first.
+    pub(crate) last_used: TimeStamp,
+
+    /// The items of the program that the program clauses of this table
+    /// were derived from.
+    pub(crate) dependencies: FxHashSet<C::ItemId>,
+
+    /// The tables for the subgoals selected by the strands of this
+    /// table. The answers of this table may depend on theirs, and so
+    /// on the items that those tables depend on.
+    pub(crate) subgoal_tables: FxHashSet<TableIndex>,
 }
 
 index_struct! {
@@ -58,9 +78,61 @@ impl<C: Context> Table<C> {
             floundered: false,
             answers_hash: FxHashMap::default(),
             strands: VecDeque::new(),
+            published: false,
+            last_used: TimeStamp::default(),
+            dependencies: FxHashSet::default(),
+            subgoal_tables: FxHashSet::default(),
+        }
+    }
+
+    /// Creates a table from the final state of a table that was
+    /// completed by some other forest.
+    pub(crate) fn from_completed(
+        table_goal: C::UCanonicalGoalInEnvironment,
+        coinductive_goal: bool,
+        completed: &CompletedTable<C>,
+    ) -> Table<C> {
+        let answers_hash = completed
+            .answers
+            .iter()
+            .map(|answer| (answer.subst.clone(), answer.ambiguous))
+            .collect();
+        Table {
+            table_goal,
+            coinductive_goal,
+            floundered: completed.floundered,
+            answers: completed.answers.clone(),
+            answers_hash,
+            strands: VecDeque::new(),
+            published: true,
+            last_used: TimeStamp::default(),
+            dependencies: completed.dependencies.clone(),
+            subgoal_tables: FxHashSet::default(),
+        }
+    }
+
+    /// Returns the final state of this table, which must be complete.
+    /// `dependencies` are the items that the table depends on,
+    /// including those of its subgoal tables.
+    pub(crate) fn to_completed(&self, dependencies: FxHashSet<C::ItemId>) -> CompletedTable<C> {
+        assert!(self.is_complete());
+        CompletedTable {
+            floundered: self.floundered,
+            answers: self.answers.clone(),
+            dependencies,
         }
     }
 
+    /// A table is complete once there are no strands left to pursue,
+    /// at which point no new answers can be found.
+    ///
+    /// Note that while a table is being solved, its active strand is
+    /// removed from the queue; so this is only meaningful when no
+    /// search is in progress.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.strands.is_empty()
+    }
+
     /// Push a strand to the back of the queue of strands to be processed.
     pub(crate) fn enqueue_strand(&mut self, strand: CanonicalStrand<C>) {
         self.strands.push_back(strand);
@@ -78,20 +150,36 @@ impl<C: Context> Table<C> {
         mem::replace(&mut self.strands, VecDeque::new())
     }
 
-    /// Remove the next strand from the queue as long as it meets the
-    /// given criteria.
+    /// Remove the strand that `scheduler` picks among the strands at
+    /// the front of the queue that meet the given criteria.
     pub(crate) fn dequeue_next_strand_if(
         &mut self,
+        scheduler: &dyn StrandScheduler,
         test: impl Fn(&CanonicalStrand<C>) -> bool,
     ) -> Option<CanonicalStrand<C>> {
-        let strand = self.strands.pop_front();
-        if let Some(strand) = strand {
-            if test(&strand) {
-                return Some(strand);
+        if scheduler.always_selects_first() {
+            let strand = self.strands.pop_front();
+            if let Some(strand) = strand {
+                if test(&strand) {
+                    return Some(strand);
+                }
+                self.strands.push_front(strand);
             }
-            self.strands.push_front(strand);
+            return None;
+        }
+
+        let candidates: Vec<StrandInfo> = self
+            .strands
+            .iter()
+            .take_while(|strand| test(strand))
+            .map(|strand| strand.info())
+            .collect();
+        if candidates.is_empty() {
+            return None;
         }
-        None
+        let index = scheduler.select_strand(&candidates);
+        assert!(index < candidates.len());
+        self.strands.remove(index)
     }
 
     /// Mark the table as floundered -- this also discards all pre-existing answers,
@@ -120,10 +208,10 @@ impl<C: Context> Table<C> {
     pub(super) fn push_answer(&mut self, answer: Answer<C>) -> Option<AnswerIndex> {
         assert!(!self.floundered);
 
-        debug_heading!("push_answer(answer={:?})", answer);
+        let _span = debug_span!("push_answer", result = ?answer).entered();
         debug!(
-            "pre-existing entry: {:?}",
-            self.answers_hash.get(&answer.subst)
+            existing = ?self.answers_hash.get(&answer.subst),
+            "pre-existing entry"
         );
 
         let added = match self.answers_hash.entry(answer.subst.clone()) {
@@ -141,10 +229,7 @@ impl<C: Context> Table<C> {
             }
         };
 
-        info!(
-            "new answer to table with goal {:?}: answer={:?}",
-            self.table_goal, answer,
-        );
+        info!(goal = ?self.table_goal, result = ?answer, "new answer to table");
         if !added {
             return None;
         }
@@ -158,6 +243,21 @@ impl<C: Context> Table<C> {
         self.answers.get(index.value)
     }
 
+    /// True if some answer has delayed subgoals.
+    pub(crate) fn has_conditional_answers(&self) -> bool {
+        self.answers
+            .iter()
+            .any(|answer| C::has_delayed_subgoals(&answer.subst))
+    }
+
+    pub(crate) fn answers(&self) -> impl Iterator<Item = &Answer<C>> {
+        self.answers.iter()
+    }
+
+    pub(super) fn num_answers(&self) -> usize {
+        self.answers.len()
+    }
+
     pub(super) fn next_answer_index(&self) -> AnswerIndex {
         AnswerIndex::from(self.answers.len())
     }
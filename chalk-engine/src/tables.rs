@@ -1,7 +1,7 @@
 use crate::context::Context;
 use crate::table::Table;
 use crate::TableIndex;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::ops::{Index, IndexMut};
 
 /// See `Forest`.
@@ -10,8 +10,12 @@ pub(crate) struct Tables<C: Context> {
     table_indices: FxHashMap<C::UCanonicalGoalInEnvironment, TableIndex>,
 
     /// Table: as described above, stores the key information for each
-    /// tree in the forest.
-    tables: Vec<Table<C>>,
+    /// tree in the forest. Tables that have been evicted leave a
+    /// `None` behind, which is reused for the next table created.
+    tables: Vec<Option<Table<C>>>,
+
+    /// Indices of the evicted tables.
+    free: Vec<TableIndex>,
 }
 
 impl<C: Context> Tables<C> {
@@ -19,48 +23,108 @@ impl<C: Context> Tables<C> {
         Tables {
             table_indices: FxHashMap::default(),
             tables: Vec::default(),
+            free: Vec::default(),
         }
     }
 
     /// The index that will be given to the next table to be inserted.
     pub(super) fn next_index(&self) -> TableIndex {
-        TableIndex {
-            value: self.tables.len(),
+        match self.free.last() {
+            Some(&index) => index,
+            None => TableIndex {
+                value: self.tables.len(),
+            },
         }
     }
 
     pub(super) fn insert(&mut self, table: Table<C>) -> TableIndex {
         let goal = table.table_goal.clone();
-        let index = self.next_index();
-        self.tables.push(table);
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.tables[index.value] = Some(table);
+                index
+            }
+            None => {
+                self.tables.push(Some(table));
+                TableIndex {
+                    value: self.tables.len() - 1,
+                }
+            }
+        };
         self.table_indices.insert(goal, index);
         index
     }
 
+    /// Removes the table with the given index. The caller must ensure
+    /// that the index is no longer referenced anywhere, since it will
+    /// be reused for some other table.
+    pub(super) fn remove(&mut self, index: TableIndex) -> Table<C> {
+        let table = self.tables[index.value]
+            .take()
+            .expect("table was already evicted");
+        self.table_indices.remove(&table.table_goal);
+        self.free.push(index);
+        table
+    }
+
     pub(super) fn index_of(&self, literal: &C::UCanonicalGoalInEnvironment) -> Option<TableIndex> {
         self.table_indices.get(literal).cloned()
     }
+
+    /// Number of tables in the forest.
+    pub(super) fn len(&self) -> usize {
+        self.table_indices.len()
+    }
+
+    /// Returns the items that the table `index` depends on, either
+    /// directly or through (the subgoal tables of) its subgoal tables.
+    pub(super) fn transitive_dependencies(&self, index: TableIndex) -> FxHashSet<C::ItemId> {
+        let mut dependencies = FxHashSet::default();
+        let mut visited = FxHashSet::default();
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if !visited.insert(index) {
+                continue;
+            }
+            let table = &self[index];
+            dependencies.extend(table.dependencies.iter().cloned());
+            stack.extend(table.subgoal_tables.iter().cloned());
+        }
+        dependencies
+    }
+
+    /// Iterates over all tables in the forest, along with their index.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (TableIndex, &Table<C>)> {
+        self.tables
+            .iter()
+            .enumerate()
+            .filter_map(|(value, table)| Some((TableIndex { value }, table.as_ref()?)))
+    }
 }
 
 impl<C: Context> Index<TableIndex> for Tables<C> {
     type Output = Table<C>;
 
     fn index(&self, index: TableIndex) -> &Table<C> {
-        &self.tables[index.value]
+        self.tables[index.value]
+            .as_ref()
+            .expect("table was evicted")
     }
 }
 
 impl<C: Context> IndexMut<TableIndex> for Tables<C> {
     fn index_mut(&mut self, index: TableIndex) -> &mut Table<C> {
-        &mut self.tables[index.value]
+        self.tables[index.value]
+            .as_mut()
+            .expect("table was evicted")
     }
 }
 
 impl<'a, C: Context> IntoIterator for &'a mut Tables<C> {
-    type IntoIter = <&'a mut Vec<Table<C>> as IntoIterator>::IntoIter;
-    type Item = <&'a mut Vec<Table<C>> as IntoIterator>::Item;
+    type IntoIter = std::iter::Flatten<std::slice::IterMut<'a, Option<Table<C>>>>;
+    type Item = &'a mut Table<C>;
 
     fn into_iter(self) -> Self::IntoIter {
-        IntoIterator::into_iter(&mut self.tables)
+        self.tables.iter_mut().flatten()
     }
 }
@@ -11,18 +11,23 @@ use chalk_ir::{
     AdtId, AssocTypeId, Canonical, ConstrainedSubst, Environment, FnDefId, GenericArg, Goal,
     ImplId, InEnvironment, OpaqueTyId, ProgramClause, ProgramClauses, TraitId, UCanonical,
 };
+use chalk_solve::dependencies::ItemId;
 use chalk_solve::rust_ir::{
     AdtDatum, AssociatedTyDatum, AssociatedTyValue, AssociatedTyValueId, FnDefDatum, ImplDatum,
     OpaqueTyDatum, TraitDatum, WellKnownTrait,
 };
-use chalk_solve::{RustIrDatabase, Solution, SolverChoice};
-use salsa::Database;
+use chalk_solve::{RustIrDatabase, Solution, Solver, SolverChoice};
+use salsa::{Database, ParallelDatabase, Snapshot};
 use std::sync::Arc;
 
 #[salsa::database(Lowering)]
 #[derive(Debug, Default)]
 pub struct ChalkDatabase {
     runtime: salsa::Runtime<ChalkDatabase>,
+
+    /// Whether this is a snapshot (see `ParallelDatabase`), which may
+    /// solve goals in parallel with other snapshots.
+    is_snapshot: bool,
 }
 
 impl Database for ChalkDatabase {
@@ -31,11 +36,21 @@ impl Database for ChalkDatabase {
     }
 }
 
+impl ParallelDatabase for ChalkDatabase {
+    fn snapshot(&self) -> Snapshot<Self> {
+        Snapshot::new(ChalkDatabase {
+            runtime: self.runtime.snapshot(self),
+            is_snapshot: true,
+        })
+    }
+}
+
 impl ChalkDatabase {
     pub fn with(program_text: &str, solver_choice: SolverChoice) -> Self {
         let mut db = ChalkDatabase::default();
         db.set_program_text(Arc::new(program_text.to_string()));
         db.set_solver_choice(solver_choice);
+        db.set_future_compat_warnings(false);
         db
     }
 
@@ -53,9 +68,7 @@ impl ChalkDatabase {
         &self,
         goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
     ) -> Option<Solution<ChalkIr>> {
-        let solver = self.solver();
-        let solution = solver.lock().unwrap().solve(self, goal);
-        solution
+        self.with_solver(|solver| solver.solve(self, goal))
     }
 
     pub fn solve_multiple(
@@ -63,9 +76,19 @@ impl ChalkDatabase {
         goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
         f: impl FnMut(SubstitutionResult<Canonical<ConstrainedSubst<ChalkIr>>>, bool) -> bool,
     ) -> bool {
-        let solver = self.solver();
-        let solution = solver.lock().unwrap().solve_multiple(self, goal, f);
-        solution
+        self.with_solver(|solver| solver.solve_multiple(self, goal, f))
+    }
+
+    /// Runs `op` with the solver of this database, brought up to date
+    /// with the current program. Snapshots use a fork of the solver, so
+    /// that they can solve goals in parallel.
+    fn with_solver<R>(&self, op: impl FnOnce(&mut Solver<ChalkIr>) -> R) -> R {
+        let program = self.program_ir().unwrap();
+        if self.is_snapshot {
+            op(&mut self.solver().fork_for(&program))
+        } else {
+            self.solver().with_solver_for(&program, op)
+        }
     }
 }
 
@@ -147,4 +170,12 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
     fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> bool {
         self.program_ir().unwrap().is_object_safe(trait_id)
     }
+
+    fn may_dangle_parameters(&self, impl_id: ImplId<ChalkIr>) -> Vec<usize> {
+        self.program_ir().unwrap().may_dangle_parameters(impl_id)
+    }
+
+    fn item_name(&self, item: ItemId<ChalkIr>) -> Option<String> {
+        self.program_ir().unwrap().item_name(item)
+    }
 }
@@ -64,6 +64,8 @@ pub enum RustIrError {
     AutoTraitWhereClauses(Identifier),
     InvalidFundamentalTypesParameters(Identifier),
     NegativeImplAssociatedValues(Identifier),
+    NegativeReservationImpl(Identifier),
+    MayDangleOutsideDropImpl(Identifier),
     MissingAssociatedType(Identifier),
     IncorrectNumberOfTypeParameters {
         identifier: Identifier,
@@ -91,6 +93,8 @@ pub enum RustIrError {
         actual: Kind,
     },
     CannotApplyTypeParameter(Identifier),
+    CyclicSupertraits(Vec<Identifier>),
+    CyclicAssociatedTypes(Vec<(Identifier, Identifier)>),
 }
 
 impl std::fmt::Display for RustIrError {
@@ -132,6 +136,19 @@ impl std::fmt::Display for RustIrError {
                 "negative impl for trait `{}` cannot define associated values",
                 name
             ),
+            RustIrError::NegativeReservationImpl(name) => {
+                write!(
+                    f,
+                    "reservation impl for trait `{}` cannot be negative",
+                    name
+                )
+            }
+            RustIrError::MayDangleOutsideDropImpl(name) => write!(
+                f,
+                "`#[may_dangle]` is only allowed on the parameters of `Drop` impls, \
+                 not of impls of trait `{}`",
+                name
+            ),
             RustIrError::MissingAssociatedType(name) => {
                 write!(f, "no associated type `{}` defined in trait", name)
             }
@@ -183,6 +200,31 @@ impl std::fmt::Display for RustIrError {
             RustIrError::CannotApplyTypeParameter(name) => {
                 write!(f, "cannot apply type parameter `{}`", name)
             }
+            RustIrError::CyclicSupertraits(path) => {
+                write!(f, "cycle in the supertraits of trait `{}`: ", path[0])?;
+                for (index, name) in path.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " -> ")?;
+                    }
+                    write!(f, "`{}`", name)?;
+                }
+                Ok(())
+            }
+            RustIrError::CyclicAssociatedTypes(path) => {
+                let (trait_name, assoc_ty_name) = &path[0];
+                write!(
+                    f,
+                    "cycle in the bounds of associated type `{}::{}`: ",
+                    trait_name, assoc_ty_name
+                )?;
+                for (index, (trait_name, assoc_ty_name)) in path.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " -> ")?;
+                    }
+                    write!(f, "`{}::{}`", trait_name, assoc_ty_name)?;
+                }
+                Ok(())
+            }
         }
     }
 }
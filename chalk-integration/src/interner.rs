@@ -1,10 +1,11 @@
 use crate::tls;
+use chalk_ir::encode::{DecodeError, Decoder, EncodeInterner, Encoder};
 use chalk_ir::interner::{HasInterner, Interner};
 use chalk_ir::{
     AdtId, AliasTy, ApplicationTy, AssocTypeId, CanonicalVarKind, CanonicalVarKinds, ConstData,
-    Goals, Lifetime, OpaqueTy, OpaqueTyId, ProgramClauseImplication, ProgramClauses, ProjectionTy,
-    QuantifiedWhereClauses, SeparatorTraitRef, Substitution, TraitId, Ty, VariableKind,
-    VariableKinds,
+    FnDefId, Goals, Lifetime, OpaqueTy, OpaqueTyId, ProgramClauseImplication, ProgramClauses,
+    ProjectionTy, QuantifiedWhereClauses, SeparatorTraitRef, Substitution, TraitId, Ty,
+    VariableKind, VariableKinds,
 };
 use chalk_ir::{
     GenericArg, GenericArgData, Goal, GoalData, LifetimeData, ProgramClause, ProgramClauseData,
@@ -79,6 +80,10 @@ impl Interner for ChalkIr {
         tls::with_current_program(|prog| Some(prog?.debug_opaque_ty_id(id, fmt)))
     }
 
+    fn debug_fn_def_id(id: FnDefId<ChalkIr>, fmt: &mut fmt::Formatter<'_>) -> Option<fmt::Result> {
+        tls::with_current_program(|prog| Some(prog?.debug_fn_def_id(id, fmt)))
+    }
+
     fn debug_alias(alias: &AliasTy<ChalkIr>, fmt: &mut fmt::Formatter<'_>) -> Option<fmt::Result> {
         tls::with_current_program(|prog| Some(prog?.debug_alias(alias, fmt)))
     }
@@ -339,6 +344,37 @@ impl Interner for ChalkIr {
     }
 }
 
+impl EncodeInterner for ChalkIr {
+    fn encode_def_id(&self, def_id: RawId, encoder: &mut Encoder<'_, ChalkIr>) {
+        encoder.write_u32(def_id.index);
+    }
+
+    fn decode_def_id(&self, decoder: &mut Decoder<'_, ChalkIr>) -> Result<RawId, DecodeError> {
+        Ok(RawId {
+            index: decoder.read_u32()?,
+        })
+    }
+
+    fn encode_adt_id(&self, adt_id: RawId, encoder: &mut Encoder<'_, ChalkIr>) {
+        self.encode_def_id(adt_id, encoder);
+    }
+
+    fn decode_adt_id(&self, decoder: &mut Decoder<'_, ChalkIr>) -> Result<RawId, DecodeError> {
+        self.decode_def_id(decoder)
+    }
+
+    fn encode_concrete_const(&self, constant: &u32, encoder: &mut Encoder<'_, ChalkIr>) {
+        encoder.write_u32(*constant);
+    }
+
+    fn decode_concrete_const(
+        &self,
+        decoder: &mut Decoder<'_, ChalkIr>,
+    ) -> Result<u32, DecodeError> {
+        decoder.read_u32()
+    }
+}
+
 impl HasInterner for ChalkIr {
     type Interner = ChalkIr;
 }
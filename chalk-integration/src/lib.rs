@@ -12,6 +12,7 @@ pub mod program;
 pub mod program_environment;
 pub mod query;
 pub mod tls;
+pub mod trace;
 
 use chalk_ir::interner::HasInterner;
 use chalk_ir::Binders;
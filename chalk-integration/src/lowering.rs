@@ -2,10 +2,11 @@ use crate::interner::ChalkIr;
 use chalk_ir::cast::{Cast, Caster};
 use chalk_ir::interner::HasInterner;
 use chalk_ir::{
-    self, AdtId, AssocTypeId, BoundVar, ClausePriority, DebruijnIndex, FnDefId, ImplId, OpaqueTyId,
-    QuantifiedWhereClauses, Substitution, ToGenericArg, TraitId,
+    self, AdtId, AssocTypeId, BoundVar, ClausePriority, ClauseSource, DebruijnIndex, FnDefId,
+    ImplId, OpaqueTyId, QuantifiedWhereClauses, Substitution, ToGenericArg, TraitId,
 };
 use chalk_parse::ast::*;
+use chalk_solve::cycles;
 use chalk_solve::rust_ir::{
     self, Anonymize, AssociatedTyValueId, IntoWhereClauses, OpaqueTyDatum, OpaqueTyDatumBound,
 };
@@ -362,6 +363,7 @@ impl LowerProgram for Program {
         let mut trait_data = BTreeMap::new();
         let mut well_known_traits = BTreeMap::new();
         let mut impl_data = BTreeMap::new();
+        let mut may_dangle = BTreeMap::new();
         let mut associated_ty_data = BTreeMap::new();
         let mut associated_ty_values = BTreeMap::new();
         let mut opaque_ty_data = BTreeMap::new();
@@ -455,6 +457,16 @@ impl LowerProgram for Program {
                         &associated_ty_value_ids,
                     )?);
                     impl_data.insert(impl_id, impl_datum.clone());
+                    let impl_may_dangle: Vec<_> = impl_defn
+                        .may_dangle
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &may_dangle)| may_dangle)
+                        .map(|(index, _)| index)
+                        .collect();
+                    if !impl_may_dangle.is_empty() {
+                        may_dangle.insert(impl_id, impl_may_dangle);
+                    }
                     let trait_id = impl_datum.trait_id();
 
                     for atv in &impl_defn.assoc_ty_values {
@@ -564,8 +576,57 @@ impl LowerProgram for Program {
             opaque_ty_data,
             custom_clauses,
             object_safe_traits,
+            may_dangle,
         };
 
+        // `#[may_dangle]` is a promise of the destructor of a `Drop` impl.
+        let drop_trait_id = program
+            .well_known_traits
+            .get(&rust_ir::WellKnownTrait::DropTrait);
+        for (item, &raw_id) in self.items.iter().zip(&raw_ids) {
+            if let Item::Impl(impl_defn) = item {
+                let impl_id = ImplId(raw_id);
+                if program.may_dangle.contains_key(&impl_id)
+                    && drop_trait_id != Some(&program.impl_data[&impl_id].trait_id())
+                {
+                    Err(RustIrError::MayDangleOutsideDropImpl(
+                        impl_defn.trait_ref.trait_name.clone(),
+                    ))?;
+                }
+            }
+        }
+
+        // Reject traits whose supertraits or associated types are cyclic,
+        // which the solver cannot make sense of.
+        let mut trait_names = BTreeMap::new();
+        let mut assoc_ty_names = BTreeMap::new();
+        for (item, &raw_id) in self.items.iter().zip(&raw_ids) {
+            if let Item::TraitDefn(d) = item {
+                let trait_id = TraitId(raw_id);
+                trait_names.insert(trait_id, d.name.clone());
+                for defn in &d.assoc_ty_defns {
+                    let lookup = &associated_ty_lookups[&(trait_id, defn.name.str.clone())];
+                    assoc_ty_names.insert(lookup.id, (d.name.clone(), defn.name.clone()));
+                }
+            }
+        }
+
+        for &trait_id in trait_names.keys() {
+            if let Some(cycle) = cycles::super_trait_cycle(&program, trait_id) {
+                Err(RustIrError::CyclicSupertraits(
+                    cycle.iter().map(|id| trait_names[id].clone()).collect(),
+                ))?;
+            }
+        }
+
+        for &trait_id in trait_names.keys() {
+            if let Some(cycle) = cycles::associated_ty_cycle(&program, trait_id) {
+                Err(RustIrError::CyclicAssociatedTypes(
+                    cycle.iter().map(|id| assoc_ty_names[id].clone()).collect(),
+                ))?;
+            }
+        }
+
         Ok(program)
     }
 }
@@ -922,6 +983,10 @@ impl LowerDomainGoal for DomainGoal {
             DomainGoal::ObjectSafe { id } => {
                 vec![chalk_ir::DomainGoal::ObjectSafe(env.lookup_trait(id)?)]
             }
+            DomainGoal::NotImplemented { trait_ref } => {
+                vec![chalk_ir::DomainGoal::NotImplemented(trait_ref.lower(env)?)]
+            }
+            DomainGoal::FutureCompatible => vec![chalk_ir::DomainGoal::FutureCompatible(())],
         };
         Ok(goals)
     }
@@ -1511,12 +1576,18 @@ impl LowerImpl for Impl {
     ) -> LowerResult<rust_ir::ImplDatum<ChalkIr>> {
         debug_heading!("LowerImpl::lower_impl(impl_id={:?})", impl_id);
 
-        let polarity = self.polarity.lower();
+        let polarity = match (self.reservation, self.polarity.lower()) {
+            (false, polarity) => polarity,
+            (true, rust_ir::Polarity::Positive) => rust_ir::Polarity::Reservation,
+            (true, _) => Err(RustIrError::NegativeReservationImpl(
+                self.trait_ref.trait_name.clone(),
+            ))?,
+        };
         let binders = empty_env.in_binders(self.all_parameters(), |env| {
             let trait_ref = self.trait_ref.lower(env)?;
             debug!("trait_ref = {:?}", trait_ref);
 
-            if !polarity.is_positive() && !self.assoc_ty_values.is_empty() {
+            if polarity.is_negative() && !self.assoc_ty_values.is_empty() {
                 Err(RustIrError::NegativeImplAssociatedValues(
                     self.trait_ref.trait_name.clone(),
                 ))?;
@@ -1574,6 +1645,7 @@ impl LowerClause for Clause {
                     consequence,
                     conditions: conditions.clone(),
                     priority: ClausePriority::High,
+                    source: ClauseSource::Program,
                 })
                 .collect::<Vec<_>>();
             Ok(implications)
@@ -7,13 +7,14 @@ use chalk_ir::{
     Goal, Goals, ImplId, Lifetime, OpaqueTy, OpaqueTyId, ProgramClause, ProgramClauseImplication,
     ProgramClauses, ProjectionTy, Substitution, TraitId, Ty,
 };
+use chalk_solve::dependencies::ItemId;
 use chalk_solve::rust_ir::{
     AdtDatum, AssociatedTyDatum, AssociatedTyValue, AssociatedTyValueId, FnDefDatum, ImplDatum,
     ImplType, OpaqueTyDatum, TraitDatum, WellKnownTrait,
 };
 use chalk_solve::split::Split;
 use chalk_solve::RustIrDatabase;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::sync::Arc;
 
@@ -70,6 +71,9 @@ pub struct Program {
 
     /// Store the traits marked with `#[object_safe]`
     pub object_safe_traits: HashSet<TraitId<ChalkIr>>,
+
+    /// For each impl with parameters marked `#[may_dangle]`, their indices
+    pub may_dangle: BTreeMap<ImplId<ChalkIr>, Vec<usize>>,
 }
 
 impl Program {
@@ -81,6 +85,67 @@ impl Program {
             .map(|(&impl_id, _)| impl_id)
             .collect()
     }
+
+    /// Returns the items that differ between this program and `new`:
+    /// those that were added, removed, or changed. Items are matched
+    /// by id, so if the ids of some items shift (e.g., because an item
+    /// was inserted before them), they are all reported as changed.
+    pub fn changed_items(&self, new: &Program) -> Vec<ItemId<ChalkIr>> {
+        let mut changed = vec![];
+        changed.extend(changed_keys(&self.trait_data, &new.trait_data).map(ItemId::Trait));
+        changed.extend(
+            self.object_safe_traits
+                .symmetric_difference(&new.object_safe_traits)
+                .map(|&id| ItemId::Trait(id)),
+        );
+        let changed_impls = changed_keys(&self.impl_data, &new.impl_data)
+            .chain(changed_keys(&self.may_dangle, &new.may_dangle))
+            .collect::<BTreeSet<_>>();
+        for id in changed_impls {
+            // Changing an impl changes the set of impls of its trait.
+            changed.push(ItemId::Impl(id));
+            for program in &[self, new] {
+                if let Some(datum) = program.impl_data.get(&id) {
+                    changed.push(ItemId::Trait(datum.trait_id()));
+                }
+            }
+        }
+        changed.extend(changed_keys(&self.adt_data, &new.adt_data).map(ItemId::Adt));
+        changed.extend(changed_keys(&self.fn_def_data, &new.fn_def_data).map(ItemId::FnDef));
+        changed.extend(
+            changed_keys(&self.associated_ty_data, &new.associated_ty_data).map(ItemId::AssocTy),
+        );
+        changed.extend(
+            changed_keys(&self.associated_ty_values, &new.associated_ty_values)
+                .map(ItemId::AssocTyValue),
+        );
+        changed
+            .extend(changed_keys(&self.opaque_ty_data, &new.opaque_ty_data).map(ItemId::OpaqueTy));
+        if self.custom_clauses != new.custom_clauses {
+            changed.push(ItemId::CustomClauses);
+        }
+        if self.well_known_traits != new.well_known_traits {
+            changed.push(ItemId::WellKnownTraits);
+        }
+        changed
+    }
+}
+
+/// Returns the keys whose values differ between `old` and `new`,
+/// including the keys that are only present in one of them.
+fn changed_keys<'a, K: Ord + Copy, V: PartialEq>(
+    old: &'a BTreeMap<K, V>,
+    new: &'a BTreeMap<K, V>,
+) -> impl Iterator<Item = K> + 'a {
+    let changed_or_removed = old
+        .iter()
+        .filter(move |(key, value)| new.get(key) != Some(value))
+        .map(|(&key, _)| key);
+    let added = new
+        .keys()
+        .filter(move |key| !old.contains_key(key))
+        .cloned();
+    changed_or_removed.chain(added)
 }
 
 impl tls::DebugContext for Program {
@@ -140,6 +205,20 @@ impl tls::DebugContext for Program {
         }
     }
 
+    fn debug_fn_def_id(
+        &self,
+        fn_def_id: FnDefId<ChalkIr>,
+        fmt: &mut fmt::Formatter<'_>,
+    ) -> Result<(), fmt::Error> {
+        if let Some(k) = self.fn_def_kinds.get(&fn_def_id) {
+            write!(fmt, "{}", k.name)
+        } else {
+            fmt.debug_struct("InvalidItemId")
+                .field("index", &fn_def_id.0)
+                .finish()
+        }
+    }
+
     fn debug_alias(
         &self,
         alias_ty: &AliasTy<ChalkIr>,
@@ -405,4 +484,25 @@ impl RustIrDatabase<ChalkIr> for Program {
     fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> bool {
         self.object_safe_traits.contains(&trait_id)
     }
+
+    fn may_dangle_parameters(&self, impl_id: ImplId<ChalkIr>) -> Vec<usize> {
+        self.may_dangle.get(&impl_id).cloned().unwrap_or_default()
+    }
+
+    fn item_name(&self, item: ItemId<ChalkIr>) -> Option<String> {
+        let kind_name = |kind: &TypeKind| kind.name.to_string();
+        match item {
+            ItemId::Trait(id) => self.trait_kinds.get(&id).map(kind_name),
+            ItemId::Adt(id) => self.adt_kinds.get(&id).map(kind_name),
+            ItemId::FnDef(id) => self.fn_def_kinds.get(&id).map(kind_name),
+            ItemId::OpaqueTy(id) => self.opaque_ty_kinds.get(&id).map(kind_name),
+            ItemId::AssocTy(id) => self
+                .associated_ty_data
+                .get(&id)
+                .map(|datum| format!("{}::{}", self.trait_kinds[&datum.trait_id].name, datum.name)),
+            ItemId::Impl(id) => self.impl_data.get(&id).map(|_| String::new()),
+            ItemId::AssocTyValue(id) => self.associated_ty_values.get(&id).map(|_| String::new()),
+            ItemId::CustomClauses | ItemId::WellKnownTraits => Some(String::new()),
+        }
+    }
 }
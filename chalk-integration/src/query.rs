@@ -7,15 +7,16 @@ use crate::lowering::LowerProgram;
 use crate::program::Program;
 use crate::program_environment::ProgramEnvironment;
 use crate::tls;
-use chalk_ir::TraitId;
+use chalk_ir::{ClauseSource, TraitId};
 use chalk_solve::clauses::builder::ClauseBuilder;
 use chalk_solve::clauses::program_clauses::ToProgramClauses;
 use chalk_solve::coherence::orphan;
-use chalk_solve::coherence::{CoherenceSolver, SpecializationPriorities};
+use chalk_solve::coherence::{CoherenceSolver, CoherenceWarning, SpecializationPriorities};
 use chalk_solve::wf;
 use chalk_solve::RustIrDatabase;
 use chalk_solve::Solver;
 use chalk_solve::SolverChoice;
+use chalk_solve::SolverStats;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -28,13 +29,17 @@ pub trait LoweringDatabase: RustIrDatabase<ChalkIr> {
     #[salsa::input]
     fn solver_choice(&self) -> SolverChoice;
 
+    /// Whether impls that only overlap once upstream crates add impls
+    /// are accepted with a warning (see `CoherenceWarning`), rather than
+    /// rejected as rustc does.
+    #[salsa::input]
+    fn future_compat_warnings(&self) -> bool;
+
     fn program_ir(&self) -> Result<Arc<Program>, ChalkError>;
 
     /// Performs coherence check and computes which impls specialize
     /// one another (the "specialization priorities").
-    fn coherence(
-        &self,
-    ) -> Result<BTreeMap<TraitId<ChalkIr>, Arc<SpecializationPriorities<ChalkIr>>>, ChalkError>;
+    fn coherence(&self) -> Result<Arc<Coherence>, ChalkError>;
 
     fn orphan_check(&self) -> Result<(), ChalkError>;
 
@@ -46,14 +51,99 @@ pub trait LoweringDatabase: RustIrDatabase<ChalkIr> {
 
     /// Creates the solver we can use to solve goals. This solver
     /// stores intermediate, cached state, which is why it is behind a
-    /// mutex. Moreover, if the set of program clauses change, that
-    /// cached state becomes invalid, so the query is marked as
-    /// volatile, thus ensuring that the solver is recreated in every
-    /// revision (i.e., each time source program changes).
-    #[salsa::volatile]
-    fn solver(&self) -> Arc<Mutex<Solver<ChalkIr>>>;
+    /// mutex; goals are solved by forks of it (see `Solver::fork`),
+    /// so the mutex is only held briefly and the completed work is
+    /// shared. The solver only depends on the solver choice, so it is
+    /// kept when the source program changes; only the cached state
+    /// that depends on the changed items is discarded then (see
+    /// `IncrementalSolver`).
+    fn solver(&self) -> IncrementalSolver;
 }
 
+/// The result of a successful coherence check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coherence {
+    /// The specialization priorities of the impls of each trait.
+    pub priorities: BTreeMap<TraitId<ChalkIr>, Arc<SpecializationPriorities<ChalkIr>>>,
+
+    /// The future-compatibility warnings of the check (see
+    /// `CoherenceWarning`), which do not make it fail. Always empty
+    /// unless `future_compat_warnings` is set.
+    pub warnings: Vec<CoherenceWarning<ChalkIr>>,
+}
+
+/// A handle to a solver, along with the program that its cached state
+/// is valid for. Cloning an `IncrementalSolver` yields a handle to the
+/// same solver, and handles compare equal if they refer to the same
+/// solver.
+#[derive(Clone, Debug)]
+pub struct IncrementalSolver {
+    state: Arc<Mutex<IncrementalSolverState>>,
+}
+
+#[derive(Debug)]
+struct IncrementalSolverState {
+    solver: Solver<ChalkIr>,
+    program: Option<Arc<Program>>,
+}
+
+impl IncrementalSolver {
+    fn new(solver: Solver<ChalkIr>) -> Self {
+        IncrementalSolver {
+            state: Arc::new(Mutex::new(IncrementalSolverState {
+                solver,
+                program: None,
+            })),
+        }
+    }
+
+    /// Returns a fork of the solver (see `Solver::fork`) to solve goals
+    /// of `program`. If the solver was last used with some other
+    /// program, the cached state that depends on the items that differ
+    /// between the two programs is invalidated first. The lock on the
+    /// solver is only held while doing so, so that several threads can
+    /// solve goals in parallel.
+    pub fn fork_for(&self, program: &Arc<Program>) -> Solver<ChalkIr> {
+        self.with_solver_for(program, |solver| solver.fork())
+    }
+
+    /// Runs `op` with the solver itself, brought up to date with
+    /// `program` as in `fork_for`. Unlike with a fork, the work done by
+    /// `op` shows in the statistics of the solver, but the lock on the
+    /// solver is held while `op` runs.
+    pub fn with_solver_for<R>(
+        &self,
+        program: &Arc<Program>,
+        op: impl FnOnce(&mut Solver<ChalkIr>) -> R,
+    ) -> R {
+        let mut state = self.state.lock().unwrap();
+        let IncrementalSolverState {
+            solver,
+            program: last_program,
+        } = &mut *state;
+        if let Some(last_program) = last_program {
+            if !Arc::ptr_eq(last_program, program) {
+                solver.invalidate(last_program.changed_items(program));
+            }
+        }
+        *last_program = Some(program.clone());
+        op(solver)
+    }
+
+    /// Returns the statistics of the solver (see `Solver::stats`).
+    pub fn stats(&self) -> SolverStats {
+        self.state.lock().unwrap().solver.stats()
+    }
+}
+
+impl PartialEq for IncrementalSolver {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for IncrementalSolver {}
+
 fn program_ir(db: &impl LoweringDatabase) -> Result<Arc<Program>, ChalkError> {
     let text = db.program_text();
     Ok(Arc::new(chalk_parse::parse_program(&text)?.lower()?))
@@ -71,26 +161,28 @@ fn orphan_check(db: &impl LoweringDatabase) -> Result<(), ChalkError> {
     })
 }
 
-fn coherence(
-    db: &impl LoweringDatabase,
-) -> Result<BTreeMap<TraitId<ChalkIr>, Arc<SpecializationPriorities<ChalkIr>>>, ChalkError> {
+fn coherence(db: &impl LoweringDatabase) -> Result<Arc<Coherence>, ChalkError> {
     let program = db.program_ir()?;
 
-    let priorities_map = tls::set_current_program(&program, || -> Result<_, ChalkError> {
-        let priorities_map: Result<BTreeMap<_, _>, ChalkError> = program
-            .trait_data
-            .keys()
-            .map(|&trait_id| {
-                let solver = CoherenceSolver::new(db, db.solver_choice(), trait_id);
-                let priorities = solver.specialization_priorities()?;
-                Ok((trait_id, priorities))
-            })
-            .collect();
-        let priorities_map = priorities_map?;
-        Ok(priorities_map)
+    let coherence = tls::set_current_program(&program, || -> Result<_, ChalkError> {
+        let mut priorities = BTreeMap::new();
+        let mut warnings = vec![];
+        for &trait_id in program.trait_data.keys() {
+            let solver = CoherenceSolver::new(db, db.solver_choice(), trait_id);
+            let trait_priorities = if db.future_compat_warnings() {
+                solver.specialization_priorities_with_warnings(&mut warnings)?
+            } else {
+                solver.specialization_priorities()?
+            };
+            priorities.insert(trait_id, trait_priorities);
+        }
+        Ok(Coherence {
+            priorities,
+            warnings,
+        })
     });
     let () = db.orphan_check()?;
-    priorities_map
+    Ok(Arc::new(coherence?))
 }
 
 fn checked_program(db: &impl LoweringDatabase) -> Result<Arc<Program>, ChalkError> {
@@ -104,6 +196,18 @@ fn checked_program(db: &impl LoweringDatabase) -> Result<Arc<Program>, ChalkErro
             solver.verify_adt_decl(id)?;
         }
 
+        for &trait_id in program.trait_data.keys() {
+            solver.verify_trait_decl(trait_id)?;
+        }
+
+        for &fn_def_id in program.fn_def_data.keys() {
+            solver.verify_fn_def(fn_def_id)?;
+        }
+
+        for &opaque_ty_id in program.opaque_ty_data.keys() {
+            solver.verify_opaque_ty(opaque_ty_id)?;
+        }
+
         for &impl_id in program.impl_data.keys() {
             solver.verify_trait_impl(impl_id)?;
         }
@@ -146,27 +250,35 @@ fn environment(db: &impl LoweringDatabase) -> Result<Arc<ProgramEnvironment>, Ch
         .filter(|(_, auto_trait)| auto_trait.is_auto_trait())
     {
         for &adt_id in program.adt_data.keys() {
-            chalk_solve::clauses::push_auto_trait_impls(builder, auto_trait_id, adt_id);
+            builder.with_source(ClauseSource::Auto, |builder| {
+                chalk_solve::clauses::push_auto_trait_impls(builder, auto_trait_id, adt_id)
+            });
         }
     }
 
-    for datum in program.impl_data.values() {
+    for (&impl_id, datum) in program.impl_data.iter() {
         // If we encounter a negative impl, do not generate any rule. Negative impls
         // are currently just there to deactivate default impls for auto traits.
         if datum.is_positive() {
-            datum.to_program_clauses(builder);
+            builder.with_source(ClauseSource::Impl(impl_id), |builder| {
+                datum.to_program_clauses(builder)
+            });
             datum
                 .associated_ty_value_ids
                 .iter()
                 .map(|&atv_id| db.associated_ty_value(atv_id))
-                .for_each(|atv| atv.to_program_clauses(builder));
+                .for_each(|atv| {
+                    builder.with_source(ClauseSource::Impl(impl_id), |builder| {
+                        atv.to_program_clauses(builder)
+                    })
+                });
         }
     }
 
     Ok(Arc::new(ProgramEnvironment::new(program_clauses)))
 }
 
-fn solver(db: &impl LoweringDatabase) -> Arc<Mutex<Solver<ChalkIr>>> {
+fn solver(db: &impl LoweringDatabase) -> IncrementalSolver {
     let choice = db.solver_choice();
-    Arc::new(Mutex::new(choice.into_solver()))
+    IncrementalSolver::new(choice.into_solver())
 }
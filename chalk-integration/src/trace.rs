@@ -0,0 +1,110 @@
+//! Subscribers for the `tracing` output of the solvers. Events are
+//! written as JSON lines, so traces can be post-processed, filtered
+//! by field (`goal`, `table`, `clause`, `result`, ...) or diffed.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::filter::{EnvFilter, ParseError};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// Parses a filter for solver traces. For compatibility with the old
+/// `CHALK_DEBUG` levels, `0`, `1` and `2` turn off tracing or enable
+/// the `info` or `debug` output of the chalk crates (but not of their
+/// dependencies, like salsa); anything else is parsed as an
+/// `EnvFilter` directive, e.g. `chalk_engine=debug,chalk_solve=info`.
+pub fn filter(directives: &str) -> Result<EnvFilter, ParseError> {
+    let directives = match directives.trim() {
+        "" | "0" => "off",
+        "1" => "chalk=info",
+        "2" => "chalk=debug",
+        directives => directives,
+    };
+    EnvFilter::try_new(directives)
+}
+
+/// A handle to the subscriber installed by `init`, which can be used
+/// to change its filter later on.
+pub struct TraceHandle {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl TraceHandle {
+    /// Replaces the filter of the global subscriber; see `filter` for
+    /// the accepted syntax.
+    pub fn set_filter(&self, directives: &str) -> Result<(), String> {
+        let filter = filter(directives).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
+
+/// Installs a global subscriber that writes JSON lines to stderr. The
+/// initial filter is taken from the `CHALK_DEBUG` environment variable
+/// (and is `off` if that is unset or invalid).
+///
+/// # Panics
+///
+/// Panics if a global subscriber has already been installed.
+pub fn init() -> TraceHandle {
+    let directives = std::env::var("CHALK_DEBUG").unwrap_or_default();
+    let filter = filter(&directives).unwrap_or_else(|_| EnvFilter::new("off"));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json_layer().with_writer(io::stderr))
+        .init();
+    TraceHandle { handle }
+}
+
+/// Runs `op` with a subscriber that records every event matching
+/// `directives` (see `filter`), and returns the result of `op` along
+/// with the recorded events, one JSON object per entry. Timestamps
+/// are omitted, so the traces of two runs can be compared directly.
+///
+/// # Panics
+///
+/// Panics if `directives` is not a valid filter.
+pub fn collect<R>(directives: &str, op: impl FnOnce() -> R) -> (R, Vec<String>) {
+    let filter = filter(directives)
+        .unwrap_or_else(|e| panic!("invalid trace filter `{}`: {}", directives, e));
+    let buffer = SharedBuffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::registry().with(filter).with(
+        json_layer()
+            .without_time()
+            .with_writer(move || writer.clone()),
+    );
+    let result = tracing::subscriber::with_default(subscriber, op);
+
+    let bytes = buffer.0.lock().unwrap();
+    let lines = String::from_utf8_lossy(&bytes)
+        .lines()
+        .map(|line| line.to_owned())
+        .collect();
+    (result, lines)
+}
+
+fn json_layer<S>() -> fmt::Layer<S, fmt::format::JsonFields, fmt::format::Format<fmt::format::Json>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_span_list(true)
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -197,6 +197,7 @@ where
             consequence: self.cast(interner),
             conditions: Goals::new(interner),
             priority: ClausePriority::High,
+            source: ClauseSource::Program,
         })
         .intern(interner)
     }
@@ -212,6 +213,7 @@ where
             consequence: bound.cast(interner),
             conditions: Goals::new(interner),
             priority: ClausePriority::High,
+            source: ClauseSource::Program,
         }))
         .intern(interner)
     }
@@ -664,6 +664,8 @@ impl<I: Interner> Debug for DomainGoal<I> {
             DomainGoal::DownstreamType(n) => write!(fmt, "DownstreamType({:?})", n),
             DomainGoal::Reveal(_) => write!(fmt, "Reveal"),
             DomainGoal::ObjectSafe(n) => write!(fmt, "ObjectSafe({:?})", n),
+            DomainGoal::NotImplemented(tr) => write!(fmt, "NotImplemented({:?})", tr.with_colon()),
+            DomainGoal::FutureCompatible(_) => write!(fmt, "FutureCompatible"),
         }
     }
 }
@@ -786,6 +788,17 @@ impl<I: Interner> Debug for Constraint<I> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
             Constraint::Outlives(a, b) => write!(fmt, "{:?}: {:?}", a, b),
+            Constraint::TypeOutlives(ty, lifetime) => write!(fmt, "{:?}: {:?}", ty, lifetime),
+            Constraint::Or(alternatives) => {
+                write!(fmt, "Or(")?;
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, " || ")?;
+                    }
+                    write!(fmt, "{:?}", alternative)?;
+                }
+                write!(fmt, ")")
+            }
         }
     }
 }
@@ -802,6 +815,22 @@ impl<I: Interner> Display for ConstrainedSubst<I> {
     }
 }
 
+impl<I: Interner> Display for AnswerSubst<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let AnswerSubst {
+            subst,
+            constraints,
+            delayed_subgoals,
+        } = self;
+
+        write!(
+            f,
+            "substitution {}, lifetime constraints {:?}, delayed subgoals {:?}",
+            subst, constraints, delayed_subgoals,
+        )
+    }
+}
+
 impl<I: Interner> Substitution<I> {
     /// Displays the substitution in the form `< P0, .. Pn >`, or (if
     /// the substitution is empty) as an empty string.
@@ -0,0 +1,450 @@
+//! A binary encoding of the IR, so that solver results can be saved
+//! and loaded back in a later process.
+//!
+//! Most of the IR is encoded structurally (see the `Encode` derive):
+//! the fields of a struct are encoded in order, preceded by the index
+//! of the variant for enums. Interned values are encoded through their
+//! data, so the encoding does not depend on how the interner represents
+//! them. The only things that the interner has to encode itself are
+//! the ids and the concrete constants, see `EncodeInterner`.
+//!
+//! The encoding does not describe itself: a value can only be decoded
+//! as the type it was encoded from, with an interner that maps ids the
+//! same way as the one it was encoded with.
+
+use crate::interner::Interner;
+use crate::*;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// An interner whose ids (and concrete constants) can be encoded.
+pub trait EncodeInterner: Interner {
+    fn encode_def_id(&self, def_id: Self::DefId, encoder: &mut Encoder<'_, Self>);
+
+    fn decode_def_id(&self, decoder: &mut Decoder<'_, Self>) -> Result<Self::DefId, DecodeError>;
+
+    fn encode_adt_id(&self, adt_id: Self::InternedAdtId, encoder: &mut Encoder<'_, Self>);
+
+    fn decode_adt_id(
+        &self,
+        decoder: &mut Decoder<'_, Self>,
+    ) -> Result<Self::InternedAdtId, DecodeError>;
+
+    fn encode_concrete_const(
+        &self,
+        constant: &Self::InternedConcreteConst,
+        encoder: &mut Encoder<'_, Self>,
+    );
+
+    fn decode_concrete_const(
+        &self,
+        decoder: &mut Decoder<'_, Self>,
+    ) -> Result<Self::InternedConcreteConst, DecodeError>;
+}
+
+/// A value that can be encoded into bytes, and decoded back.
+pub trait Encode<I: EncodeInterner>: Sized {
+    fn encode(&self, encoder: &mut Encoder<'_, I>);
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError>;
+}
+
+/// Accumulates the encoding of some values.
+pub struct Encoder<'i, I: Interner> {
+    interner: &'i I,
+    bytes: Vec<u8>,
+}
+
+impl<'i, I: Interner> Encoder<'i, I> {
+    pub fn new(interner: &'i I) -> Self {
+        Encoder {
+            interner,
+            bytes: vec![],
+        }
+    }
+
+    pub fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// The bytes encoded so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Decodes values from the bytes produced by an `Encoder`.
+pub struct Decoder<'i, I: Interner> {
+    interner: &'i I,
+    bytes: &'i [u8],
+}
+
+impl<'i, I: Interner> Decoder<'i, I> {
+    pub fn new(interner: &'i I, bytes: &'i [u8]) -> Self {
+        Decoder { interner, bytes }
+    }
+
+    pub fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    /// True if all the bytes have been decoded.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'i [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+/// The bytes being decoded are not the encoding of a value of the
+/// expected type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes end in the middle of a value.
+    UnexpectedEnd,
+
+    /// An enum variant index (or a `bool`) is out of range.
+    InvalidTag(u32),
+
+    /// An id that the interner does not know of.
+    InvalidId,
+
+    /// There are bytes left after the value.
+    TrailingData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            DecodeError::InvalidTag(tag) => write!(f, "invalid tag {}", tag),
+            DecodeError::InvalidId => write!(f, "invalid id"),
+            DecodeError::TrailingData => write!(f, "unexpected data after the end"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl<I: EncodeInterner> Encode<I> for u32 {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder.write_u32(*self);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        decoder.read_u32()
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for u64 {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder.write_u64(*self);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        decoder.read_u64()
+    }
+}
+
+/// Encoded as a `u64`, so that the encoding does not depend on the
+/// platform.
+impl<I: EncodeInterner> Encode<I> for usize {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder.write_u64(*self as u64);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(decoder.read_u64()? as usize)
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for bool {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder.write_u8(*self as u8);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        match decoder.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(DecodeError::InvalidTag(tag as u32)),
+        }
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for () {
+    fn encode(&self, _encoder: &mut Encoder<'_, I>) {}
+
+    fn decode(_decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for Void {
+    fn encode(&self, _encoder: &mut Encoder<'_, I>) {
+        match *self {}
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Err(DecodeError::InvalidTag(decoder.read_u32()?))
+    }
+}
+
+impl<T, I: EncodeInterner> Encode<I> for PhantomData<T> {
+    fn encode(&self, _encoder: &mut Encoder<'_, I>) {}
+
+    fn decode(_decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(PhantomData)
+    }
+}
+
+impl<T: Encode<I>, I: EncodeInterner> Encode<I> for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encode_slice(self, encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        decode_vec(decoder)
+    }
+}
+
+impl<T: Encode<I>, I: EncodeInterner> Encode<I> for Option<T> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        match self {
+            None => encoder.write_u8(0),
+            Some(value) => {
+                encoder.write_u8(1);
+                value.encode(encoder);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        match decoder.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(decoder)?)),
+            tag => Err(DecodeError::InvalidTag(tag as u32)),
+        }
+    }
+}
+
+impl<T: Encode<I>, I: EncodeInterner> Encode<I> for Box<T> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        (**self).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(Box::new(T::decode(decoder)?))
+    }
+}
+
+impl<T: Encode<I>, I: EncodeInterner> Encode<I> for Arc<T> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        (**self).encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(Arc::new(T::decode(decoder)?))
+    }
+}
+
+impl<A: Encode<I>, B: Encode<I>, I: EncodeInterner> Encode<I> for (A, B) {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        self.0.encode(encoder);
+        self.1.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok((A::decode(decoder)?, B::decode(decoder)?))
+    }
+}
+
+/// Encodes the length of `values`, followed by each value.
+fn encode_slice<T: Encode<I>, I: EncodeInterner>(values: &[T], encoder: &mut Encoder<'_, I>) {
+    values.len().encode(encoder);
+    for value in values {
+        value.encode(encoder);
+    }
+}
+
+/// Decodes the values encoded by `encode_slice`.
+fn decode_vec<T: Encode<I>, I: EncodeInterner>(
+    decoder: &mut Decoder<'_, I>,
+) -> Result<Vec<T>, DecodeError> {
+    let len = usize::decode(decoder)?;
+    (0..len).map(|_| T::decode(decoder)).collect()
+}
+
+macro_rules! id_encode {
+    ($t:ident) => {
+        id_encode!($t, encode_def_id, decode_def_id);
+    };
+    ($t:ident, $encode_fn:ident, $decode_fn:ident) => {
+        impl<I: EncodeInterner> Encode<I> for $t<I> {
+            fn encode(&self, encoder: &mut Encoder<'_, I>) {
+                encoder.interner().$encode_fn(self.0, encoder);
+            }
+
+            fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+                Ok($t(decoder.interner().$decode_fn(decoder)?))
+            }
+        }
+    };
+}
+
+id_encode!(ImplId);
+id_encode!(AdtId, encode_adt_id, decode_adt_id);
+id_encode!(TraitId);
+id_encode!(AssocTypeId);
+id_encode!(OpaqueTyId);
+id_encode!(FnDefId);
+id_encode!(ClauseId);
+
+/// Implements `Encode` for a type that is interned as `$data`.
+macro_rules! interned_encode {
+    ($t:ident, $data:ident) => {
+        impl<I: EncodeInterner> Encode<I> for $t<I> {
+            fn encode(&self, encoder: &mut Encoder<'_, I>) {
+                self.data(encoder.interner()).encode(encoder);
+            }
+
+            fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+                let data = $data::decode(decoder)?;
+                Ok(data.intern(decoder.interner()))
+            }
+        }
+    };
+}
+
+interned_encode!(Ty, TyData);
+interned_encode!(Lifetime, LifetimeData);
+interned_encode!(Const, ConstData);
+interned_encode!(GenericArg, GenericArgData);
+interned_encode!(Goal, GoalData);
+interned_encode!(ProgramClause, ProgramClauseData);
+
+/// Implements `Encode` for a type that is interned as a list of `$elem`.
+macro_rules! interned_list_encode {
+    ($t:ident, $elem:ty) => {
+        impl<I: EncodeInterner> Encode<I> for $t<I> {
+            fn encode(&self, encoder: &mut Encoder<'_, I>) {
+                encode_slice(self.as_slice(encoder.interner()), encoder);
+            }
+
+            fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+                let values: Vec<$elem> = decode_vec(decoder)?;
+                Ok($t::from(decoder.interner(), values))
+            }
+        }
+    };
+}
+
+interned_list_encode!(Goals, Goal<I>);
+interned_list_encode!(ProgramClauses, ProgramClause<I>);
+interned_list_encode!(QuantifiedWhereClauses, QuantifiedWhereClause<I>);
+interned_list_encode!(VariableKinds, VariableKind<I>);
+interned_list_encode!(CanonicalVarKinds, CanonicalVarKind<I>);
+
+impl<I: EncodeInterner> Encode<I> for Substitution<I> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encode_slice(self.parameters(encoder.interner()), encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        let parameters: Vec<GenericArg<I>> = decode_vec(decoder)?;
+        Ok(Substitution::from(decoder.interner(), parameters))
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for ConcreteConst<I> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder
+            .interner()
+            .encode_concrete_const(&self.interned, encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(ConcreteConst {
+            interned: decoder.interner().decode_concrete_const(decoder)?,
+        })
+    }
+}
+
+impl<I: EncodeInterner, T: Encode<I>> Encode<I> for WithKind<I, T> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        self.kind.encode(encoder);
+        self.value.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        let kind = VariableKind::decode(decoder)?;
+        let value = T::decode(decoder)?;
+        Ok(WithKind::new(kind, value))
+    }
+}
+
+/// Encodes `value` on its own.
+pub fn encode<I: EncodeInterner, T: Encode<I>>(interner: &I, value: &T) -> Vec<u8> {
+    let mut encoder = Encoder::new(interner);
+    value.encode(&mut encoder);
+    encoder.into_bytes()
+}
+
+/// Decodes a value encoded by `encode`, failing if there are bytes
+/// left over.
+pub fn decode<I: EncodeInterner, T: Encode<I>>(
+    interner: &I,
+    bytes: &[u8],
+) -> Result<T, DecodeError> {
+    let mut decoder = Decoder::new(interner, bytes);
+    let value = T::decode(&mut decoder)?;
+    if !decoder.is_empty() {
+        return Err(DecodeError::TrailingData);
+    }
+    Ok(value)
+}
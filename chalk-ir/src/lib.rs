@@ -8,7 +8,7 @@ use crate::fold::shift::Shift;
 use crate::fold::{Fold, Folder, Subst, SuperFold};
 use crate::visit::{SuperVisit, Visit, VisitExt, VisitResult, Visitor};
 use chalk_base::results::*;
-use chalk_derive::{Fold, HasInterner, SuperVisit, Visit, Zip};
+use chalk_derive::{Encode, Fold, HasInterner, SuperVisit, Visit, Zip};
 use std::iter;
 use std::marker::PhantomData;
 
@@ -45,8 +45,9 @@ use interner::{HasInterner, Interner};
 
 pub mod could_match;
 pub mod debug;
+pub mod encode;
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Encode)]
 /// The set of assumptions we've made so far, and the current number of
 /// universal (forall) quantifiers we're within.
 pub struct Environment<I: Interner> {
@@ -71,7 +72,7 @@ impl<I: Interner> Environment<I> {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, Encode)]
 pub struct InEnvironment<G: HasInterner> {
     pub environment: Environment<G::Interner>,
     pub goal: G,
@@ -101,7 +102,7 @@ impl<G: HasInterner> HasInterner for InEnvironment<G> {
     type Interner = G::Interner;
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub enum IntTy {
     Isize,
     I8,
@@ -111,7 +112,7 @@ pub enum IntTy {
     I128,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub enum UintTy {
     Usize,
     U8,
@@ -121,13 +122,13 @@ pub enum UintTy {
     U128,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub enum FloatTy {
     F32,
     F64,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub enum Scalar {
     Bool,
     Char,
@@ -136,13 +137,13 @@ pub enum Scalar {
     Float(FloatTy),
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub enum Mutability {
     Mut,
     Not,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Fold, Visit)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Fold, Visit, Encode)]
 pub enum TypeName<I: Interner> {
     /// Abstract data types, i.e., structs, unions, or enumerations.
     /// For example, a type like `Vec<T>`.
@@ -194,7 +195,7 @@ impl<I: Interner> HasInterner for TypeName<I> {
 /// `forall { Goal(?0) }` (used a DeBruijn index)
 /// `Goal(!U1)` (the quantifier was moved to the environment and replaced with a universe index)
 /// See https://rustc-dev-guide.rust-lang.org/borrow_check/region_inference.html#placeholders-and-universes for more.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub struct UniverseIndex {
     pub counter: usize,
 }
@@ -322,7 +323,7 @@ impl<I: Interner> Ty<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, HasInterner, Encode)]
 pub enum TyData<I: Interner> {
     /// An "application" type is one that applies the set of type
     /// arguments to some base type. For example, `Vec<u32>` would be
@@ -395,7 +396,7 @@ impl<I: Interner> TyData<I> {
 /// The variables `'c` and `'d` both have debruijn index of 0 because
 /// they appear in the *innermost* binder enclosing the `...`. The
 /// indices identify the location *within* that binder.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Encode)]
 pub struct BoundVar {
     pub debruijn: DebruijnIndex,
     pub index: usize,
@@ -480,7 +481,7 @@ impl BoundVar {
 /// `BoundVar(0)` (as the `for` is the innermost binder).
 ///
 /// [de Bruijn index]: https://en.wikipedia.org/wiki/De_Bruijn_index
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Encode)]
 pub struct DebruijnIndex {
     depth: u32,
 }
@@ -633,12 +634,12 @@ impl DebruijnIndex {
 /// known. It is referenced within the type using `^1`, indicating
 /// a bound type with debruijn index 1 (i.e., skipping through one
 /// level of binder).
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub struct DynTy<I: Interner> {
     pub bounds: Binders<QuantifiedWhereClauses<I>>,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Encode)]
 pub struct InferenceVar {
     index: u32,
 }
@@ -673,7 +674,7 @@ impl InferenceVar {
 
 /// for<'a...'z> X -- all binders are instantiated at once,
 /// and we use deBruijn indices within `self.ty`
-#[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, HasInterner, Encode)]
 pub struct Fn<I: Interner> {
     pub num_binders: usize,
     pub substitution: Substitution<I>,
@@ -729,13 +730,13 @@ impl<I: Interner> Const<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, HasInterner, Encode)]
 pub struct ConstData<I: Interner> {
     pub ty: Ty<I>,
     pub value: ConstValue<I>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, HasInterner, Encode)]
 pub enum ConstValue<I: Interner> {
     BoundVar(BoundVar),
     InferenceVar(InferenceVar),
@@ -810,7 +811,7 @@ impl<I: Interner> Lifetime<I> {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, HasInterner)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, HasInterner, Encode)]
 pub enum LifetimeData<I: Interner> {
     /// See TyData::Var(_).
     BoundVar(BoundVar),
@@ -828,7 +829,7 @@ impl<I: Interner> LifetimeData<I> {
 /// Index of an universally quantified parameter in the environment.
 /// Two indexes are required, the one of the universe itself
 /// and the relative index inside the universe.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
 pub struct PlaceholderIndex {
     /// Index *of* the universe.
     pub ui: UniverseIndex,
@@ -855,7 +856,7 @@ impl PlaceholderIndex {
 }
 
 // Fold derive intentionally omitted, folded through Ty
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub struct ApplicationTy<I: Interner> {
     pub name: TypeName<I>,
     pub substitution: Substitution<I>,
@@ -882,7 +883,7 @@ impl<I: Interner> ApplicationTy<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Encode)]
 pub enum VariableKind<I: Interner> {
     Ty,
     Lifetime,
@@ -972,7 +973,7 @@ impl<I: Interner> GenericArg<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Visit, Fold, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Visit, Fold, Zip, Encode)]
 pub enum GenericArgData<I: Interner> {
     Ty(Ty<I>),
     Lifetime(Lifetime<I>),
@@ -1034,7 +1035,7 @@ impl<I: Interner, T> WithKind<I, T> {
 #[allow(type_alias_bounds)]
 pub type CanonicalVarKind<I: Interner> = WithKind<I, UniverseIndex>;
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub enum AliasTy<I: Interner> {
     Projection(ProjectionTy<I>),
     Opaque(OpaqueTy<I>),
@@ -1058,19 +1059,19 @@ impl<I: Interner> AliasTy<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub struct ProjectionTy<I: Interner> {
     pub associated_ty_id: AssocTypeId<I>,
     pub substitution: Substitution<I>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub struct OpaqueTy<I: Interner> {
     pub opaque_ty_id: OpaqueTyId<I>,
     pub substitution: Substitution<I>,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub struct TraitRef<I: Interner> {
     pub trait_id: TraitId<I>,
     pub substitution: Substitution<I>,
@@ -1098,13 +1099,13 @@ impl<I: Interner> TraitRef<I> {
 }
 
 /// Where clauses that can be written by a Rust programmer.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip, Encode)]
 pub enum WhereClause<I: Interner> {
     Implemented(TraitRef<I>),
     AliasEq(AliasEq<I>),
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub enum WellFormed<I: Interner> {
     /// A predicate which is true when some trait ref is well-formed.
     /// For example, given the following trait definitions:
@@ -1134,7 +1135,7 @@ pub enum WellFormed<I: Interner> {
     Ty(Ty<I>),
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 pub enum FromEnv<I: Interner> {
     /// A predicate which enables deriving everything which should be true if we *know* that
     /// some trait ref is well-formed. For example given the above trait definitions, we can use
@@ -1166,7 +1167,7 @@ pub enum FromEnv<I: Interner> {
 /// A "domain goal" is a goal that is directly about Rust, rather than a pure
 /// logical statement. As much as possible, the Chalk solver should avoid
 /// decomposing this enum, and instead treat its values opaquely.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip, Encode)]
 pub enum DomainGoal<I: Interner> {
     Holds(WhereClause<I>),
 
@@ -1234,6 +1235,20 @@ pub enum DomainGoal<I: Interner> {
 
     /// Used to indicate that a trait is object safe.
     ObjectSafe(TraitId<I>),
+
+    /// True if a negative impl like `impl !Trait for Foo` promises that
+    /// the trait is not implemented for the type. Unlike `not { T: Trait }`,
+    /// this also holds in the "compatible modality": a negative impl is a
+    /// promise that no crate will ever add such an impl.
+    NotImplemented(TraitRef<I>),
+
+    /// Used to activate the rules of the "compatible modality" that account for impls that
+    /// upstream crates may add in future versions. Like `Compatible`, this is present in the
+    /// environment of `compatible { G }` goals; it is left out to ask whether `G` holds in a
+    /// compatible world where only downstream crates add impls.
+    ///
+    /// (HACK: Having `()` makes some of our macros work better.)
+    FutureCompatible(()),
 }
 
 pub type QuantifiedWhereClause<I> = Binders<WhereClause<I>>;
@@ -1354,7 +1369,7 @@ impl<I: Interner> DomainGoal<I> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip, Encode)]
 pub struct EqGoal<I: Interner> {
     pub a: GenericArg<I>,
     pub b: GenericArg<I>,
@@ -1364,14 +1379,14 @@ pub struct EqGoal<I: Interner> {
 /// type. A projection `T::Foo` normalizes to the type `U` if we can
 /// **match it to an impl** and that impl has a `type Foo = V` where
 /// `U = V`.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip, Encode)]
 pub struct Normalize<I: Interner> {
     pub alias: AliasTy<I>,
     pub ty: Ty<I>,
 }
 
 /// Proves **equality** between an alias and a type.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip, Encode)]
 pub struct AliasEq<I: Interner> {
     pub alias: AliasTy<I>,
     pub ty: Ty<I>,
@@ -1388,7 +1403,7 @@ impl<I: Interner> HasInterner for AliasEq<I> {
 ///
 /// (IOW, we use deBruijn indices, where binders are introduced in reverse order
 /// of `self.binders`.)
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash, Encode)]
 pub struct Binders<T: HasInterner> {
     pub binders: VariableKinds<T::Interner>,
     value: T,
@@ -1616,19 +1631,69 @@ where
 /// Represents one clause of the form `consequence :- conditions` where
 /// `conditions = cond_1 && cond_2 && ...` is the conjunction of the individual
 /// conditions.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, Fold, Visit, HasInterner, Encode)]
 pub struct ProgramClauseImplication<I: Interner> {
     pub consequence: DomainGoal<I>,
     pub conditions: Goals<I>,
     pub priority: ClausePriority,
+
+    /// What the clause was derived from; when a goal is proven using
+    /// this clause, this tells which impl (say) the answer comes from.
+    /// This is only metadata: it is ignored when comparing, hashing or
+    /// zipping clauses.
+    pub source: ClauseSource<I>,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+impl<I: Interner> PartialEq for ProgramClauseImplication<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.consequence == other.consequence
+            && self.conditions == other.conditions
+            && self.priority == other.priority
+    }
+}
+
+impl<I: Interner> Eq for ProgramClauseImplication<I> {}
+
+impl<I: Interner> std::hash::Hash for ProgramClauseImplication<I> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.consequence.hash(state);
+        self.conditions.hash(state);
+        self.priority.hash(state);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Encode)]
 pub enum ClausePriority {
     High,
     Low,
 }
 
+/// The origin of a program clause.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Fold, Visit, HasInterner, Encode)]
+pub enum ClauseSource<I: Interner> {
+    /// An impl of the program, or one of its associated type values.
+    Impl(ImplId<I>),
+
+    /// A hypothesis of the environment, such as a where clause in
+    /// scope; this includes the rule `Implemented(T: Trait) :-
+    /// FromEnv(T: Trait)`, which makes use of them.
+    Environment,
+
+    /// An impl that is built into the language, for a well-known trait
+    /// (e.g., `Copy` for tuples or `Sized` for structs).
+    Builtin,
+
+    /// The implicit impl of an auto trait for an ADT.
+    Auto,
+
+    /// The implicit impl of its traits for a `dyn Trait` type.
+    Object,
+
+    /// Any other rule, such as those derived from the declaration of
+    /// a trait or type, or the custom clauses of the program.
+    Program,
+}
+
 impl std::ops::BitAnd for ClausePriority {
     type Output = ClausePriority;
     fn bitand(self, rhs: ClausePriority) -> Self::Output {
@@ -1639,7 +1704,7 @@ impl std::ops::BitAnd for ClausePriority {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, HasInterner, Zip, Encode)]
 pub enum ProgramClauseData<I: Interner> {
     Implies(ProgramClauseImplication<I>),
     ForAll(Binders<ProgramClauseImplication<I>>),
@@ -1652,6 +1717,7 @@ impl<I: Interner> ProgramClauseImplication<I> {
                 consequence: self.consequence.into_from_env_goal(interner),
                 conditions: self.conditions.clone(),
                 priority: self.priority,
+                source: self.source,
             }
         } else {
             self
@@ -1695,6 +1761,36 @@ impl<I: Interner> ProgramClause<I> {
         Self::new(interner, new_clause)
     }
 
+    /// The source of this clause; see `ClauseSource`.
+    pub fn source(&self, interner: &I) -> ClauseSource<I> {
+        match self.data(interner) {
+            ProgramClauseData::Implies(implication) => implication.source,
+            ProgramClauseData::ForAll(binders) => binders.skip_binders().source,
+        }
+    }
+
+    /// Returns this clause with its source replaced by `source`.
+    pub fn with_source(self, interner: &I, source: ClauseSource<I>) -> ProgramClause<I> {
+        if self.source(interner) == source {
+            return self;
+        }
+        let new_clause = match self.data(interner).clone() {
+            ProgramClauseData::Implies(implication) => {
+                ProgramClauseData::Implies(ProgramClauseImplication {
+                    source,
+                    ..implication
+                })
+            }
+            ProgramClauseData::ForAll(binders) => {
+                ProgramClauseData::ForAll(binders.map(|implication| ProgramClauseImplication {
+                    source,
+                    ..implication
+                }))
+            }
+        };
+        Self::new(interner, new_clause)
+    }
+
     pub fn interned(&self) -> &I::InternedProgramClause {
         &self.interned
     }
@@ -1866,7 +1962,7 @@ impl<I: Interner> CanonicalVarKinds<I> {
 /// All unresolved existential variables are "renumbered" according to their
 /// first appearance; the kind/universe of the variable is recorded in the
 /// `binders` field.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Encode)]
 pub struct Canonical<T: HasInterner> {
     pub value: T,
     pub binders: CanonicalVarKinds<T::Interner>,
@@ -1882,7 +1978,7 @@ impl<T: HasInterner> HasInterner for Canonical<T> {
 /// distinctions.
 ///
 /// To produce one of these values, use the `u_canonicalize` method.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Encode)]
 pub struct UCanonical<T: HasInterner> {
     pub canonical: Canonical<T>,
     pub universes: usize,
@@ -2023,15 +2119,31 @@ impl<I: Interner> Goal<I> {
 
     /// Takes a goal `G` and turns it into `compatible { G }`.
     pub fn compatible(self, interner: &I) -> Self {
-        // compatible { G } desugars into: forall<T> { if (Compatible, DownstreamType(T)) { G } }
+        // compatible { G } desugars into:
+        //
+        //     forall<T> { if (Compatible, FutureCompatible, DownstreamType(T)) { G } }
+        //
         // This activates the compatible modality rules and introduces an anonymous downstream type
+        self.compatible_with(interner, Some(DomainGoal::FutureCompatible(())))
+    }
+
+    /// Takes a goal `G` and turns it into `compatible { G }`, but without
+    /// the rules for the impls that upstream crates may add in future
+    /// versions (see `DomainGoal::FutureCompatible`).
+    pub fn downstream_compatible(self, interner: &I) -> Self {
+        self.compatible_with(interner, None)
+    }
+
+    fn compatible_with(self, interner: &I, future: Option<DomainGoal<I>>) -> Self {
         GoalData::Quantified(
             QuantifierKind::ForAll,
             Binders::with_fresh_type_var(interner, |ty| {
                 GoalData::Implies(
                     ProgramClauses::from(
                         interner,
-                        vec![DomainGoal::Compatible(()), DomainGoal::DownstreamType(ty)],
+                        iter::once(DomainGoal::Compatible(()))
+                            .chain(future)
+                            .chain(iter::once(DomainGoal::DownstreamType(ty))),
                     ),
                     self.shifted_in(interner),
                 )
@@ -2083,7 +2195,7 @@ where
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip, Encode)]
 /// A general goal; this is the full range of questions you can pose to Chalk.
 pub enum GoalData<I: Interner> {
     /// Introduces a binding at depth 0, shifting other bindings up
@@ -2118,7 +2230,7 @@ impl<I: Interner> GoalData<I> {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Encode)]
 pub enum QuantifierKind {
     ForAll,
     Exists,
@@ -2130,9 +2242,22 @@ pub enum QuantifierKind {
 /// lifetime constraints, instead gathering them up to return with our solution
 /// for later checking. This allows for decoupling between type and region
 /// checking in the compiler.
-#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Encode)]
 pub enum Constraint<I: Interner> {
     Outlives(Lifetime<I>, Lifetime<I>),
+
+    /// The type outlives the lifetime, that is, all lifetimes in the type
+    /// do. This is only used for types whose lifetimes are not known,
+    /// like type parameters and projections; for other types, we use
+    /// `Outlives` constraints on their lifetimes instead.
+    TypeOutlives(Ty<I>, Lifetime<I>),
+
+    /// At least one of the given sets of constraints must hold. This
+    /// arises when a goal can be proven in several ways that only
+    /// differ in the region constraints they require. Each alternative
+    /// carries its own environments, so the environment of an `Or`
+    /// constraint itself is irrelevant.
+    Or(Vec<Vec<InEnvironment<Constraint<I>>>>),
 }
 
 /// A mapping of inference variables to instantiations thereof.
@@ -2374,13 +2499,13 @@ impl<'i, I: Interner> Folder<'i, I> for &SubstFolder<'i, I> {
 /// substitution stores the values for the query's unknown variables,
 /// and the constraints represents any region constraints that must
 /// additionally be solved.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Encode)]
 pub struct ConstrainedSubst<I: Interner> {
     pub subst: Substitution<I>, /* NB: The `is_trivial` routine relies on the fact that `subst` is folded first. */
     pub constraints: Vec<InEnvironment<Constraint<I>>>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Encode)]
 pub struct AnswerSubst<I: Interner> {
     pub subst: Substitution<I>, /* NB: The `is_trivial` routine relies on the fact that `subst` is folded first. */
     pub constraints: Vec<InEnvironment<Constraint<I>>>,
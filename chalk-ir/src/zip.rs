@@ -229,6 +229,19 @@ impl<T: HasInterner<Interner = I> + Zip<I>, I: Interner> Zip<I> for InEnvironmen
     }
 }
 
+impl<I: Interner> Zip<I> for ProgramClauseImplication<I> {
+    fn zip_with<'i, Z: Zipper<'i, I>>(zipper: &mut Z, a: &Self, b: &Self) -> Fallible<()>
+    where
+        I: 'i,
+    {
+        // The source of a clause is not part of it.
+        Zip::zip_with(zipper, &a.consequence, &b.consequence)?;
+        Zip::zip_with(zipper, &a.conditions, &b.conditions)?;
+        Zip::zip_with(zipper, &a.priority, &b.priority)?;
+        Ok(())
+    }
+}
+
 impl<I: Interner> Zip<I> for Environment<I> {
     fn zip_with<'i, Z: Zipper<'i, I>>(zipper: &mut Z, a: &Self, b: &Self) -> Fallible<()>
     where
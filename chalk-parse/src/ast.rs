@@ -163,8 +163,11 @@ impl fmt::Display for Kind {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Impl {
     pub variable_kinds: Vec<VariableKind>,
+    /// Whether each of `variable_kinds` is marked `#[may_dangle]`.
+    pub may_dangle: Vec<bool>,
     pub trait_ref: TraitRef,
     pub polarity: Polarity,
+    pub reservation: bool,
     pub where_clauses: Vec<QuantifiedWhereClause>,
     pub assoc_ty_values: Vec<AssocTyValue>,
     pub impl_type: ImplType,
@@ -337,6 +340,8 @@ pub enum DomainGoal {
     DownstreamType { ty: Ty },
     Reveal,
     ObjectSafe { id: Identifier },
+    NotImplemented { trait_ref: TraitRef },
+    FutureCompatible,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
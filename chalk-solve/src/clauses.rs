@@ -9,6 +9,7 @@ use chalk_ir::could_match::CouldMatch;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
 use rustc_hash::FxHashSet;
+use tracing::instrument;
 
 pub mod builder;
 mod builtin_traits;
@@ -17,6 +18,8 @@ mod env_elaborator;
 mod generalize;
 pub mod program_clauses;
 
+pub(crate) use self::dyn_ty::super_traits_with_cycles;
+
 /// For auto-traits, we generate a default rule for every struct,
 /// unless there is a manual impl for that struct given explicitly.
 ///
@@ -109,16 +112,12 @@ pub fn push_auto_trait_impls<I: Interner>(
 /// to this goal from the Rust program. So for example if the goal
 /// is `Implemented(T: Clone)`, then this function might return clauses
 /// derived from the trait `Clone` and its impls.
+#[instrument(level = "debug", skip(db))]
 pub(crate) fn program_clauses_for_goal<'db, I: Interner>(
     db: &'db dyn RustIrDatabase<I>,
     environment: &Environment<I>,
     goal: &DomainGoal<I>,
 ) -> Result<Vec<ProgramClause<I>>, Floundered> {
-    debug_heading!(
-        "program_clauses_for_goal(goal={:?}, environment={:?})",
-        goal,
-        environment
-    );
     let interner = db.interner();
 
     // FIXME: change this to use `.chain().filter()`
@@ -130,10 +129,10 @@ pub(crate) fn program_clauses_for_goal<'db, I: Interner>(
         db.program_clauses_for_env(environment)
             .iter(interner)
             .filter(|c| (*c).could_match(interner, goal))
-            .cloned(),
+            .map(|c| c.clone().with_source(interner, ClauseSource::Environment)),
     );
 
-    debug!("vec = {:#?}", vec);
+    debug!(clauses = ?vec, "program clauses for goal");
 
     Ok(vec)
 }
@@ -176,7 +175,9 @@ fn program_clauses_that_could_match<I: Interner>(
                 trait_ref.trait_id,
                 trait_ref.substitution.parameters(interner),
             ) {
-                db.impl_datum(impl_id).to_program_clauses(builder);
+                builder.with_source(ClauseSource::Impl(impl_id), |builder| {
+                    db.impl_datum(impl_id).to_program_clauses(builder)
+                });
             }
 
             // If this is a `Foo: Send` (or any auto-trait), then add
@@ -186,7 +187,9 @@ fn program_clauses_that_could_match<I: Interner>(
                 match trait_ref.self_type_parameter(interner).data(interner) {
                     TyData::Apply(apply) => match &apply.name {
                         TypeName::Adt(adt_id) => {
-                            push_auto_trait_impls(builder, trait_id, *adt_id);
+                            builder.with_source(ClauseSource::Auto, |builder| {
+                                push_auto_trait_impls(builder, trait_id, *adt_id)
+                            });
                         }
                         _ => {}
                     },
@@ -210,7 +213,9 @@ fn program_clauses_that_could_match<I: Interner>(
             // things but not `Clone`.
             let self_ty = trait_ref.self_type_parameter(interner);
             if let TyData::Dyn(_) = self_ty.data(interner) {
-                dyn_ty::build_dyn_self_ty_clauses(db, builder, self_ty.clone())
+                builder.with_source(ClauseSource::Object, |builder| {
+                    dyn_ty::build_dyn_self_ty_clauses(db, builder, self_ty.clone())
+                });
             }
 
             match self_ty.data(interner) {
@@ -225,7 +230,9 @@ fn program_clauses_that_could_match<I: Interner>(
             }
 
             if let Some(well_known) = trait_datum.well_known {
-                builtin_traits::add_builtin_program_clauses(db, builder, well_known, trait_ref);
+                builder.with_source(ClauseSource::Builtin, |builder| {
+                    builtin_traits::add_builtin_program_clauses(db, builder, well_known, trait_ref)
+                });
             }
         }
         DomainGoal::Holds(WhereClause::AliasEq(alias_eq)) => match &alias_eq.alias {
@@ -254,6 +261,19 @@ fn program_clauses_that_could_match<I: Interner>(
             db.trait_datum(trait_ref.trait_id)
                 .to_program_clauses(builder);
         }
+        DomainGoal::NotImplemented(trait_ref) => {
+            for impl_id in db.impls_for_trait(
+                trait_ref.trait_id,
+                trait_ref.substitution.parameters(interner),
+            ) {
+                let impl_datum = db.impl_datum(impl_id);
+                if impl_datum.is_negative() {
+                    builder.with_source(ClauseSource::Impl(impl_id), |builder| {
+                        impl_datum.to_program_clauses(builder)
+                    });
+                }
+            }
+        }
         DomainGoal::ObjectSafe(trait_id) => {
             if builder.db.is_object_safe(*trait_id) {
                 builder.push_fact(DomainGoal::ObjectSafe(*trait_id));
@@ -301,7 +321,7 @@ fn program_clauses_that_could_match<I: Interner>(
             }
             AliasTy::Opaque(_) => (),
         },
-        DomainGoal::Compatible(()) | DomainGoal::Reveal(()) => (),
+        DomainGoal::Compatible(()) | DomainGoal::FutureCompatible(()) | DomainGoal::Reveal(()) => {}
     };
 
     Ok(())
@@ -345,7 +365,9 @@ fn push_program_clauses_for_associated_type_values_in_impls_of<I: Interner>(
         for &atv_id in &impl_datum.associated_ty_value_ids {
             let atv = builder.db.associated_ty_value(atv_id);
             debug!("atv_id = {:?} atv = {:#?}", atv_id, atv);
-            atv.to_program_clauses(builder);
+            builder.with_source(ClauseSource::Impl(impl_id), |builder| {
+                atv.to_program_clauses(builder)
+            });
         }
     }
 }
@@ -16,6 +16,7 @@ pub struct ClauseBuilder<'me, I: Interner> {
     clauses: &'me mut Vec<ProgramClause<I>>,
     binders: Vec<VariableKind<I>>,
     parameters: Vec<GenericArg<I>>,
+    source: ClauseSource<I>,
 }
 
 impl<'me, I: Interner> ClauseBuilder<'me, I> {
@@ -25,6 +26,7 @@ impl<'me, I: Interner> ClauseBuilder<'me, I> {
             clauses,
             binders: vec![],
             parameters: vec![],
+            source: ClauseSource::Program,
         }
     }
 
@@ -75,6 +77,7 @@ impl<'me, I: Interner> ClauseBuilder<'me, I> {
             consequence: consequence.cast(interner),
             conditions: Goals::from(interner, conditions),
             priority,
+            source: self.source,
         };
 
         if self.binders.len() == 0 {
@@ -93,6 +96,15 @@ impl<'me, I: Interner> ClauseBuilder<'me, I> {
         debug!("pushed clause {:?}", self.clauses.last());
     }
 
+    /// Executes `op` with `source` as the source of the clauses it
+    /// pushes; the clauses pushed outside of any `with_source` come
+    /// from `ClauseSource::Program`.
+    pub fn with_source(&mut self, source: ClauseSource<I>, op: impl FnOnce(&mut Self)) {
+        let old_source = std::mem::replace(&mut self.source, source);
+        op(self);
+        self.source = old_source;
+    }
+
     /// Accesses the placeholders for the current list of parameters in scope.
     pub fn placeholders_in_scope(&self) -> &[GenericArg<I>] {
         &self.parameters
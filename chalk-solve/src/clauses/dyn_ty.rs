@@ -1,5 +1,3 @@
-use rustc_hash::FxHashSet;
-
 use super::{builder::ClauseBuilder, generalize};
 use crate::RustIrDatabase;
 use chalk_ir::{
@@ -108,9 +106,21 @@ fn push_dyn_ty_impl_clauses<I: Interner>(
 pub fn super_traits<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     trait_id: TraitId<I>,
+) -> Binders<Vec<Binders<TraitRef<I>>>> {
+    super_traits_with_cycles(db, trait_id, &mut Vec::new())
+}
+
+/// Like `super_traits`, but also pushes the cycles that the walk over the
+/// supertraits runs into onto `cycles`. Each cycle is the path of traits
+/// from the first trait of the cycle back to that trait, e.g. `[A, B, A]`
+/// for `trait A where Self: B {}` and `trait B where Self: A {}`.
+pub(crate) fn super_traits_with_cycles<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+    cycles: &mut Vec<Vec<TraitId<I>>>,
 ) -> Binders<Vec<Binders<TraitRef<I>>>> {
     let interner = db.interner();
-    let mut seen_traits = FxHashSet::default();
+    let mut seen_traits = Vec::new();
     let trait_datum = db.trait_datum(trait_id);
     let trait_ref = Binders::empty(
         db.interner(),
@@ -123,20 +133,25 @@ pub fn super_traits<I: Interner>(
         },
     );
     let mut trait_refs = Vec::new();
-    go(db, trait_ref, &mut seen_traits, &mut trait_refs);
+    go(db, trait_ref, &mut seen_traits, &mut trait_refs, cycles);
 
     fn go<I: Interner>(
         db: &dyn RustIrDatabase<I>,
         trait_ref: Binders<TraitRef<I>>,
-        seen_traits: &mut FxHashSet<TraitId<I>>,
+        seen_traits: &mut Vec<TraitId<I>>,
         trait_refs: &mut Vec<Binders<TraitRef<I>>>,
+        cycles: &mut Vec<Vec<TraitId<I>>>,
     ) {
         let interner = db.interner();
         let trait_id = trait_ref.skip_binders().trait_id;
-        // Avoid cycles
-        if !seen_traits.insert(trait_id) {
+        // Avoid cycles, but remember them
+        if let Some(start) = seen_traits.iter().position(|&id| id == trait_id) {
+            let mut cycle = seen_traits[start..].to_vec();
+            cycle.push(trait_id);
+            cycles.push(cycle);
             return;
         }
+        seen_traits.push(trait_id);
         trait_refs.push(trait_ref.clone());
         let trait_datum = db.trait_datum(trait_id);
         let super_trait_refs = trait_datum
@@ -173,9 +188,9 @@ pub fn super_traits<I: Interner>(
             // binders of super_trait_ref.
             let actual_binders = Binders::new(trait_ref.binders.clone(), q_super_trait_ref);
             let q_super_trait_ref = actual_binders.fuse_binders(interner);
-            go(db, q_super_trait_ref, seen_traits, trait_refs);
+            go(db, q_super_trait_ref, seen_traits, trait_refs, cycles);
         }
-        seen_traits.remove(&trait_id);
+        seen_traits.pop();
     }
 
     Binders::new(trait_datum.binders.binders.clone(), trait_refs)
@@ -23,17 +23,31 @@ impl<I: Interner> ToProgramClauses<I> for ImplDatum<I> {
     /// }
     /// ```
     ///
-    /// For a negative impl like `impl... !Clone for ...`, however, we
-    /// generate nothing -- this is just a way to *opt out* from the
-    /// default auto trait impls, it doesn't have any positive effect
-    /// on its own.
+    /// A negative impl like `impl<T: Clone> !Send for Vec<T>` opts out
+    /// from the default auto trait impls, and promises that the trait
+    /// is not implemented:
+    ///
+    /// ```notrust
+    /// -- Rule NotImplemented-From-Impl
+    /// forall<T> {
+    ///     NotImplemented(Vec<T>: Send) :- Implemented(T: Clone).
+    /// }
+    /// ```
+    ///
+    /// A reservation impl generates no clauses: it only matters for the
+    /// coherence check.
     fn to_program_clauses(&self, builder: &mut ClauseBuilder<'_, I>) {
-        if self.is_positive() {
-            let binders = self.binders.map_ref(|b| (&b.trait_ref, &b.where_clauses));
-            builder.push_binders(&binders, |builder, (trait_ref, where_clauses)| {
-                builder.push_clause(trait_ref, where_clauses);
-            });
+        if self.polarity == Polarity::Reservation {
+            return;
         }
+        let binders = self.binders.map_ref(|b| (&b.trait_ref, &b.where_clauses));
+        builder.push_binders(&binders, |builder, (trait_ref, where_clauses)| {
+            if self.is_positive() {
+                builder.push_clause(trait_ref, where_clauses);
+            } else {
+                builder.push_clause(DomainGoal::NotImplemented(trait_ref), where_clauses);
+            }
+        });
     }
 }
 
@@ -537,10 +551,12 @@ impl<I: Interner> ToProgramClauses<I> for TraitDatum<I> {
     ///     Implemented(Self: Foo<T, U, V>) :-
     ///         Implemented(Self: Eq<T>), // where clauses
     ///         Compatible,               // compatible modality
+    ///         FutureCompatible,         // ... including future versions of upstream crates
     ///         IsUpstream(Self),
     ///         IsUpstream(T),
     ///         IsUpstream(U),
     ///         IsUpstream(V),
+    ///         not { NotImplemented(Self: Foo<T, U, V>) }, // no negative impl
     ///         CannotProve.              // returns ambiguous
     /// }
     /// ```
@@ -572,6 +588,9 @@ impl<I: Interner> ToProgramClauses<I> for TraitDatum<I> {
     /// To implement fundamental traits, we simply just do not add the rule above that allows
     /// upstream types to implement upstream traits. Fundamental traits are not allowed to
     /// compatibly do that.
+    ///
+    /// Alternatively, std can write `impl !Sized for str { }`. A negative impl is a promise that
+    /// the impl will never be added, so the rule above does not apply to the types it covers.
     fn to_program_clauses(&self, builder: &mut ClauseBuilder<'_, I>) {
         let interner = builder.interner();
         let binders = self.binders.map_ref(|b| &b.where_clauses);
@@ -652,11 +671,17 @@ impl<I: Interner> ToProgramClauses<I> for TraitDatum<I> {
                         .cloned()
                         .casted(interner)
                         .chain(iter::once(DomainGoal::Compatible(()).cast(interner)))
+                        .chain(iter::once(DomainGoal::FutureCompatible(()).cast(interner)))
                         .chain(
                             trait_ref
                                 .type_parameters(interner)
                                 .map(|ty| DomainGoal::IsUpstream(ty).cast(interner)),
                         )
+                        .chain(iter::once(
+                            DomainGoal::NotImplemented(trait_ref.clone())
+                                .cast::<Goal<_>>(interner)
+                                .negate(interner),
+                        ))
                         .chain(iter::once(GoalData::CannotProve(()).intern(interner))),
                 );
             }
@@ -687,7 +712,9 @@ impl<I: Interner> ToProgramClauses<I> for TraitDatum<I> {
             // ```
             // Implemented(T: Foo) :- FromEnv(T: Foo)
             // ```
-            builder.push_clause(trait_ref.clone(), Some(trait_ref.clone().from_env()));
+            builder.with_source(ClauseSource::Environment, |builder| {
+                builder.push_clause(trait_ref.clone(), Some(trait_ref.clone().from_env()))
+            });
         });
     }
 }
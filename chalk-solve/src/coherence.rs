@@ -3,7 +3,7 @@ use petgraph::prelude::*;
 use crate::solve::SolverChoice;
 use crate::RustIrDatabase;
 use chalk_ir::interner::Interner;
-use chalk_ir::{self, ImplId, TraitId};
+use chalk_ir::{self, Canonical, GenericArg, ImplId, Lifetime, TraitId, TraitRef, Ty};
 use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
@@ -19,18 +19,195 @@ pub struct CoherenceSolver<'db, I: Interner> {
 
 #[derive(Debug)]
 pub enum CoherenceError<I: Interner> {
-    OverlappingImpls(TraitId<I>),
-    FailedOrphanCheck(TraitId<I>),
+    /// The impls `lhs` and `rhs` of the trait `trait_id` overlap,
+    /// neither specializes the other, and no impl that specializes
+    /// both covers their overlap. `witness` is a trait reference that
+    /// both impls apply to, if one could be found.
+    OverlappingImpls {
+        trait_id: TraitId<I>,
+        lhs: ImplId<I>,
+        rhs: ImplId<I>,
+        witness: Option<Canonical<TraitRef<I>>>,
+        kind: OverlapKind,
+    },
+    /// The impl `impl_id` of the trait `trait_id` violates the orphan
+    /// rules. `non_local_ty` is the first input type of the impl that
+    /// is not local.
+    FailedOrphanCheck {
+        trait_id: TraitId<I>,
+        impl_id: ImplId<I>,
+        non_local_ty: OrphanInputTy<I>,
+        uncovered: Option<UncoveredTyParam<I>>,
+    },
+    /// The impl `impl_id` of the trait `trait_id` specializes the impl
+    /// `specialized`, but whether it applies depends on more than the
+    /// types that `specialized` applies to, which is unsound.
+    NotAlwaysApplicable {
+        trait_id: TraitId<I>,
+        impl_id: ImplId<I>,
+        specialized: ImplId<I>,
+        violation: ApplicabilityViolation<I>,
+    },
+}
+
+/// Why a specializing impl is not always applicable. The parameters
+/// refer to the binders of the specializing impl.
+#[derive(Debug)]
+pub enum ApplicabilityViolation<I: Interner> {
+    /// The parameter is the value of several parameters of the
+    /// specialized impl, so the impl only applies if their values are
+    /// equal, like `impl<T> Foo for (T, T)` specializing
+    /// `impl<A, B> Foo for (A, B)`.
+    RepeatedParameter(GenericArg<I>),
+
+    /// The lifetime appears several times in the values of the
+    /// parameters of the specialized impl, so the impl only applies
+    /// if those lifetimes are equal, like `impl<'a> Foo for &'a &'a u8`
+    /// specializing `impl<T> Foo for T`.
+    LifetimeConstraint(Lifetime<I>),
+}
+
+/// A type parameter of an impl that appears in an input type before
+/// the first local type, which the orphan rules forbid.
+#[derive(Debug)]
+pub struct UncoveredTyParam<I: Interner> {
+    /// The position of the type parameter among the parameters of the
+    /// impl.
+    pub param: usize,
+
+    /// The input type that the parameter appears in.
+    pub input_ty: OrphanInputTy<I>,
+}
+
+/// An input type of an impl checked by the orphan rules.
+#[derive(Debug)]
+pub struct OrphanInputTy<I: Interner> {
+    /// The position of the type among the input types of the impl:
+    /// 0 for the self type, followed by the type arguments of the
+    /// trait.
+    pub index: usize,
+
+    /// The type, unless it refers to the parameters of the impl,
+    /// which have no names to print it with.
+    pub ty: Option<Ty<I>>,
+}
+
+impl<I: Interner> fmt::Display for OrphanInputTy<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ty, self.index) {
+            (Some(ty), _) => write!(f, "`{:?}`", ty),
+            (None, 0) => write!(f, "the self type"),
+            (None, index) => write!(f, "the {} type argument of the trait", Ordinal(index)),
+        }
+    }
+}
+
+/// Formats a 1-based position as an English ordinal, like `2nd`.
+struct Ordinal(usize);
+
+impl fmt::Display for Ordinal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match (self.0 % 10, self.0 % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        };
+        write!(f, "{}{}", self.0, suffix)
+    }
+}
+
+/// How sure the coherence check is that two impls overlap.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverlapKind {
+    /// Both impls apply to the witness in the current crate.
+    Definite,
+
+    /// The solver could not decide whether both impls apply.
+    Ambiguous,
+
+    /// The impls do not overlap in the current crate, but they might
+    /// in a downstream crate, or once an upstream crate adds impls.
+    Downstream,
 }
 
 impl<I: Interner> fmt::Display for CoherenceError<I> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CoherenceError::OverlappingImpls(id) => {
-                write!(f, "overlapping impls of trait `{:?}`", id)
+            CoherenceError::OverlappingImpls {
+                trait_id,
+                lhs,
+                rhs,
+                witness,
+                kind,
+            } => {
+                write!(
+                    f,
+                    "overlapping impls of trait `{:?}`: `{:?}` and `{:?}`",
+                    trait_id, lhs, rhs
+                )?;
+                let apply = match kind {
+                    OverlapKind::Definite => "both apply",
+                    OverlapKind::Ambiguous | OverlapKind::Downstream => "may both apply",
+                };
+                match witness {
+                    Some(witness) => write!(f, " {} to `{:?}`", apply, witness.value)?,
+                    None => write!(f, " {}", apply)?,
+                }
+                match kind {
+                    OverlapKind::Definite => Ok(()),
+                    OverlapKind::Ambiguous => write!(f, " (the overlap check is ambiguous)"),
+                    OverlapKind::Downstream => write!(f, " in a downstream or future crate"),
+                }
             }
-            CoherenceError::FailedOrphanCheck(id) => {
-                write!(f, "impl for trait `{:?}` violates the orphan rules", id)
+            CoherenceError::FailedOrphanCheck {
+                trait_id,
+                impl_id,
+                non_local_ty,
+                uncovered,
+            } => {
+                write!(
+                    f,
+                    "impl for trait `{:?}` violates the orphan rules: \
+                     the first non-local input type of `{:?}` is {}",
+                    trait_id, impl_id, non_local_ty
+                )?;
+                match uncovered {
+                    Some(UncoveredTyParam { param, input_ty }) => write!(
+                        f,
+                        ", and the {} impl parameter appears uncovered in {} \
+                         before any local type",
+                        Ordinal(param + 1),
+                        input_ty
+                    ),
+                    None => write!(f, ", and none of its input types is local"),
+                }
+            }
+            CoherenceError::NotAlwaysApplicable {
+                trait_id,
+                impl_id,
+                specialized,
+                violation,
+            } => {
+                write!(
+                    f,
+                    "impl `{:?}` of trait `{:?}` cannot specialize `{:?}` \
+                     because it is not always applicable: ",
+                    impl_id, trait_id, specialized
+                )?;
+                match violation {
+                    ApplicabilityViolation::RepeatedParameter(param) => write!(
+                        f,
+                        "parameter `{:?}` is used for several parameters of the specialized impl",
+                        param
+                    ),
+                    ApplicabilityViolation::LifetimeConstraint(lifetime) => write!(
+                        f,
+                        "lifetime `{:?}` is required to be equal to other lifetimes",
+                        lifetime
+                    ),
+                }
             }
         }
     }
@@ -38,6 +215,46 @@ impl<I: Interner> fmt::Display for CoherenceError<I> {
 
 impl<I: Interner> std::error::Error for CoherenceError<I> {}
 
+/// A future-compatibility warning of the coherence check. Unlike a
+/// `CoherenceError`, a warning does not make the check fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoherenceWarning<I: Interner> {
+    /// The impls `lhs` and `rhs` of the trait `trait_id` only overlap
+    /// if an upstream crate adds an impl in a future version, which it
+    /// is allowed to do compatibly. `witness` is a trait reference that
+    /// both impls may apply to, if one could be found.
+    FutureOverlappingImpls {
+        trait_id: TraitId<I>,
+        lhs: ImplId<I>,
+        rhs: ImplId<I>,
+        witness: Option<Canonical<TraitRef<I>>>,
+    },
+}
+
+impl<I: Interner> fmt::Display for CoherenceWarning<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoherenceWarning::FutureOverlappingImpls {
+                trait_id,
+                lhs,
+                rhs,
+                witness,
+            } => {
+                write!(
+                    f,
+                    "impls of trait `{:?}` may overlap in the future: `{:?}` and `{:?}` \
+                     may both apply",
+                    trait_id, lhs, rhs
+                )?;
+                if let Some(witness) = witness {
+                    write!(f, " to `{:?}`", witness.value)?;
+                }
+                write!(f, " once upstream crates add impls")
+            }
+        }
+    }
+}
+
 /// Stores the specialization priorities for a set of impls.
 /// This basically encodes which impls specialize one another.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -57,6 +274,11 @@ impl<I: Interner> SpecializationPriorities<I> {
         self.map[&impl_id]
     }
 
+    /// Lookup the priority of an impl, if it is in the set.
+    pub fn get(&self, impl_id: ImplId<I>) -> Option<SpecializationPriority> {
+        self.map.get(&impl_id).copied()
+    }
+
     /// Store the priority of an impl (used during construction).
     /// Panics if we have already stored the priority for this impl.
     fn insert(&mut self, impl_id: ImplId<I>, p: SpecializationPriority) {
@@ -90,22 +312,53 @@ where
 
     pub fn specialization_priorities(
         &self,
+    ) -> Result<Arc<SpecializationPriorities<I>>, CoherenceError<I>> {
+        self.specialization_priorities_impl(None)
+    }
+
+    /// Like `specialization_priorities`, but impls that only overlap once
+    /// upstream crates add impls are accepted, and the overlap is pushed
+    /// onto `warnings` instead. rustc rejects such impls, so this is an
+    /// opt-in.
+    pub fn specialization_priorities_with_warnings(
+        &self,
+        warnings: &mut Vec<CoherenceWarning<I>>,
+    ) -> Result<Arc<SpecializationPriorities<I>>, CoherenceError<I>> {
+        self.specialization_priorities_impl(Some(warnings))
+    }
+
+    fn specialization_priorities_impl(
+        &self,
+        warnings: Option<&mut Vec<CoherenceWarning<I>>>,
     ) -> Result<Arc<SpecializationPriorities<I>>, CoherenceError<I>> {
         let mut result = SpecializationPriorities::<I>::new();
 
-        let forest = self.build_specialization_forest()?;
+        let forest = self.build_specialization_forest(warnings)?;
 
-        // Visit every root in the forest & set specialization
-        // priority for the tree that is the root of.
-        for root_idx in forest.externals(Direction::Incoming) {
-            self.set_priorities(root_idx, &forest, 0, &mut result);
+        // Visit the impls so that every impl comes after the impls it
+        // specializes, and give each impl a priority one higher than the
+        // highest priority among those. With lattice specialization, an
+        // impl can specialize several impls that do not specialize each
+        // other, so the forest is not necessarily a tree.
+        let order = petgraph::algo::toposort(&forest, None)
+            .unwrap_or_else(|_| panic!("specialization cycle among impls of {:?}", self.trait_id));
+        for idx in order {
+            let p = forest
+                .neighbors_directed(idx, Direction::Incoming)
+                .map(|parent| result.priority(forest[parent]).0 + 1)
+                .max()
+                .unwrap_or(0);
+            result.insert(forest[idx], SpecializationPriority(p));
         }
 
         Ok(Arc::new(result))
     }
 
     // Build the forest of specialization relationships.
-    fn build_specialization_forest(&self) -> Result<Graph<ImplId<I>, ()>, CoherenceError<I>> {
+    fn build_specialization_forest(
+        &self,
+        warnings: Option<&mut Vec<CoherenceWarning<I>>>,
+    ) -> Result<Graph<ImplId<I>, ()>, CoherenceError<I>> {
         // The forest is returned as a graph but built as a GraphMap; this is
         // so that we never add multiple nodes with the same ItemId.
         let mut forest = DiGraphMap::new();
@@ -113,32 +366,13 @@ where
         // Find all specializations (implemented in coherence/solve)
         // Record them in the forest by adding an edge from the less special
         // to the more special.
-        self.visit_specializations_of_trait(|less_special, more_special| {
-            forest.add_edge(less_special, more_special, ());
-        })?;
+        self.visit_specializations_of_trait(
+            |less_special, more_special| {
+                forest.add_edge(less_special, more_special, ());
+            },
+            warnings,
+        )?;
 
         Ok(forest.into_graph())
     }
-
-    // Recursively set priorities for those node and all of its children.
-    fn set_priorities(
-        &self,
-        idx: NodeIndex,
-        forest: &Graph<ImplId<I>, ()>,
-        p: usize,
-        map: &mut SpecializationPriorities<I>,
-    ) {
-        // Get the impl datum recorded at this node and reset its priority
-        {
-            let impl_id = forest
-                .node_weight(idx)
-                .expect("index should be a valid index into graph");
-            map.insert(*impl_id, SpecializationPriority(p));
-        }
-
-        // Visit all children of this node, setting their priority to this + 1
-        for child_idx in forest.neighbors(idx) {
-            self.set_priorities(child_idx, forest, p + 1, map);
-        }
-    }
 }
@@ -1,9 +1,12 @@
-use crate::coherence::CoherenceError;
+use crate::coherence::{CoherenceError, OrphanInputTy, UncoveredTyParam};
 use crate::ext::GoalExt;
-use crate::solve::SolverChoice;
+use crate::rust_ir::ImplDatum;
+use crate::solve::{Solver, SolverChoice};
 use crate::RustIrDatabase;
 use chalk_ir::cast::*;
 use chalk_ir::interner::Interner;
+use chalk_ir::visit::visitors::FindAny;
+use chalk_ir::visit::{SuperVisit, Visit, VisitResult, Visitor};
 use chalk_ir::*;
 
 // Test if a local impl violates the orphan rules.
@@ -32,16 +35,138 @@ pub fn perform_orphan_check<I: Interner>(
         .cast(db.interner());
 
     let canonical_goal = &impl_allowed.into_closed_goal(db.interner());
-    let is_allowed = solver_choice
-        .into_solver()
-        .solve(db, canonical_goal)
-        .is_some();
+    let mut solver = solver_choice.into_solver();
+    let is_allowed = solver.solve(db, canonical_goal).is_some();
     debug!("overlaps = {:?}", is_allowed);
 
     if !is_allowed {
-        let trait_id = impl_datum.trait_id();
-        Err(CoherenceError::FailedOrphanCheck(trait_id))?;
+        let (non_local_ty, uncovered) = explain_orphan_check(db, &mut solver, &impl_datum);
+        Err(CoherenceError::FailedOrphanCheck {
+            trait_id: impl_datum.trait_id(),
+            impl_id,
+            non_local_ty,
+            uncovered,
+        })?;
     }
 
     Ok(())
 }
+
+// Figures out why an impl violates the orphan rules, following the `LocalImplAllowed` clauses
+// (see `TraitDatum::to_program_clauses`): for `impl<P0..Pn> Trait<T1..Tm> for T0`, some `Ti`
+// must be local, and each of `T0..Ti` before it must be fully visible, i.e. must not contain
+// any of the type parameters `P0..Pn`.
+//
+// Returns the first input type that is not local and, if one of the input types before the
+// first local type is not fully visible, the first type parameter that appears in it.
+fn explain_orphan_check<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    solver: &mut Solver<I>,
+    impl_datum: &ImplDatum<I>,
+) -> (OrphanInputTy<I>, Option<UncoveredTyParam<I>>) {
+    let interner = db.interner();
+    let input_tys: Vec<_> = impl_datum
+        .binders
+        .skip_binders()
+        .trait_ref
+        .type_parameters(interner)
+        .collect();
+
+    // forall<P0..Pn> { goal(Ti) }
+    let mut holds = |goal: fn(Ty<I>) -> DomainGoal<I>, ty: &Ty<I>| {
+        let goal: Goal<I> = impl_datum
+            .binders
+            .map_ref(|_| goal(ty.clone()))
+            .cast(interner);
+        let canonical_goal = &goal.into_closed_goal(interner);
+        match solver.solve(db, canonical_goal) {
+            Some(solution) => solution.is_unique(),
+            None => false,
+        }
+    };
+    let is_local: Vec<_> = input_tys
+        .iter()
+        .map(|ty| holds(DomainGoal::IsLocal, ty))
+        .collect();
+
+    // The parameters of the impl have no names to print, so input types that refer to them
+    // are described by their position instead.
+    let input_ty = |index: usize| {
+        let ty = &input_tys[index];
+        OrphanInputTy {
+            index,
+            ty: if ty.needs_shift(interner) {
+                None
+            } else {
+                Some(ty.clone())
+            },
+        }
+    };
+
+    let non_local_index = is_local
+        .iter()
+        .position(|&is_local| !is_local)
+        .expect("an impl that violates the orphan rules has a non-local input type");
+    let non_local_ty = input_ty(non_local_index);
+
+    let uncovered = (0..input_tys.len())
+        .take_while(|&index| !is_local[index])
+        .find(|&index| !holds(DomainGoal::IsFullyVisible, &input_tys[index]))
+        .and_then(|index| {
+            let param = first_ty_param(interner, &input_tys[index])?;
+            Some(UncoveredTyParam {
+                param,
+                input_ty: input_ty(index),
+            })
+        });
+    debug!(
+        "explain_orphan_check: non_local_ty={:?}, uncovered={:?}",
+        non_local_ty, uncovered
+    );
+
+    (non_local_ty, uncovered)
+}
+
+// Returns the position among the impl binders of the first type parameter of the impl that
+// appears in `ty`.
+fn first_ty_param<I: Interner>(interner: &I, ty: &Ty<I>) -> Option<usize> {
+    let mut finder = TyParamFinder {
+        interner,
+        found: None,
+    };
+    ty.visit_with(&mut finder, DebruijnIndex::INNERMOST);
+    finder.found
+}
+
+struct TyParamFinder<'i, I: Interner> {
+    interner: &'i I,
+    found: Option<usize>,
+}
+
+impl<'i, I: Interner> Visitor<'i, I> for TyParamFinder<'i, I> {
+    type Result = FindAny;
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'i, I, Result = Self::Result> {
+        self
+    }
+
+    fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) -> FindAny {
+        match ty.data(self.interner) {
+            TyData::BoundVar(bound_var) => match bound_var.shifted_out_to(outer_binder) {
+                Some(bound_var) => match bound_var.index_if_innermost() {
+                    Some(index) => {
+                        self.found = Some(index);
+                        FindAny::FOUND
+                    }
+                    None => FindAny::new(),
+                },
+                None => FindAny::new(),
+            },
+            _ => ty.super_visit_with(self.as_dyn(), outer_binder),
+        }
+    }
+}
@@ -1,17 +1,27 @@
-use crate::coherence::{CoherenceError, CoherenceSolver};
+use crate::coherence::{
+    ApplicabilityViolation, CoherenceError, CoherenceSolver, CoherenceWarning, OverlapKind,
+};
 use crate::ext::*;
+use crate::infer::InferenceTable;
 use crate::rust_ir::*;
-use crate::{goal_builder::GoalBuilder, Solution};
+use crate::{goal_builder::GoalBuilder, Guidance, Solution, Solver};
+use chalk_base::results::Fallible;
 use chalk_ir::cast::*;
+use chalk_ir::could_match::CouldMatch;
 use chalk_ir::fold::shift::Shift;
-use chalk_ir::interner::Interner;
+use chalk_ir::fold::Fold;
+use chalk_ir::interner::{HasInterner, Interner};
+use chalk_ir::visit::{Visit, Visitor};
+use chalk_ir::zip::{Zip, Zipper};
 use chalk_ir::*;
 use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap};
 
 impl<I: Interner> CoherenceSolver<'_, I> {
     pub(super) fn visit_specializations_of_trait(
         &self,
         mut record_specialization: impl FnMut(ImplId<I>, ImplId<I>),
+        mut warnings: Option<&mut Vec<CoherenceWarning<I>>>,
     ) -> Result<(), CoherenceError<I>> {
         // Ignore impls for marker traits as they are allowed to overlap.
         let trait_datum = self.db.trait_datum(self.trait_id);
@@ -19,34 +29,159 @@ impl<I: Interner> CoherenceSolver<'_, I> {
             return Ok(());
         }
 
-        // Iterate over every pair of impls for the same trait.
+        // Iterate over every pair of impls for the same trait that could overlap. The
+        // solver is shared by all the pairs, so that they can reuse each other's work.
         let impls = self.db.local_impls_to_coherence_check(self.trait_id);
-        for (l_id, r_id) in impls.into_iter().tuple_combinations() {
+        let solver = &mut self.solver_choice.into_solver();
+        let mut specializations = BTreeSet::new();
+        let mut overlaps = vec![];
+        for (l_id, r_id) in self.overlap_candidates(&impls) {
             let lhs = &self.db.impl_datum(l_id);
             let rhs = &self.db.impl_datum(r_id);
 
-            // Two negative impls never overlap.
-            if !lhs.is_positive() && !rhs.is_positive() {
+            // Two negative impls never overlap. Reservation impls count
+            // as positive impls here, even though they never hold.
+            if lhs.is_negative() && rhs.is_negative() {
                 continue;
             }
 
             // Check if the impls overlap, then if they do, check if one specializes
             // the other. Note that specialization can only run one way - if both
             // specialization checks return *either* true or false, that's an error.
-            if !self.disjoint(lhs, rhs) {
-                match (self.specializes(l_id, r_id), self.specializes(r_id, l_id)) {
-                    (true, false) => record_specialization(l_id, r_id),
-                    (false, true) => record_specialization(r_id, l_id),
+            // A reservation impl reserves the right to add exactly that impl, so it
+            // takes no part in specialization.
+            if !self.disjoint(solver, lhs, rhs, true) {
+                let reservation =
+                    lhs.polarity == Polarity::Reservation || rhs.polarity == Polarity::Reservation;
+                let specializes = if reservation {
+                    (false, false)
+                } else {
+                    (
+                        self.specializes(solver, l_id, r_id),
+                        self.specializes(solver, r_id, l_id),
+                    )
+                };
+                let (less_special, more_special) = match specializes {
+                    (true, false) => (l_id, r_id),
+                    (false, true) => (r_id, l_id),
                     (_, _) => {
-                        Err(CoherenceError::OverlappingImpls(self.trait_id))?;
+                        let (kind, witness) = self.overlap_witness(solver, lhs, rhs);
+
+                        // If the impls only overlap once upstream crates add
+                        // impls, this is a future-compatibility warning when the
+                        // caller asked for those.
+                        if let Some(warnings) = warnings.as_mut() {
+                            if kind == OverlapKind::Downstream
+                                && self.disjoint(solver, lhs, rhs, false)
+                            {
+                                warnings.push(CoherenceWarning::FutureOverlappingImpls {
+                                    trait_id: self.trait_id,
+                                    lhs: l_id,
+                                    rhs: r_id,
+                                    witness,
+                                });
+                                continue;
+                            }
+                        }
+
+                        overlaps.push((l_id, r_id, kind, witness));
+                        continue;
                     }
-                }
+                };
+                self.check_always_applicable(less_special, more_special)?;
+                specializations.insert((less_special, more_special));
+                record_specialization(less_special, more_special);
+            }
+        }
+
+        // Impls that overlap without specializing one another are still fine if some impl that
+        // specializes both applies to every trait reference that both apply to, like the third
+        // impl in:
+        //
+        //      impl<T> Foo for (T, u8) { }
+        //      impl<U> Foo for (u8, U) { }
+        //      impl Foo for (u8, u8) { }
+        //
+        // The impls then form a lattice rather than a tree.
+        for (l_id, r_id, kind, witness) in overlaps {
+            let covered = match self.intersection(l_id, r_id) {
+                Some(intersection) => specializations
+                    .iter()
+                    .filter(|&&(less_special, more_special)| {
+                        less_special == l_id && specializations.contains(&(r_id, more_special))
+                    })
+                    .any(|&(_, more_special)| {
+                        self.applies_to_all(solver, more_special, &intersection)
+                    }),
+                None => false,
+            };
+            if !covered {
+                return Err(CoherenceError::OverlappingImpls {
+                    trait_id: self.trait_id,
+                    lhs: l_id,
+                    rhs: r_id,
+                    witness,
+                    kind,
+                });
             }
         }
 
         Ok(())
     }
 
+    // Finds the pairs of impls that could overlap, in the order of `impls`. Comparing every pair
+    // would be quadratic in the number of impls, so we group the impls by the head of their self
+    // type, like `could_match` does: impls for `Vec<T>` and for `u32` never apply to the same
+    // type. An impl whose self type has no head, like `impl<T> Foo for T`, is compared with every
+    // other impl. The pairs are then filtered with `could_match` on all the trait parameters.
+    fn overlap_candidates(&self, impls: &[ImplId<I>]) -> Vec<(ImplId<I>, ImplId<I>)> {
+        let interner = self.db.interner();
+        let trait_refs: Vec<_> = impls
+            .iter()
+            .map(|&impl_id| {
+                let impl_datum = self.db.impl_datum(impl_id);
+                impl_datum.binders.skip_binders().trait_ref.clone()
+            })
+            .collect();
+
+        let mut blanket = vec![];
+        let mut by_head: HashMap<TypeName<I>, Vec<usize>> = HashMap::new();
+        for (index, trait_ref) in trait_refs.iter().enumerate() {
+            match trait_ref.self_type_parameter(interner).data(interner) {
+                TyData::Apply(apply) => by_head.entry(apply.name).or_default().push(index),
+                _ => blanket.push(index),
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize)> = blanket.iter().copied().tuple_combinations().collect();
+        for group in by_head.values() {
+            pairs.extend(group.iter().copied().tuple_combinations::<(_, _)>());
+            pairs.extend(
+                group
+                    .iter()
+                    .cartesian_product(&blanket)
+                    .map(|(&i, &j)| (i.min(j), i.max(j))),
+            );
+        }
+        pairs.retain(|&(l, r)| {
+            trait_refs[l]
+                .substitution
+                .parameters(interner)
+                .could_match(interner, trait_refs[r].substitution.parameters(interner))
+        });
+        pairs.sort();
+        debug!(
+            "overlap_candidates: {} of {} pairs",
+            pairs.len(),
+            impls.len() * impls.len().saturating_sub(1) / 2
+        );
+
+        pairs
+            .into_iter()
+            .map(|(l, r)| (impls[l], impls[r]))
+            .collect()
+    }
+
     // Test if the set of types that these two impls apply to overlap. If the test succeeds, these
     // two impls are disjoint.
     //
@@ -60,6 +195,9 @@ impl<I: Interner> CoherenceSolver<'_, I> {
     // want since we want to ensure that there is no overlap in *all* compatible worlds, not just
     // that there is no overlap in *some* compatible world.
     //
+    // Without `with_future_impls`, the compatible worlds are those where only downstream crates
+    // add impls, and upstream crates stay as they are (see `DomainGoal::FutureCompatible`).
+    //
     // Examples:
     //
     //  Impls:
@@ -80,7 +218,13 @@ impl<I: Interner> CoherenceSolver<'_, I> {
     //  Generates:
     //      not { compatible { exists<T> { exists<U> { Vec<T> = Vec<U>, T: Bar, U: Baz } } } }
     //
-    fn disjoint(&self, lhs: &ImplDatum<I>, rhs: &ImplDatum<I>) -> bool {
+    fn disjoint(
+        &self,
+        solver: &mut Solver<I>,
+        lhs: &ImplDatum<I>,
+        rhs: &ImplDatum<I>,
+        with_future_impls: bool,
+    ) -> bool {
         debug_heading!("overlaps(lhs={:#?}, rhs={:#?})", lhs, rhs);
 
         let interner = self.db.interner();
@@ -124,21 +268,22 @@ impl<I: Interner> CoherenceSolver<'_, I> {
         // over the joined binders. This is our query.
         let goal = Box::new(Goal::all(interner, params_goals.chain(wc_goals)))
             .quantify(interner, QuantifierKind::Exists, lhs_binders)
-            .quantify(interner, QuantifierKind::Exists, rhs_binders)
-            .compatible(interner)
-            .negate(interner);
+            .quantify(interner, QuantifierKind::Exists, rhs_binders);
+        let goal = if with_future_impls {
+            goal.compatible(interner)
+        } else {
+            goal.downstream_compatible(interner)
+        }
+        .negate(interner);
 
         let canonical_goal = &goal.into_closed_goal(interner);
-        let solution = self
-            .solver_choice
-            .into_solver()
-            .solve(self.db, canonical_goal);
+        let solution = solver.solve(self.db, canonical_goal);
         let result = match solution {
             // Goal was proven with a unique solution, so no impl was found that causes these two
             // to overlap
-            Some(Solution::Unique(_)) => true,
-            // Goal was ambiguous, so there *may* be overlap
-            Some(Solution::Ambig(_)) |
+            Some(Solution::Unique(..)) => true,
+            // Goal was ambiguous (or undefined), so there *may* be overlap
+            Some(Solution::Ambig(_)) | Some(Solution::Undefined(_)) |
             // Goal cannot be proven, so there is some impl that causes overlap
             None => false,
         };
@@ -146,6 +291,123 @@ impl<I: Interner> CoherenceSolver<'_, I> {
         result
     }
 
+    // Finds a trait reference that two overlapping impls both apply to. We ask the question of
+    // `disjoint` without the `compatible` modality, with the binders of both impls as the
+    // canonical variables of the goal:
+    //
+    //      exists<P0..Pn, Q0..Qo> { T0 = U0, ..., Tm = Um, WC_lhs, WC_rhs }
+    //
+    // If this has a unique solution, the impls definitely overlap, and the solution gives the
+    // witness. If it has no solution, the impls only overlap in some compatible world, so we look
+    // for a witness among the trait references that the impls unify on.
+    fn overlap_witness(
+        &self,
+        solver: &mut Solver<I>,
+        lhs: &ImplDatum<I>,
+        rhs: &ImplDatum<I>,
+    ) -> (OverlapKind, Option<Canonical<TraitRef<I>>>) {
+        let interner = self.db.interner();
+
+        let (kind, solution) = match self.solve_overlap(solver, lhs, rhs, true) {
+            solution @ Some(Solution::Unique(..)) => (OverlapKind::Definite, solution),
+            solution @ Some(_) => (OverlapKind::Ambiguous, solution),
+            None => (
+                OverlapKind::Downstream,
+                self.solve_overlap(solver, lhs, rhs, false),
+            ),
+        };
+        let subst = match solution {
+            Some(Solution::Unique(subst)) => Canonical {
+                binders: subst.binders,
+                value: subst.value.subst,
+            },
+            Some(Solution::Ambig(Guidance::Definite(subst)))
+            | Some(Solution::Ambig(Guidance::Suggested(subst))) => subst,
+            _ => return (kind, None),
+        };
+
+        // The first values of the substitution are those of the lhs binders.
+        let lhs_params = &subst.value.parameters(interner)[..lhs.binders.len(interner)];
+        let witness = lhs
+            .binders
+            .map_ref(|bound| bound.trait_ref.clone())
+            .substitute(interner, lhs_params);
+        debug!(
+            "overlap_witness: kind = {:?}, witness = {:?}",
+            kind, witness
+        );
+        (
+            kind,
+            Some(Canonical {
+                binders: subst.binders,
+                value: witness,
+            }),
+        )
+    }
+
+    fn solve_overlap(
+        &self,
+        solver: &mut Solver<I>,
+        lhs: &ImplDatum<I>,
+        rhs: &ImplDatum<I>,
+        with_where_clauses: bool,
+    ) -> Option<Solution<I>> {
+        let interner = self.db.interner();
+
+        // Refer to the rhs binders with the variables after those of the lhs.
+        let lhs_len = lhs.binders.len(interner);
+        let rhs_vars = Substitution::from(
+            interner,
+            rhs.binders
+                .binders
+                .iter(interner)
+                .zip(lhs_len..)
+                .map(|p| p.to_generic_arg(interner)),
+        );
+        let lhs_bound = lhs.binders.skip_binders();
+        let rhs_bound = rhs.binders.substitute(interner, &rhs_vars);
+
+        let params_goals = lhs_bound
+            .trait_ref
+            .substitution
+            .parameters(interner)
+            .iter()
+            .cloned()
+            .zip(
+                rhs_bound
+                    .trait_ref
+                    .substitution
+                    .parameters(interner)
+                    .iter()
+                    .cloned(),
+            )
+            .map(|(a, b)| GoalData::EqGoal(EqGoal { a, b }).intern(interner));
+        let wc_goals = lhs_bound
+            .where_clauses
+            .iter()
+            .chain(&rhs_bound.where_clauses)
+            .filter(|_| with_where_clauses)
+            .map(|wc| wc.clone().cast(interner));
+        let goal = Goal::all(interner, params_goals.chain(wc_goals));
+
+        let binders = CanonicalVarKinds::from(
+            interner,
+            lhs.binders
+                .binders
+                .iter(interner)
+                .chain(rhs.binders.binders.iter(interner))
+                .map(|kind| WithKind::new(kind.clone(), UniverseIndex::ROOT)),
+        );
+        let canonical_goal = UCanonical {
+            canonical: Canonical {
+                binders,
+                value: InEnvironment::new(&Environment::new(interner), goal),
+            },
+            universes: 1,
+        };
+        solver.solve(self.db, &canonical_goal)
+    }
+
     // Creates a goal which, if provable, means "more special" impl specializes the "less special" one.
     //
     // # General rule
@@ -193,11 +455,34 @@ impl<I: Interner> CoherenceSolver<'_, I> {
     //  }
     // }
     // ```
-    fn specializes(&self, less_special_id: ImplId<I>, more_special_id: ImplId<I>) -> bool {
+    fn specializes(
+        &self,
+        solver: &mut Solver<I>,
+        less_special_id: ImplId<I>,
+        more_special_id: ImplId<I>,
+    ) -> bool {
+        // An impl never specializes an impl of the other polarity: if a positive and a negative
+        // impl overlap, the trait would both hold and not hold for their overlap.
+        let less_special = &self.db.impl_datum(less_special_id);
         let more_special = &self.db.impl_datum(more_special_id);
+        if less_special.polarity != more_special.polarity {
+            return false;
+        }
+        self.applies_to_all(solver, less_special_id, &more_special.binders)
+    }
+
+    // Tests if the impl `less_special_id` applies to every trait reference that `more_special`
+    // describes, along with its where clauses. This is the goal of `specializes`, where
+    // `more_special` need not be an impl.
+    fn applies_to_all(
+        &self,
+        solver: &mut Solver<I>,
+        less_special_id: ImplId<I>,
+        more_special: &Binders<ImplDatumBound<I>>,
+    ) -> bool {
         let less_special = &self.db.impl_datum(less_special_id);
         debug_heading!(
-            "specializes(less_special={:#?}, more_special={:#?})",
+            "applies_to_all(less_special={:#?}, more_special={:#?})",
             less_special,
             more_special
         );
@@ -208,7 +493,7 @@ impl<I: Interner> CoherenceSolver<'_, I> {
 
         // forall<P0..Pn> { ... }
         let goal = gb.forall(
-            &more_special.binders,
+            more_special,
             less_special_id,
             |gb, _, more_special_impl, less_special_id| {
                 // if (WC_more) { ... }
@@ -254,17 +539,262 @@ impl<I: Interner> CoherenceSolver<'_, I> {
         );
 
         let canonical_goal = &goal.into_closed_goal(interner);
-        let result = match self
-            .solver_choice
-            .into_solver()
-            .solve(self.db, canonical_goal)
-        {
+        let result = match solver.solve(self.db, canonical_goal) {
             Some(sol) => sol.is_unique(),
             None => false,
         };
 
-        debug!("specializes: result = {:?}", result);
+        debug!("applies_to_all: result = {:?}", result);
+
+        result
+    }
+
+    // Computes the trait references that both impls apply to, along with the where clauses of
+    // both, under the binders of the variables that remain free. We unify the trait references of
+    // the impls; if that needs more than syntactic equality, like normalizing an associated type,
+    // we give up and return `None`.
+    fn intersection(
+        &self,
+        lhs_id: ImplId<I>,
+        rhs_id: ImplId<I>,
+    ) -> Option<Binders<ImplDatumBound<I>>> {
+        let interner = self.db.interner();
+        let lhs = self.db.impl_datum(lhs_id);
+        let rhs = self.db.impl_datum(rhs_id);
+
+        let mut infer = InferenceTable::new();
+        let lhs_bound = infer.instantiate_binders_existentially(interner, &lhs.binders);
+        let rhs_bound = infer.instantiate_binders_existentially(interner, &rhs.binders);
+        let result = infer
+            .unify(
+                interner,
+                &Environment::new(interner),
+                &lhs_bound.trait_ref,
+                &rhs_bound.trait_ref,
+            )
+            .ok()?;
+        if !result.goals.is_empty() {
+            return None;
+        }
+
+        let intersection = ImplDatumBound {
+            trait_ref: lhs_bound.trait_ref,
+            where_clauses: lhs_bound
+                .where_clauses
+                .into_iter()
+                .chain(rhs_bound.where_clauses)
+                .collect(),
+        };
+        let canonical = infer.canonicalize(interner, &intersection).quantified;
+        let binders = VariableKinds::from(
+            interner,
+            canonical
+                .binders
+                .iter(interner)
+                .map(|kind| kind.kind.clone()),
+        );
+        Some(Binders::new(binders, canonical.value))
+    }
+
+    // Checks that the impl `more_special_id` is always applicable with respect to the impl
+    // `less_special_id` that it specializes: whenever the less special impl applies, whether the
+    // more special one applies must only depend on the types involved, and not on lifetimes, which
+    // are erased by the time impls are selected in codegen. We match the trait reference of the
+    // less special impl against that of the more special one to find the values of the less
+    // special impl's parameters, and reject the specialization if
+    //
+    // * a parameter of the more special impl is the value of several parameters of the less
+    //   special impl, as in `impl<T> Foo for (T, T)` specializing `impl<A, B> Foo for (A, B)`, or
+    // * a lifetime parameter of the more special impl appears several times in those values, as
+    //   in `impl<'a> Foo for &'a &'a u8` specializing `impl<T> Foo for T`.
+    fn check_always_applicable(
+        &self,
+        less_special_id: ImplId<I>,
+        more_special_id: ImplId<I>,
+    ) -> Result<(), CoherenceError<I>> {
+        let interner = self.db.interner();
+        let less_special = self.db.impl_datum(less_special_id);
+        let more_special = self.db.impl_datum(more_special_id);
+
+        let mut params = ParamValues {
+            interner,
+            outer_binder: DebruijnIndex::INNERMOST,
+            values: vec![None; less_special.binders.len(interner)],
+        };
+        // A mismatch only means that we learn nothing about the parameters below it.
+        let _ = Zip::zip_with(
+            &mut params,
+            &less_special.binders.skip_binders().trait_ref,
+            &more_special.binders.skip_binders().trait_ref,
+        );
+        let values: Vec<_> = params.values.into_iter().flatten().collect();
+        debug!(
+            "check_always_applicable(less_special={:?}, more_special={:?}): values = {:?}",
+            less_special_id, more_special_id, values
+        );
+
+        let violation = values
+            .iter()
+            .enumerate()
+            .find(|&(i, value)| is_param(interner, value) && values[..i].contains(value))
+            .map(|(_, value)| ApplicabilityViolation::RepeatedParameter(value.clone()))
+            .or_else(|| {
+                let mut lifetimes = LifetimeParams {
+                    interner,
+                    found: vec![],
+                };
+                values.visit_with(&mut lifetimes, DebruijnIndex::INNERMOST);
+                lifetimes
+                    .found
+                    .iter()
+                    .enumerate()
+                    .find(|&(i, lifetime)| lifetimes.found[..i].contains(lifetime))
+                    .map(|(_, lifetime)| {
+                        ApplicabilityViolation::LifetimeConstraint(lifetime.clone())
+                    })
+            });
+
+        match violation {
+            Some(violation) => Err(CoherenceError::NotAlwaysApplicable {
+                trait_id: self.trait_id,
+                impl_id: more_special_id,
+                specialized: less_special_id,
+                violation,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+// Returns true if `arg` is a parameter of the impl, that is, a variable bound by the impl binders.
+fn is_param<I: Interner>(interner: &I, arg: &GenericArg<I>) -> bool {
+    let bound_var = match arg.data(interner) {
+        GenericArgData::Ty(ty) => ty.bound_var(interner),
+        GenericArgData::Lifetime(lifetime) => lifetime.bound_var(interner),
+        GenericArgData::Const(constant) => constant.bound_var(interner),
+    };
+    bound_var
+        .and_then(|bound_var| bound_var.index_if_innermost())
+        .is_some()
+}
+
+// Records the values that the parameters of a less special impl take when its trait reference is
+// matched against that of a more special impl. Only parts of the trait references with the same
+// structure are matched; the values are in terms of the parameters of the more special impl.
+struct ParamValues<'i, I: Interner> {
+    interner: &'i I,
+    outer_binder: DebruijnIndex,
+    values: Vec<Option<GenericArg<I>>>,
+}
+
+impl<'i, I: Interner> ParamValues<'i, I> {
+    // Records `value` if `bound_var` is a parameter of the less special impl. Returns false if it
+    // is not, so that the caller matches the values structurally instead.
+    fn record(&mut self, bound_var: BoundVar, value: GenericArgData<I>) -> bool {
+        let index = match bound_var
+            .shifted_out_to(self.outer_binder)
+            .and_then(|bound_var| bound_var.index_if_innermost())
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        // Values that refer to binders within the trait reference are not parameters of the more
+        // special impl, so we can't say anything about them.
+        let value = value
+            .intern(self.interner)
+            .shifted_out_to(self.interner, self.outer_binder)
+            .ok();
+        if self.values[index].is_none() {
+            self.values[index] = value;
+        }
+        true
+    }
+}
 
+impl<'i, I: Interner> Zipper<'i, I> for ParamValues<'i, I> {
+    fn zip_tys(&mut self, less_special: &Ty<I>, more_special: &Ty<I>) -> Fallible<()> {
+        let interner = self.interner;
+        if let TyData::BoundVar(bound_var) = less_special.data(interner) {
+            if self.record(*bound_var, GenericArgData::Ty(more_special.clone())) {
+                return Ok(());
+            }
+        }
+
+        match (less_special.data(interner), more_special.data(interner)) {
+            (TyData::Apply(less_special), TyData::Apply(more_special))
+                if less_special.name == more_special.name =>
+            {
+                Zip::zip_with(self, &less_special.substitution, &more_special.substitution)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn zip_lifetimes(
+        &mut self,
+        less_special: &Lifetime<I>,
+        more_special: &Lifetime<I>,
+    ) -> Fallible<()> {
+        if let LifetimeData::BoundVar(bound_var) = less_special.data(self.interner) {
+            self.record(*bound_var, GenericArgData::Lifetime(more_special.clone()));
+        }
+        Ok(())
+    }
+
+    fn zip_consts(&mut self, less_special: &Const<I>, more_special: &Const<I>) -> Fallible<()> {
+        if let ConstValue::BoundVar(bound_var) = &less_special.data(self.interner).value {
+            self.record(*bound_var, GenericArgData::Const(more_special.clone()));
+        }
+        Ok(())
+    }
+
+    fn zip_binders<T>(
+        &mut self,
+        less_special: &Binders<T>,
+        more_special: &Binders<T>,
+    ) -> Fallible<()>
+    where
+        T: HasInterner<Interner = I> + Zip<I> + Fold<I, I, Result = T>,
+    {
+        self.outer_binder.shift_in();
+        let result = Zip::zip_with(
+            self,
+            less_special.skip_binders(),
+            more_special.skip_binders(),
+        );
+        self.outer_binder.shift_out();
         result
     }
+
+    fn interner(&self) -> &'i I {
+        self.interner
+    }
+}
+
+// Collects the occurrences of the lifetime parameters of an impl.
+struct LifetimeParams<'i, I: Interner> {
+    interner: &'i I,
+    found: Vec<Lifetime<I>>,
+}
+
+impl<'i, I: Interner> Visitor<'i, I> for LifetimeParams<'i, I> {
+    type Result = ();
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'i, I, Result = Self::Result> {
+        self
+    }
+
+    fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &Lifetime<I>, outer_binder: DebruijnIndex) {
+        if let LifetimeData::BoundVar(bound_var) = lifetime.data(self.interner) {
+            if let Some(bound_var) = bound_var.shifted_out_to(outer_binder) {
+                if bound_var.index_if_innermost().is_some() {
+                    self.found.push(bound_var.to_lifetime(self.interner));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,99 @@
+//! Detects cycles in the declarations of traits. The solver cannot make
+//! sense of such declarations: goals involving them overflow or stay
+//! ambiguous, so they should be rejected before solving.
+
+use crate::clauses::super_traits_with_cycles;
+use crate::RustIrDatabase;
+use chalk_ir::fold::shift::Shift;
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+
+/// Returns a cycle of supertraits that goes through the trait
+/// `trait_id`, as the path of traits from `trait_id` back to itself.
+/// For example, given:
+///
+/// ```ignore
+/// trait A where Self: B { }
+/// trait B where Self: A { }
+/// ```
+///
+/// the cycle of `A` is `[A, B, A]`.
+pub fn super_trait_cycle<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+) -> Option<Vec<TraitId<I>>> {
+    let mut cycles = Vec::new();
+    super_traits_with_cycles(db, trait_id, &mut cycles);
+    cycles.into_iter().find(|cycle| cycle[0] == trait_id)
+}
+
+/// Returns a cycle of associated types that are required to be equal to
+/// one another when `Self` implements the trait `trait_id`, so that none
+/// of them can ever be normalized. The cycle is the path of associated
+/// types from the first one back to itself. For example, given:
+///
+/// ```ignore
+/// trait A { type X; type Y; }
+/// trait B where Self: A<X = <Self as A>::Y, Y = <Self as A>::X> { }
+/// ```
+///
+/// the cycle of `B` is `[A::X, A::Y, A::X]`.
+pub fn associated_ty_cycle<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+) -> Option<Vec<AssocTypeId<I>>> {
+    let interner = db.interner();
+
+    // Collect the equalities between projections that the trait and its
+    // supertraits require, like `<Self as A>::X = <Self as A>::Y`. We
+    // leave out higher-ranked trait references and where clauses, whose
+    // projections may differ for each of their parameters.
+    let super_trait_refs = super_traits_with_cycles(db, trait_id, &mut Vec::new());
+    let mut equalities = Vec::new();
+    for q_super_trait_ref in super_trait_refs.skip_binders() {
+        let super_trait_ref = match q_super_trait_ref.skip_binders().shifted_out(interner) {
+            Ok(super_trait_ref) => super_trait_ref,
+            Err(_) => continue,
+        };
+        let trait_datum = db.trait_datum(super_trait_ref.trait_id);
+        let where_clauses = trait_datum
+            .where_clauses()
+            .substitute(interner, &super_trait_ref.substitution);
+        for qwc in where_clauses {
+            if let Ok(WhereClause::AliasEq(AliasEq {
+                alias: AliasTy::Projection(from),
+                ty,
+            })) = qwc.skip_binders().shifted_out(interner)
+            {
+                if let TyData::Alias(AliasTy::Projection(to)) = ty.data(interner) {
+                    equalities.push((from, to.clone()));
+                }
+            }
+        }
+    }
+
+    // Follow the equalities from each projection, until we find one that
+    // leads back to it.
+    for (start, _) in &equalities {
+        let mut path = vec![start];
+        while let Some((_, next)) = equalities
+            .iter()
+            .find(|(from, _)| from == path[path.len() - 1])
+        {
+            if let Some(index) = path.iter().position(|&projection| projection == next) {
+                if index == 0 {
+                    path.push(next);
+                    return Some(
+                        path.iter()
+                            .map(|projection| projection.associated_ty_id)
+                            .collect(),
+                    );
+                }
+                break;
+            }
+            path.push(next);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,241 @@
+//! Tracking of the items that the solvers' cached results depend on.
+//!
+//! Whenever a solver computes the program clauses for a goal, it
+//! records which items of the program were consulted to do so. Cached
+//! results can then be kept across edits of the program: only those
+//! that (transitively) depend on a changed item are invalidated, see
+//! `Solver::invalidate`.
+
+use crate::rust_ir::*;
+use crate::RustIrDatabase;
+use chalk_derive::Encode;
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+/// An item of the program that program clauses can be derived from.
+///
+/// A trait stands for both its declaration and its *set* of impls:
+/// everything that enumerated the impls of a trait depends on the
+/// trait, so adding or removing an impl (or changing the trait
+/// reference it implements) must report the trait as changed too.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Encode)]
+pub enum ItemId<I: Interner> {
+    Trait(TraitId<I>),
+    Impl(ImplId<I>),
+    Adt(AdtId<I>),
+    FnDef(FnDefId<I>),
+    AssocTy(AssocTypeId<I>),
+    AssocTyValue(AssociatedTyValueId<I>),
+    OpaqueTy(OpaqueTyId<I>),
+
+    /// The "custom program clauses" of the program.
+    CustomClauses,
+
+    /// The mapping from well-known traits to the traits implementing
+    /// them.
+    WellKnownTraits,
+}
+
+/// The items that the program clauses of each environment are derived
+/// from, so that they are recorded once per environment rather than
+/// once per goal. Cloning an `EnvDependencies` yields a handle to the
+/// same data.
+#[derive(Debug)]
+pub(crate) struct EnvDependencies<I: Interner> {
+    data: Arc<Mutex<FxHashMap<Environment<I>, Items<I>>>>,
+}
+
+type Items<I> = Arc<FxHashSet<ItemId<I>>>;
+
+impl<I: Interner> EnvDependencies<I> {
+    pub(crate) fn new() -> Self {
+        EnvDependencies {
+            data: Arc::new(Mutex::new(FxHashMap::default())),
+        }
+    }
+
+    /// Returns the items that the program clauses of `environment` are
+    /// derived from, elaborating the environment to record them if it
+    /// was not seen before.
+    fn get_or_record(&self, db: &dyn RustIrDatabase<I>, environment: &Environment<I>) -> Items<I> {
+        if let Some(items) = self.data.lock().unwrap().get(environment) {
+            return items.clone();
+        }
+        // The lock is not held while elaborating, since that may look up
+        // other environments.
+        let recording = RecordingDatabase::new(db, self);
+        crate::clauses::program_clauses_for_env(&recording, environment);
+        let items = Arc::new(recording.into_items());
+        self.data
+            .lock()
+            .unwrap()
+            .insert(environment.clone(), items.clone());
+        items
+    }
+
+    /// Forgets the environments whose program clauses depend on one of
+    /// the `changed` items.
+    pub(crate) fn invalidate(&self, changed: &FxHashSet<ItemId<I>>) {
+        self.data
+            .lock()
+            .unwrap()
+            .retain(|_, items| items.is_disjoint(changed));
+    }
+}
+
+impl<I: Interner> Clone for EnvDependencies<I> {
+    fn clone(&self) -> Self {
+        EnvDependencies {
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// A `RustIrDatabase` that records each item that is looked up
+/// through it.
+#[derive(Debug)]
+pub(crate) struct RecordingDatabase<'db, I: Interner> {
+    db: &'db dyn RustIrDatabase<I>,
+    env_dependencies: &'db EnvDependencies<I>,
+    items: RefCell<FxHashSet<ItemId<I>>>,
+}
+
+impl<'db, I: Interner> RecordingDatabase<'db, I> {
+    pub(crate) fn new(
+        db: &'db dyn RustIrDatabase<I>,
+        env_dependencies: &'db EnvDependencies<I>,
+    ) -> Self {
+        RecordingDatabase {
+            db,
+            env_dependencies,
+            items: RefCell::new(FxHashSet::default()),
+        }
+    }
+
+    /// Returns the items looked up so far.
+    pub(crate) fn into_items(self) -> FxHashSet<ItemId<I>> {
+        self.items.into_inner()
+    }
+
+    fn record(&self, item: ItemId<I>) {
+        self.items.borrow_mut().insert(item);
+    }
+}
+
+impl<I: Interner> RustIrDatabase<I> for RecordingDatabase<'_, I> {
+    fn custom_clauses(&self) -> Vec<ProgramClause<I>> {
+        self.record(ItemId::CustomClauses);
+        self.db.custom_clauses()
+    }
+
+    fn associated_ty_data(&self, ty: AssocTypeId<I>) -> Arc<AssociatedTyDatum<I>> {
+        self.record(ItemId::AssocTy(ty));
+        self.db.associated_ty_data(ty)
+    }
+
+    fn trait_datum(&self, trait_id: TraitId<I>) -> Arc<TraitDatum<I>> {
+        self.record(ItemId::Trait(trait_id));
+        self.db.trait_datum(trait_id)
+    }
+
+    fn adt_datum(&self, adt_id: AdtId<I>) -> Arc<AdtDatum<I>> {
+        self.record(ItemId::Adt(adt_id));
+        self.db.adt_datum(adt_id)
+    }
+
+    fn fn_def_datum(&self, fn_def_id: FnDefId<I>) -> Arc<FnDefDatum<I>> {
+        self.record(ItemId::FnDef(fn_def_id));
+        self.db.fn_def_datum(fn_def_id)
+    }
+
+    fn impl_datum(&self, impl_id: ImplId<I>) -> Arc<ImplDatum<I>> {
+        self.record(ItemId::Impl(impl_id));
+        self.db.impl_datum(impl_id)
+    }
+
+    fn associated_ty_value(&self, id: AssociatedTyValueId<I>) -> Arc<AssociatedTyValue<I>> {
+        self.record(ItemId::AssocTyValue(id));
+        self.db.associated_ty_value(id)
+    }
+
+    fn opaque_ty_data(&self, id: OpaqueTyId<I>) -> Arc<OpaqueTyDatum<I>> {
+        self.record(ItemId::OpaqueTy(id));
+        self.db.opaque_ty_data(id)
+    }
+
+    fn impls_for_trait(
+        &self,
+        trait_id: TraitId<I>,
+        parameters: &[GenericArg<I>],
+    ) -> Vec<ImplId<I>> {
+        self.record(ItemId::Trait(trait_id));
+        self.db.impls_for_trait(trait_id, parameters)
+    }
+
+    fn local_impls_to_coherence_check(&self, trait_id: TraitId<I>) -> Vec<ImplId<I>> {
+        self.record(ItemId::Trait(trait_id));
+        self.db.local_impls_to_coherence_check(trait_id)
+    }
+
+    fn impl_provided_for(&self, auto_trait_id: TraitId<I>, adt_id: AdtId<I>) -> bool {
+        self.record(ItemId::Trait(auto_trait_id));
+        self.db.impl_provided_for(auto_trait_id, adt_id)
+    }
+
+    fn force_impl_for(&self, well_known: WellKnownTrait, ty: &TyData<I>) -> Option<bool> {
+        self.db.force_impl_for(well_known, ty)
+    }
+
+    fn well_known_trait_id(&self, well_known_trait: WellKnownTrait) -> Option<TraitId<I>> {
+        self.record(ItemId::WellKnownTraits);
+        self.db.well_known_trait_id(well_known_trait)
+    }
+
+    fn program_clauses_for_env(&self, environment: &Environment<I>) -> ProgramClauses<I> {
+        let clauses = self.db.program_clauses_for_env(environment);
+        // The database may have cached the clauses, in which case the
+        // items they are elaborated from are not looked up, so we record
+        // those separately.
+        let items = self.env_dependencies.get_or_record(self.db, environment);
+        self.items.borrow_mut().extend(items.iter().copied());
+        clauses
+    }
+
+    fn interner(&self) -> &I {
+        self.db.interner()
+    }
+
+    fn is_object_safe(&self, trait_id: TraitId<I>) -> bool {
+        self.record(ItemId::Trait(trait_id));
+        self.db.is_object_safe(trait_id)
+    }
+
+    fn may_dangle_parameters(&self, impl_id: ImplId<I>) -> Vec<usize> {
+        self.record(ItemId::Impl(impl_id));
+        self.db.may_dangle_parameters(impl_id)
+    }
+
+    fn item_name(&self, item: ItemId<I>) -> Option<String> {
+        self.db.item_name(item)
+    }
+}
+
+/// Computes the program clauses for `goal` (see
+/// `program_clauses_for_goal`), adding the items they were derived
+/// from to `dependencies`. The items are recorded even if the goal
+/// flounders.
+pub(crate) fn program_clauses_for_goal_with_dependencies<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    env_dependencies: &EnvDependencies<I>,
+    environment: &Environment<I>,
+    goal: &DomainGoal<I>,
+    dependencies: &mut FxHashSet<ItemId<I>>,
+) -> Result<Vec<ProgramClause<I>>, chalk_base::results::Floundered> {
+    let recording = RecordingDatabase::new(db, env_dependencies);
+    let clauses = crate::clauses::program_clauses_for_goal(&recording, environment, goal);
+    dependencies.extend(recording.into_items());
+    clauses
+}
@@ -0,0 +1,265 @@
+//! Drop-check: computes what must still be live when a value is dropped.
+//!
+//! Dropping a value runs the destructor of its type, if the type has a
+//! `Drop` impl, and then drops each of its fields. A destructor may
+//! access the data of the parameters of its type, so those must still be
+//! live, unless the parameter is marked `#[may_dangle]` in the `Drop`
+//! impl, like `T` in:
+//!
+//! ```ignore
+//! impl<#[may_dangle] T> Drop for Vec<T> { }
+//! ```
+//!
+//! Such a destructor promises not to access the data of `T`, but it may
+//! still drop values of type `T`, so what must be live to drop a `T` must
+//! be live to drop a `Vec<T>` as well.
+
+use crate::rust_ir::WellKnownTrait;
+use crate::RustIrDatabase;
+use chalk_ir::cast::{Cast, CastTo};
+use chalk_ir::fold::shift::Shift;
+use chalk_ir::interner::Interner;
+use chalk_ir::visit::{SuperVisit, Visit, Visitor};
+use chalk_ir::*;
+
+/// The result of `dropck_outlives`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DropckOutlives<I: Interner> {
+    /// The types and lifetimes that must still be live when a value of
+    /// the type is dropped.
+    pub kinds: Vec<GenericArg<I>>,
+}
+
+impl<I: Interner> DropckOutlives<I> {
+    /// Returns the constraints under which it is sound to drop a value of
+    /// the type at the end of `lifetime`, that is, when `lifetime` is
+    /// dead: each of the `kinds` must outlive `lifetime`. The constraints
+    /// on a type are constraints on the lifetimes that appear in it,
+    /// except for the types whose lifetimes we do not know, like type
+    /// parameters and projections.
+    pub fn constraints(&self, interner: &I, lifetime: &Lifetime<I>) -> Vec<Constraint<I>> {
+        let mut collector = OutlivesCollector {
+            interner,
+            lifetime,
+            constraints: Vec::new(),
+        };
+        self.kinds
+            .visit_with(&mut collector, DebruijnIndex::INNERMOST);
+        collector.constraints
+    }
+}
+
+/// Computes what must still be live when a value of type `ty` is dropped.
+/// The result is conservative: if we do not know what dropping a type
+/// does, like for a type parameter, we require all of its data to be live.
+pub fn dropck_outlives<I: Interner>(db: &dyn RustIrDatabase<I>, ty: &Ty<I>) -> DropckOutlives<I> {
+    let mut collector = DropckCollector {
+        db,
+        adts: Vec::new(),
+        kinds: Vec::new(),
+    };
+    collector.add_drop(ty);
+    DropckOutlives {
+        kinds: collector.kinds,
+    }
+}
+
+struct DropckCollector<'db, I: Interner> {
+    db: &'db dyn RustIrDatabase<I>,
+
+    /// The ADTs whose fields we are dropping, to stop at recursive ADTs.
+    adts: Vec<(AdtId<I>, Ty<I>)>,
+
+    kinds: Vec<GenericArg<I>>,
+}
+
+impl<I: Interner> DropckCollector<'_, I> {
+    /// Requires `kind` to be live.
+    fn add_live(&mut self, kind: impl CastTo<GenericArg<I>>) {
+        let kind = kind.cast(self.db.interner());
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+    }
+
+    /// Requires what must be live to drop a value of type `ty` to be live.
+    fn add_drop(&mut self, ty: &Ty<I>) {
+        let interner = self.db.interner();
+        match ty.data(interner) {
+            TyData::Apply(apply) => match apply.name {
+                // These types do not own any data, so dropping them does
+                // nothing.
+                TypeName::Scalar(_)
+                | TypeName::Str
+                | TypeName::Never
+                | TypeName::Raw(_)
+                | TypeName::Ref(_)
+                | TypeName::FnDef(_)
+                | TypeName::Error => {}
+
+                // Dropping these types drops their elements.
+                TypeName::Tuple(_) | TypeName::Slice => {
+                    for ty in apply.type_parameters(interner) {
+                        self.add_drop(&ty);
+                    }
+                }
+
+                TypeName::Adt(adt_id) => self.add_adt_drop(adt_id, ty, &apply.substitution),
+
+                TypeName::AssociatedType(_) | TypeName::OpaqueType(_) => self.add_live(ty.clone()),
+            },
+
+            // Function pointers do not own any data.
+            TyData::Function(_) => {}
+
+            // We do not know what dropping these types does.
+            TyData::Placeholder(_)
+            | TyData::Dyn(_)
+            | TyData::Alias(_)
+            | TyData::BoundVar(_)
+            | TyData::InferenceVar(_) => self.add_live(ty.clone()),
+        }
+    }
+
+    fn add_adt_drop(&mut self, adt_id: AdtId<I>, ty: &Ty<I>, substitution: &Substitution<I>) {
+        let interner = self.db.interner();
+
+        // If we are already dropping the fields of `ty`, we have accounted
+        // for them. If we are dropping the fields of the same ADT with
+        // other parameters, it grows as it recurses, like
+        // `struct Foo<T> { foo: Box<Foo<Vec<T>>> }`, so we give up and
+        // require all of its data to be live.
+        if self.adts.iter().any(|(_, seen)| seen == ty) {
+            return;
+        }
+        if self.adts.iter().any(|&(seen, _)| seen == adt_id) {
+            self.add_live(ty.clone());
+            return;
+        }
+
+        // The destructor of the ADT may access the data of its parameters,
+        // except for those that are `#[may_dangle]`.
+        for impl_id in self.drop_impls(adt_id, ty) {
+            let impl_datum = self.db.impl_datum(impl_id);
+            let may_dangle_parameters = self.db.may_dangle_parameters(impl_id);
+            let impl_self_ty = impl_datum
+                .binders
+                .skip_binders()
+                .trait_ref
+                .self_type_parameter(interner);
+            let impl_parameters = match impl_self_ty.data(interner) {
+                TyData::Apply(apply) => apply.substitution.clone(),
+                _ => continue,
+            };
+            for (parameter, arg) in impl_parameters
+                .iter(interner)
+                .zip(substitution.iter(interner))
+            {
+                let bound_var = match parameter.data(interner) {
+                    GenericArgData::Ty(ty) => ty.bound_var(interner),
+                    GenericArgData::Lifetime(lifetime) => lifetime.bound_var(interner),
+                    GenericArgData::Const(constant) => constant.bound_var(interner),
+                };
+                let may_dangle = match bound_var.and_then(BoundVar::index_if_innermost) {
+                    Some(index) => may_dangle_parameters.contains(&index),
+                    None => false,
+                };
+                match arg.data(interner) {
+                    GenericArgData::Ty(ty) if may_dangle => self.add_drop(ty),
+                    GenericArgData::Lifetime(_) | GenericArgData::Const(_) if may_dangle => {}
+                    _ => self.add_live(arg.clone()),
+                }
+            }
+        }
+
+        // Then, dropping the ADT drops its fields.
+        let adt_datum = self.db.adt_datum(adt_id);
+        let fields = adt_datum.binders.substitute(interner, substitution).fields;
+        self.adts.push((adt_id, ty.clone()));
+        for field in &fields {
+            self.add_drop(field);
+        }
+        self.adts.pop();
+    }
+
+    /// The `Drop` impls for the ADT `adt_id` that could apply to `ty`.
+    fn drop_impls(&self, adt_id: AdtId<I>, ty: &Ty<I>) -> Vec<ImplId<I>> {
+        let interner = self.db.interner();
+        let drop_trait_id = match self.db.well_known_trait_id(WellKnownTrait::DropTrait) {
+            Some(drop_trait_id) => drop_trait_id,
+            None => return vec![],
+        };
+        self.db
+            .impls_for_trait(drop_trait_id, &[ty.clone().cast(interner)])
+            .into_iter()
+            .filter(|&impl_id| {
+                let impl_datum = self.db.impl_datum(impl_id);
+                impl_datum.is_positive() && impl_datum.self_type_adt_id(interner) == Some(adt_id)
+            })
+            .collect()
+    }
+}
+
+/// Collects the constraints that the types and lifetimes it visits
+/// outlive `lifetime`.
+struct OutlivesCollector<'i, I: Interner> {
+    interner: &'i I,
+    lifetime: &'i Lifetime<I>,
+    constraints: Vec<Constraint<I>>,
+}
+
+impl<'i, I: Interner> OutlivesCollector<'i, I> {
+    fn push(&mut self, constraint: Constraint<I>) {
+        if !self.constraints.contains(&constraint) {
+            self.constraints.push(constraint);
+        }
+    }
+}
+
+impl<'i, I: Interner> Visitor<'i, I> for OutlivesCollector<'i, I> {
+    type Result = ();
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'i, I, Result = Self::Result> {
+        self
+    }
+
+    fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) {
+        let interner = self.interner;
+        let is_known = match ty.data(interner) {
+            TyData::Apply(apply) => !matches!(
+                apply.name,
+                TypeName::AssociatedType(_) | TypeName::OpaqueType(_)
+            ),
+            TyData::Function(_) => true,
+            TyData::Placeholder(_)
+            | TyData::Dyn(_)
+            | TyData::Alias(_)
+            | TyData::BoundVar(_)
+            | TyData::InferenceVar(_) => false,
+        };
+        if !is_known {
+            if let Ok(ty) = ty.shifted_out_to(interner, outer_binder) {
+                self.push(Constraint::TypeOutlives(ty, self.lifetime.clone()));
+                return;
+            }
+        }
+
+        // Types that refer to binders within the kinds, like the
+        // projection in `for<'a> fn(<&'a T as Foo>::Out)`, have no meaning
+        // outside of them, so we require the types and lifetimes that they
+        // are made of to outlive `lifetime` instead.
+        ty.super_visit_with(self, outer_binder);
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &Lifetime<I>, outer_binder: DebruijnIndex) {
+        // Lifetimes bound within the kinds, like `'a` in `for<'a> fn(&'a u8)`,
+        // need not outlive anything.
+        if let Ok(lifetime) = lifetime.shifted_out_to(self.interner, outer_binder) {
+            self.push(Constraint::Outlives(lifetime, self.lifetime.clone()));
+        }
+    }
+}
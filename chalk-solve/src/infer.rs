@@ -150,6 +150,12 @@ impl<I: Interner> InferenceTable<I> {
         }
     }
 
+    /// Returns the representative of the variables that `var` has been
+    /// unified with.
+    pub(crate) fn inference_var_root(&mut self, var: InferenceVar) -> InferenceVar {
+        self.unify.find(EnaVariable::from(var)).into()
+    }
+
     /// Returns true if `var` has been bound.
     pub(crate) fn var_is_bound(&mut self, var: InferenceVar) -> bool {
         match self.unify.probe_value(EnaVariable::from(var)) {
@@ -1,5 +1,6 @@
 #![deny(rust_2018_idioms)]
 
+use crate::dependencies::ItemId;
 use crate::rust_ir::*;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
@@ -16,12 +17,16 @@ mod test_macros;
 pub mod clauses;
 pub mod coherence;
 mod coinductive_goal;
+pub mod cycles;
+pub mod dependencies;
+pub mod dropck;
 pub mod ext;
 pub mod goal_builder;
 mod infer;
 #[cfg(feature = "recursive-solver")]
 pub mod recursive;
 pub mod rust_ir;
+pub mod select;
 mod solve;
 pub mod split;
 pub mod wf;
@@ -101,11 +106,46 @@ pub trait RustIrDatabase<I: Interner>: Debug {
 
     /// Check if a trait is object safe
     fn is_object_safe(&self, trait_id: TraitId<I>) -> bool;
+
+    /// Returns the indices of the parameters of the `Drop` impl `impl_id`
+    /// that are marked `#[may_dangle]`, like `T` in
+    /// `impl<#[may_dangle] T> Drop for Vec<T>`. The destructor promises
+    /// not to access data of these parameters, so they need not be live
+    /// when a value is dropped (see `dropck`). By default, no parameter
+    /// is marked.
+    fn may_dangle_parameters(&self, _impl_id: ImplId<I>) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Returns a name for `item` that does not depend on its id, such as
+    /// the name it is declared with, or `None` if the program has no
+    /// such item (e.g., because it was removed since the id was
+    /// obtained). Items without a name of their own, like impls, may be
+    /// given an empty name. Used to check that saved solver work still
+    /// applies to the items it refers to (see `Solver::load`).
+    ///
+    /// By default, every item exists and has an empty name, so saved
+    /// work is only checked against the program clauses of its items.
+    /// Databases whose items can be removed, or whose ids can refer to
+    /// other items from one process to the next, should override this.
+    fn item_name(&self, _item: ItemId<I>) -> Option<String> {
+        Some(String::new())
+    }
 }
 
 pub use clauses::program_clauses_for_env;
 
 pub use solve::Guidance;
+pub use solve::LoadError;
+pub use solve::Overflow;
+pub use solve::OverflowLimit;
 pub use solve::Solution;
 pub use solve::Solver;
 pub use solve::SolverChoice;
+pub use solve::SolverStats;
+#[cfg(feature = "slg-solver")]
+pub use solve::StrandSchedule;
+#[cfg(feature = "slg-solver")]
+pub use solve::TableBudget;
+pub use solve::TruncationLimit;
+pub use solve::TruncationPolicy;
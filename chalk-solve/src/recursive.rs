@@ -1,27 +1,43 @@
+mod cache;
 mod fulfill;
 mod search_graph;
 mod stack;
 
+use self::cache::{Cache, CacheEntry};
 use self::fulfill::Fulfill;
 use self::search_graph::{DepthFirstNumber, SearchGraph};
 use self::stack::{Stack, StackDepth};
-use crate::clauses::program_clauses_for_goal;
-use crate::{Guidance, RustIrDatabase, Solution};
+use crate::dependencies::{program_clauses_for_goal_with_dependencies, EnvDependencies, ItemId};
+use crate::{Guidance, Overflow, OverflowLimit, RustIrDatabase, Solution};
 use chalk_base::results::{Fallible, Floundered, NoSolution};
+use chalk_base::stats::SolverStats;
 use chalk_ir::interner::Interner;
 use chalk_ir::{
-    Binders, Canonical, ClausePriority, ConstrainedSubst, DomainGoal, Environment, GenericArg,
-    Goal, GoalData, InEnvironment, ProgramClause, ProgramClauseData, ProgramClauseImplication,
-    UCanonical, VariableKinds,
+    Binders, Canonical, CanonicalVarKinds, ClausePriority, ClauseSource, ConstrainedSubst,
+    DomainGoal, Environment, GenericArg, Goal, GoalData, InEnvironment, ProgramClause,
+    ProgramClauseData, ProgramClauseImplication, UCanonical, VariableKinds,
 };
-use rustc_hash::FxHashMap;
+use rustc_hash::FxHashSet;
+use tracing::{debug_span, info_span, instrument};
 
-type UCanonicalGoal<I> = UCanonical<InEnvironment<Goal<I>>>;
+pub(crate) type UCanonicalGoal<I> = UCanonical<InEnvironment<Goal<I>>>;
+
+/// A solution, along with the source of the program clause it was
+/// proven with, if it is unique and there is a single such clause.
+pub(crate) type SourcedSolution<I> = (Solution<I>, Option<ClauseSource<I>>);
 
 pub(crate) struct RecursiveContext<I: Interner> {
     stack: Stack,
     search_graph: SearchGraph<I>,
-    cache: FxHashMap<UCanonicalGoal<I>, Fallible<Solution<I>>>,
+    cache: Cache<I>,
+    env_dependencies: EnvDependencies<I>,
+    stats: SolverStats,
+
+    /// Goals that overflowed, each listed once; see `Solver::overflows`.
+    overflows: Vec<Overflow<I>>,
+
+    /// The entries of `overflows`, to keep them unique.
+    overflows_set: FxHashSet<Overflow<I>>,
 
     caching_enabled: bool,
 }
@@ -34,6 +50,10 @@ pub(crate) struct RecursiveContext<I: Interner> {
 pub(crate) struct Solver<'me, I: Interner> {
     program: &'me dyn RustIrDatabase<I>,
     context: &'me mut RecursiveContext<I>,
+
+    /// The search graph node of the goal whose subgoals are currently
+    /// being solved; the dependencies of each subgoal are added to it.
+    current: Option<DepthFirstNumber>,
 }
 
 /// The `minimums` struct is used while solving to track whether we encountered
@@ -41,6 +61,11 @@ pub(crate) struct Solver<'me, I: Interner> {
 #[derive(Copy, Clone, Debug)]
 struct Minimums {
     positive: DepthFirstNumber,
+
+    /// Whether some goal overflowed in the process. The results that
+    /// depend on it are not cached, since they depend on how deep in
+    /// the stack the overflowing goal was reached.
+    overflowed: bool,
 }
 
 /// An extension trait for merging `Result`s
@@ -68,11 +93,44 @@ impl<I: Interner> RecursiveContext<I> {
         RecursiveContext {
             stack: Stack::new(overflow_depth),
             search_graph: SearchGraph::new(),
-            cache: FxHashMap::default(),
+            cache: Cache::new(),
+            env_dependencies: EnvDependencies::new(),
+            stats: SolverStats::default(),
+            overflows: vec![],
+            overflows_set: FxHashSet::default(),
             caching_enabled,
         }
     }
 
+    /// Creates a new context that shares the cache of this one, so
+    /// that results cached by either are visible to both.
+    pub(crate) fn fork(&self) -> Self {
+        RecursiveContext {
+            stack: Stack::new(self.stack.overflow_depth()),
+            search_graph: SearchGraph::new(),
+            cache: self.cache.clone(),
+            env_dependencies: self.env_dependencies.clone(),
+            stats: SolverStats::default(),
+            overflows: vec![],
+            overflows_set: FxHashSet::default(),
+            caching_enabled: self.caching_enabled,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> &SolverStats {
+        &self.stats
+    }
+
+    pub(crate) fn overflows(&self) -> &[Overflow<I>] {
+        &self.overflows
+    }
+
+    pub(crate) fn reset_stats(&mut self) {
+        self.stats.reset();
+        self.overflows.clear();
+        self.overflows_set.clear();
+    }
+
     pub(crate) fn solver<'me>(
         &'me mut self,
         program: &'me dyn RustIrDatabase<I>,
@@ -80,8 +138,36 @@ impl<I: Interner> RecursiveContext<I> {
         Solver {
             program,
             context: self,
+            current: None,
         }
     }
+
+    /// Removes every cached result that depends on one of the
+    /// `changed` items. The cache may be shared with other contexts
+    /// (see `fork`), which are affected as well.
+    pub(crate) fn invalidate(&mut self, changed: &FxHashSet<ItemId<I>>) -> usize {
+        assert!(self.stack.is_empty());
+        self.env_dependencies.invalidate(changed);
+        let invalidated = self.cache.invalidate(changed);
+        self.stats.invalidations += invalidated;
+        invalidated
+    }
+
+    /// Returns a copy of the cached results, along with the items each
+    /// of them depends on.
+    pub(crate) fn export_cache(&self) -> Vec<(UCanonicalGoal<I>, CacheEntry<I>)> {
+        self.cache.export()
+    }
+
+    /// Seeds the cache with the given results, keeping the results
+    /// already cached for the same goals. Returns the number of results
+    /// added.
+    pub(crate) fn import_cache(
+        &mut self,
+        entries: impl IntoIterator<Item = (UCanonicalGoal<I>, CacheEntry<I>)>,
+    ) -> usize {
+        self.cache.import(entries)
+    }
 }
 
 impl<'me, I: Interner> Solver<'me, I> {
@@ -103,11 +189,11 @@ impl<'me, I: Interner> Solver<'me, I> {
     pub(crate) fn solve_root_goal(
         &mut self,
         canonical_goal: &UCanonicalGoal<I>,
-    ) -> Fallible<Solution<I>> {
-        debug!("solve_root_goal(canonical_goal={:?})", canonical_goal);
+    ) -> Fallible<SourcedSolution<I>> {
+        debug!(goal = ?canonical_goal, "solve_root_goal");
         assert!(self.context.stack.is_empty());
         let minimums = &mut Minimums::new();
-        self.solve_goal(canonical_goal.clone(), minimums)
+        self.solve_goal_with_source(canonical_goal.clone(), minimums)
     }
 
     /// Attempt to solve a goal that has been fully broken down into leaf form
@@ -118,18 +204,41 @@ impl<'me, I: Interner> Solver<'me, I> {
         goal: UCanonicalGoal<I>,
         minimums: &mut Minimums,
     ) -> Fallible<Solution<I>> {
-        info_heading!("solve_goal({:?})", goal);
+        self.solve_goal_with_source(goal, minimums)
+            .map(|(solution, _)| solution)
+    }
 
+    #[instrument(level = "info", skip(self, minimums))]
+    fn solve_goal_with_source(
+        &mut self,
+        goal: UCanonicalGoal<I>,
+        minimums: &mut Minimums,
+    ) -> Fallible<SourcedSolution<I>> {
         // First check the cache.
-        if let Some(value) = self.context.cache.get(&goal) {
-            debug!("solve_reduced_goal: cache hit, value={:?}", value);
-            return value.clone();
+        if let Some((value, dependencies)) = self.context.cache.get(&goal) {
+            debug!(result = ?value, "cache hit");
+            self.context.stats.cache_hits += 1;
+            if let Some(current) = self.current {
+                self.context.search_graph[current]
+                    .dependencies
+                    .extend(dependencies.iter().cloned());
+            }
+            return value;
         }
+        self.context.stats.cache_misses += 1;
 
         // Next, check if the goal is in the search tree already.
         if let Some(dfn) = self.context.search_graph.lookup(&goal) {
+            if let Some(current) = self.current {
+                self.context
+                    .search_graph
+                    .propagate_dependencies(dfn, current);
+            }
+
             // Check if this table is still on the stack.
             if let Some(depth) = self.context.search_graph[dfn].stack_depth {
+                self.context.stats.cycles += 1;
+
                 // Is this a coinductive goal? If so, that is success,
                 // so we can return normally. Note that this return is
                 // not tabled.
@@ -141,10 +250,13 @@ impl<'me, I: Interner> Solver<'me, I> {
                         constraints: vec![],
                     };
                     debug!("applying coinductive semantics");
-                    return Ok(Solution::Unique(Canonical {
-                        value,
-                        binders: goal.canonical.binders,
-                    }));
+                    return Ok((
+                        Solution::Unique(Canonical {
+                            value,
+                            binders: goal.canonical.binders,
+                        }),
+                        None,
+                    ));
                 }
 
                 self.context.stack[depth].flag_cycle();
@@ -156,16 +268,34 @@ impl<'me, I: Interner> Solver<'me, I> {
             let previous_solution = self.context.search_graph[dfn].solution.clone();
             let previous_solution_priority = self.context.search_graph[dfn].solution_priority;
             info!(
-                "solve_goal: cycle detected, previous solution {:?} with prio {:?}",
-                previous_solution, previous_solution_priority
+                result = ?previous_solution,
+                priority = ?previous_solution_priority,
+                "cycle detected, using previous solution"
             );
             previous_solution
         } else {
             // Otherwise, push the goal onto the stack and create a table.
-            // The initial result for this table is error.
+            // The initial result for this table is error. If the stack is
+            // already full, we give up on the goal instead; the result is
+            // not tabled, since it depends on how deep we are, and neither
+            // are those of the goals that depend on it.
+            if self.context.stack.is_full() {
+                self.context.stats.overflows += 1;
+                let limit = OverflowLimit::Depth(self.context.stack.overflow_depth());
+                self.record_overflow(goal, limit);
+                minimums.overflowed = true;
+                return Ok((Solution::Ambig(Guidance::Unknown), None));
+            }
             let depth = self.context.stack.push(self.program, &goal);
             let dfn = self.context.search_graph.insert(&goal, depth);
+            let parent = self.current.replace(dfn);
             let subgoal_minimums = self.solve_new_subgoal(goal, depth, dfn);
+            self.current = parent;
+            if let Some(parent) = parent {
+                self.context
+                    .search_graph
+                    .propagate_dependencies(dfn, parent);
+            }
             self.context.search_graph[dfn].links = subgoal_minimums;
             self.context.search_graph[dfn].stack_depth = None;
             self.context.stack.pop(depth);
@@ -180,10 +310,15 @@ impl<'me, I: Interner> Solver<'me, I> {
             // cache now. This is a sort of hack to alleviate the
             // worst of the repeated work that we do during tabling.
             if subgoal_minimums.positive >= dfn {
-                if self.context.caching_enabled {
+                if subgoal_minimums.overflowed {
+                    debug!(
+                        "solve_reduced_goal: SCC head encountered, rolling back as it overflowed"
+                    );
+                    self.context.search_graph.rollback_to(dfn);
+                } else if self.context.caching_enabled {
                     self.context
                         .search_graph
-                        .move_to_cache(dfn, &mut self.context.cache);
+                        .move_to_cache(dfn, &self.context.cache);
                     debug!("solve_reduced_goal: SCC head encountered, moving to cache");
                 } else {
                     debug!(
@@ -193,24 +328,18 @@ impl<'me, I: Interner> Solver<'me, I> {
                 }
             }
 
-            info!("solve_goal: solution = {:?} prio {:?}", result, priority);
+            info!(result = ?result, priority = ?priority, "solved goal");
             result
         }
     }
 
+    #[instrument(level = "debug", skip(self, canonical_goal), fields(goal = ?canonical_goal))]
     fn solve_new_subgoal(
         &mut self,
         canonical_goal: UCanonicalGoal<I>,
         depth: StackDepth,
         dfn: DepthFirstNumber,
     ) -> Minimums {
-        debug_heading!(
-            "solve_new_subgoal(canonical_goal={:?}, depth={:?}, dfn={:?})",
-            canonical_goal,
-            depth,
-            dfn,
-        );
-
         // We start with `answer = None` and try to solve the goal. At the end of the iteration,
         // `answer` will be updated with the result of the solving process. If we detect a cycle
         // during the solving process, we cache `answer` and try to solve the goal again. We repeat
@@ -255,17 +384,18 @@ impl<'me, I: Interner> Solver<'me, I> {
                     let (prog_solution, prog_prio) = {
                         debug_heading!("prog_clauses");
 
-                        let prog_clauses = self.program_clauses_for_goal(environment, &goal);
+                        let prog_clauses = self.program_clauses_for_goal(environment, goal, dfn);
                         match prog_clauses {
                             Ok(clauses) => {
                                 self.solve_from_clauses(&canonical_goal, clauses, minimums)
                             }
-                            Err(Floundered) => {
-                                (Ok(Solution::Ambig(Guidance::Unknown)), ClausePriority::High)
-                            }
+                            Err(Floundered) => (
+                                Ok((Solution::Ambig(Guidance::Unknown), None)),
+                                ClausePriority::High,
+                            ),
                         }
                     };
-                    debug!("prog_solution={:?}", prog_solution);
+                    debug!(result = ?prog_solution, "solved from program clauses");
 
                     (prog_solution, prog_prio)
                 }
@@ -284,8 +414,9 @@ impl<'me, I: Interner> Solver<'me, I> {
             };
 
             debug!(
-                "solve_new_subgoal: loop iteration result = {:?} with minimums {:?}",
-                current_answer, minimums
+                result = ?current_answer,
+                ?minimums,
+                "solve_new_subgoal: loop iteration"
             );
 
             if !self.context.stack[depth].read_and_reset_cycle_flag() {
@@ -301,6 +432,7 @@ impl<'me, I: Interner> Solver<'me, I> {
 
             let (current_answer, current_prio) = combine_with_priorities_for_goal(
                 self.program.interner(),
+                &canonical_goal.canonical.binders,
                 &canonical_goal.canonical.value.goal,
                 old_answer.clone(),
                 old_prio,
@@ -316,7 +448,7 @@ impl<'me, I: Interner> Solver<'me, I> {
             }
 
             let current_answer_is_ambig = match &current_answer {
-                Ok(s) => s.is_ambig(),
+                Ok((s, _)) => s.is_ambig(),
                 Err(_) => false,
             };
 
@@ -339,13 +471,17 @@ impl<'me, I: Interner> Solver<'me, I> {
         &mut self,
         canonical_goal: &UCanonicalGoal<I>,
         minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority) {
-        debug_heading!("solve_via_simplification({:?})", canonical_goal);
+    ) -> (Fallible<SourcedSolution<I>>, ClausePriority) {
+        let _span = debug_span!("solve_via_simplification", goal = ?canonical_goal).entered();
         let (mut fulfill, subst, goal) = Fulfill::new(self, canonical_goal);
         if let Err(e) = fulfill.push_goal(&goal.environment, goal.goal) {
             return (Err(e), ClausePriority::High);
         }
-        (fulfill.solve(subst, minimums), ClausePriority::High)
+        let solution = fulfill.solve(subst, minimums);
+        (
+            solution.map(|solution| (solution, None)),
+            ClausePriority::High,
+        )
     }
 
     /// See whether we can solve a goal by implication on any of the given
@@ -356,17 +492,22 @@ impl<'me, I: Interner> Solver<'me, I> {
         canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
         clauses: C,
         minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority)
+    ) -> (Fallible<SourcedSolution<I>>, ClausePriority)
     where
         C: IntoIterator<Item = ProgramClause<I>>,
     {
         let mut cur_solution = None;
         for program_clause in clauses {
-            debug_heading!("clause={:?}", program_clause);
+            let _span = debug_span!("clause", clause = ?program_clause).entered();
 
             // If we have a completely ambiguous answer, it's not going to get better, so stop
-            if cur_solution == Some((Solution::Ambig(Guidance::Unknown), ClausePriority::High)) {
-                return (Ok(Solution::Ambig(Guidance::Unknown)), ClausePriority::High);
+            if let Some(((Solution::Ambig(Guidance::Unknown), _), ClausePriority::High)) =
+                cur_solution
+            {
+                return (
+                    Ok((Solution::Ambig(Guidance::Unknown), None)),
+                    ClausePriority::High,
+                );
             }
 
             match program_clause.data(self.program.interner()) {
@@ -380,11 +521,12 @@ impl<'me, I: Interner> Solver<'me, I> {
                         minimums,
                     );
                     if let (Ok(solution), priority) = res {
-                        debug!("ok: solution={:?} prio={:?}", solution, priority);
+                        debug!(result = ?solution, ?priority, "ok");
                         cur_solution = Some(match cur_solution {
                             None => (solution, priority),
                             Some((cur, cur_priority)) => combine_with_priorities(
                                 self.program.interner(),
+                                &canonical_goal.canonical.binders,
                                 &canonical_goal.canonical.value.goal,
                                 cur,
                                 cur_priority,
@@ -399,11 +541,12 @@ impl<'me, I: Interner> Solver<'me, I> {
                 ProgramClauseData::ForAll(implication) => {
                     let res = self.solve_via_implication(canonical_goal, implication, minimums);
                     if let (Ok(solution), priority) = res {
-                        debug!("ok: solution={:?} prio={:?}", solution, priority);
+                        debug!(result = ?solution, ?priority, "ok");
                         cur_solution = Some(match cur_solution {
                             None => (solution, priority),
                             Some((cur, cur_priority)) => combine_with_priorities(
                                 self.program.interner(),
+                                &canonical_goal.canonical.binders,
                                 &canonical_goal.canonical.value.goal,
                                 cur,
                                 cur_priority,
@@ -426,23 +569,19 @@ impl<'me, I: Interner> Solver<'me, I> {
         canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
         clause: &Binders<ProgramClauseImplication<I>>,
         minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority) {
-        info_heading!(
-            "solve_via_implication(\
-             \n    canonical_goal={:?},\
-             \n    clause={:?})",
-            canonical_goal,
-            clause
-        );
+    ) -> (Fallible<SourcedSolution<I>>, ClausePriority) {
+        let _span =
+            info_span!("solve_via_implication", goal = ?canonical_goal, clause = ?clause).entered();
         let interner = self.program.interner();
         let (mut fulfill, subst, goal) = Fulfill::new(self, canonical_goal);
         let ProgramClauseImplication {
             consequence,
             conditions,
             priority: _,
+            source,
         } = fulfill.instantiate_binders_existentially(clause);
 
-        debug!("the subst is {:?}", subst);
+        debug!(?subst, "instantiated clause");
 
         if let Err(e) = fulfill.unify(&goal.environment, &goal.goal, &consequence) {
             return (Err(e), ClausePriority::High);
@@ -456,18 +595,39 @@ impl<'me, I: Interner> Solver<'me, I> {
         }
 
         // and then try to solve
-        (
-            fulfill.solve(subst, minimums),
-            clause.skip_binders().priority,
-        )
+        let solution = fulfill.solve(subst, minimums).map(|solution| {
+            let source = Some(source).filter(|_| solution.is_unique());
+            (solution, source)
+        });
+        (solution, clause.skip_binders().priority)
     }
 
+    /// Returns the program clauses for `goal`, recording the items they
+    /// were derived from as dependencies of the node `dfn`.
     fn program_clauses_for_goal(
-        &self,
+        &mut self,
         environment: &Environment<I>,
         goal: &DomainGoal<I>,
+        dfn: DepthFirstNumber,
     ) -> Result<Vec<ProgramClause<I>>, Floundered> {
-        program_clauses_for_goal(self.program, environment, goal)
+        let env_dependencies = &self.context.env_dependencies;
+        let dependencies = &mut self.context.search_graph[dfn].dependencies;
+        program_clauses_for_goal_with_dependencies(
+            self.program,
+            env_dependencies,
+            environment,
+            goal,
+            dependencies,
+        )
+    }
+
+    /// Records that we gave up on `goal` because it reached `limit`.
+    fn record_overflow(&mut self, goal: UCanonicalGoal<I>, limit: OverflowLimit) {
+        info!(?goal, ?limit, "overflow");
+        let overflow = Overflow { goal, limit };
+        if self.context.overflows_set.insert(overflow.clone()) {
+            self.context.overflows.push(overflow);
+        }
     }
 }
 
@@ -486,12 +646,13 @@ fn calculate_inputs<I: Interner>(
 
 fn combine_with_priorities_for_goal<I: Interner>(
     interner: &I,
+    binders: &CanonicalVarKinds<I>,
     goal: &Goal<I>,
-    a: Fallible<Solution<I>>,
+    a: Fallible<SourcedSolution<I>>,
     prio_a: ClausePriority,
-    b: Fallible<Solution<I>>,
+    b: Fallible<SourcedSolution<I>>,
     prio_b: ClausePriority,
-) -> (Fallible<Solution<I>>, ClausePriority) {
+) -> (Fallible<SourcedSolution<I>>, ClausePriority) {
     let domain_goal = match goal.data(interner) {
         GoalData::DomainGoal(domain_goal) => domain_goal,
         _ => {
@@ -502,7 +663,7 @@ fn combine_with_priorities_for_goal<I: Interner>(
     match (a, b) {
         (Ok(a), Ok(b)) => {
             let (solution, prio) =
-                combine_with_priorities(interner, domain_goal, a, prio_a, b, prio_b);
+                combine_with_priorities(interner, binders, domain_goal, a, prio_a, b, prio_b);
             (Ok(solution), prio)
         }
         (Ok(solution), Err(_)) => (Ok(solution), prio_a),
@@ -513,12 +674,13 @@ fn combine_with_priorities_for_goal<I: Interner>(
 
 fn combine_with_priorities<I: Interner>(
     interner: &I,
+    binders: &CanonicalVarKinds<I>,
     domain_goal: &DomainGoal<I>,
-    a: Solution<I>,
+    a: SourcedSolution<I>,
     prio_a: ClausePriority,
-    b: Solution<I>,
+    b: SourcedSolution<I>,
     prio_b: ClausePriority,
-) -> (Solution<I>, ClausePriority) {
+) -> (SourcedSolution<I>, ClausePriority) {
     match (prio_a, prio_b, a, b) {
         (ClausePriority::High, ClausePriority::Low, higher, lower)
         | (ClausePriority::Low, ClausePriority::High, lower, higher) => {
@@ -528,30 +690,49 @@ fn combine_with_priorities<I: Interner>(
             // solution overriding a general low-priority one. Currently inputs
             // only matter for projections; in a goal like `AliasEq(<?0 as
             // Trait>::Type = ?1)`, ?0 is the input.
-            let inputs_higher = calculate_inputs(interner, domain_goal, &higher);
-            let inputs_lower = calculate_inputs(interner, domain_goal, &lower);
+            let inputs_higher = calculate_inputs(interner, domain_goal, &higher.0);
+            let inputs_lower = calculate_inputs(interner, domain_goal, &lower.0);
             if inputs_higher == inputs_lower {
                 debug!(
-                    "preferring solution: {:?} over {:?} because of higher prio",
-                    higher, lower
+                    result = ?higher,
+                    discarded = ?lower,
+                    "preferring solution because of higher prio"
                 );
                 (higher, ClausePriority::High)
             } else {
-                (higher.combine(lower, interner), ClausePriority::High)
+                (
+                    combine(interner, binders, higher, lower),
+                    ClausePriority::High,
+                )
             }
         }
-        (_, _, a, b) => (a.combine(b, interner), prio_a),
+        (_, _, a, b) => (combine(interner, binders, a, b), prio_a),
     }
 }
 
+/// Combines two solutions with `Solution::combine`. The result is only
+/// proven with a single clause if both solutions were proven with it.
+fn combine<I: Interner>(
+    interner: &I,
+    binders: &CanonicalVarKinds<I>,
+    (a, source_a): SourcedSolution<I>,
+    (b, source_b): SourcedSolution<I>,
+) -> SourcedSolution<I> {
+    let solution = a.combine(b, binders, interner);
+    let source = source_a.filter(|_| source_a == source_b && solution.is_unique());
+    (solution, source)
+}
+
 impl Minimums {
     fn new() -> Self {
         Minimums {
             positive: DepthFirstNumber::MAX,
+            overflowed: false,
         }
     }
 
     fn update_from(&mut self, minimums: Minimums) {
         self.positive = ::std::cmp::min(self.positive, minimums.positive);
+        self.overflowed |= minimums.overflowed;
     }
 }
@@ -0,0 +1,85 @@
+use super::{SourcedSolution, UCanonicalGoal};
+use crate::dependencies::ItemId;
+use chalk_base::results::Fallible;
+use chalk_ir::interner::Interner;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::{Arc, Mutex};
+
+/// The cache of the recursive solver. Only final results are stored
+/// here (i.e., results of goals whose strongly connected component in
+/// the search graph has been fully solved), so the cache can safely be
+/// shared between solvers running on different threads. Cloning a
+/// `Cache` yields a handle to the same data.
+///
+/// Each result is stored along with the items of the program it
+/// depends on, so that it can be invalidated when one of them changes.
+pub(crate) struct Cache<I: Interner> {
+    data: Arc<Mutex<FxHashMap<UCanonicalGoal<I>, CacheEntry<I>>>>,
+}
+
+pub(crate) type CacheEntry<I> = (Fallible<SourcedSolution<I>>, Arc<FxHashSet<ItemId<I>>>);
+
+impl<I: Interner> Cache<I> {
+    pub(crate) fn new() -> Self {
+        Cache {
+            data: Arc::new(Mutex::new(FxHashMap::default())),
+        }
+    }
+
+    pub(crate) fn insert(
+        &self,
+        goal: UCanonicalGoal<I>,
+        result: Fallible<SourcedSolution<I>>,
+        dependencies: Arc<FxHashSet<ItemId<I>>>,
+    ) {
+        self.data
+            .lock()
+            .unwrap()
+            .insert(goal, (result, dependencies));
+    }
+
+    pub(crate) fn get(&self, goal: &UCanonicalGoal<I>) -> Option<CacheEntry<I>> {
+        self.data.lock().unwrap().get(goal).cloned()
+    }
+
+    /// Removes every result that depends on one of the `changed`
+    /// items, and returns how many were removed.
+    pub(crate) fn invalidate(&self, changed: &FxHashSet<ItemId<I>>) -> usize {
+        let mut data = self.data.lock().unwrap();
+        let len = data.len();
+        data.retain(|_, (_, dependencies)| dependencies.is_disjoint(changed));
+        len - data.len()
+    }
+
+    /// Returns a copy of every cached result.
+    pub(crate) fn export(&self) -> Vec<(UCanonicalGoal<I>, CacheEntry<I>)> {
+        self.data
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(goal, entry)| (goal.clone(), entry.clone()))
+            .collect()
+    }
+
+    /// Adds the results for the goals that are not cached yet, and
+    /// returns how many were added.
+    pub(crate) fn import(
+        &self,
+        entries: impl IntoIterator<Item = (UCanonicalGoal<I>, CacheEntry<I>)>,
+    ) -> usize {
+        let mut data = self.data.lock().unwrap();
+        let len = data.len();
+        for (goal, entry) in entries {
+            data.entry(goal).or_insert(entry);
+        }
+        data.len() - len
+    }
+}
+
+impl<I: Interner> Clone for Cache<I> {
+    fn clone(&self) -> Self {
+        Cache {
+            data: self.data.clone(),
+        }
+    }
+}
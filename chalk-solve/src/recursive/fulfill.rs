@@ -6,7 +6,7 @@ use crate::infer::{
     InferenceTable, ParameterEnaVariable, ParameterEnaVariableExt,
 };
 use crate::recursive::{Minimums, Solver};
-use crate::solve::{truncate, Guidance, Solution};
+use crate::solve::{truncate, Guidance, OverflowLimit, Solution, TruncationLimit};
 use chalk_base::results::{Fallible, NoSolution};
 use chalk_ir::cast::Cast;
 use chalk_ir::fold::Fold;
@@ -18,6 +18,11 @@ use chalk_ir::{
 };
 use rustc_hash::FxHashSet;
 use std::fmt::Debug;
+use tracing::debug_span;
+
+/// Obligations bigger than this are not proven; the solution is
+/// ambiguous instead.
+const MAX_OBLIGATION_SIZE: usize = 30;
 
 enum Outcome {
     Complete,
@@ -123,32 +128,30 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
 
     fn push_obligation(&mut self, obligation: Obligation<I>) {
         // truncate to avoid overflows
-        match &obligation {
-            Obligation::Prove(goal) => {
-                if truncate::needs_truncation(
-                    self.solver.program.interner(),
-                    &mut self.infer,
-                    30,
-                    goal,
-                ) {
-                    // the goal is too big. Record that we should return Ambiguous
-                    self.cannot_prove = true;
-                    return;
-                }
-            }
-            Obligation::Refute(goal) => {
-                if truncate::needs_truncation(
-                    self.solver.program.interner(),
-                    &mut self.infer,
-                    30,
-                    goal,
-                ) {
-                    // the goal is too big. Record that we should return Ambiguous
-                    self.cannot_prove = true;
-                    return;
-                }
-            }
+        let goal = match &obligation {
+            Obligation::Prove(goal) | Obligation::Refute(goal) => goal,
         };
+        if truncate::needs_truncation(
+            self.solver.program.interner(),
+            &mut self.infer,
+            TruncationLimit::Size(MAX_OBLIGATION_SIZE),
+            goal,
+        ) {
+            // the goal is too big. Record that we should return Ambiguous
+            let interner = self.solver.program.interner();
+            let canonical_goal = self.infer.canonicalize(interner, goal).quantified;
+            let goal = self
+                .infer
+                .u_canonicalize(interner, &canonical_goal)
+                .quantified;
+            self.solver.context.stats.truncations += 1;
+            self.solver.record_overflow(
+                goal,
+                OverflowLimit::Goal(TruncationLimit::Size(MAX_OBLIGATION_SIZE)),
+            );
+            self.cannot_prove = true;
+            return;
+        }
         self.obligations.push(obligation);
     }
 
@@ -163,9 +166,7 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
         let UnificationResult { goals, constraints } =
             self.infer
                 .unify(self.solver.program.interner(), environment, a, b)?;
-        debug!("unify({:?}, {:?}) succeeded", a, b);
-        debug!("unify: goals={:?}", goals);
-        debug!("unify: constraints={:?}", constraints);
+        debug!(?a, ?b, ?goals, ?constraints, "unify succeeded");
         self.constraints.extend(constraints);
         let interner = self.solver.program.interner();
         for goal in goals {
@@ -181,7 +182,7 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
         environment: &Environment<I>,
         goal: Goal<I>,
     ) -> Fallible<()> {
-        debug!("push_goal({:?}, {:?})", goal, environment);
+        debug!(?goal, ?environment, "push_goal");
         let interner = self.interner();
         match goal.data(interner) {
             GoalData::Quantified(QuantifierKind::ForAll, subgoal) => {
@@ -247,7 +248,11 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
         })
     }
 
-    fn refute(&mut self, goal: &InEnvironment<Goal<I>>) -> Fallible<NegativeSolution> {
+    fn refute(
+        &mut self,
+        goal: &InEnvironment<Goal<I>>,
+        minimums: &mut Minimums,
+    ) -> Fallible<NegativeSolution> {
         let canonicalized = match self
             .infer
             .invert_then_canonicalize(self.solver.program.interner(), goal)
@@ -267,8 +272,10 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
         } = self
             .infer
             .u_canonicalize(self.solver.program.interner(), &canonicalized);
-        let mut minimums = Minimums::new(); // FIXME -- minimums here seems wrong
-        if let Ok(solution) = self.solver.solve_goal(quantified, &mut minimums) {
+        let mut negative_minimums = Minimums::new(); // FIXME -- minimums here seems wrong
+        let result = self.solver.solve_goal(quantified, &mut negative_minimums);
+        minimums.overflowed |= negative_minimums.overflowed;
+        if let Ok(solution) = result {
             if solution.is_unique() {
                 Err(NoSolution)
             } else {
@@ -298,10 +305,7 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
             .infer
             .instantiate_canonical(self.solver.program.interner(), &subst);
 
-        debug!(
-            "fulfill::apply_solution: adding constraints {:?}",
-            constraints
-        );
+        debug!(?constraints, "fulfill::apply_solution: adding constraints");
         self.constraints.extend(constraints);
 
         // We use the empty environment for unification here because we're
@@ -323,7 +327,7 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
     }
 
     fn fulfill(&mut self, minimums: &mut Minimums) -> Fallible<Outcome> {
-        debug_heading!("fulfill(obligations={:#?})", self.obligations);
+        let _span = debug_span!("fulfill", obligations = ?self.obligations).entered();
 
         // Try to solve all the obligations. We do this via a fixed-point
         // iteration. We try to solve each obligation in turn. Anything which is
@@ -335,7 +339,7 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
 
         while progress {
             progress = false;
-            debug_heading!("start of round, {} obligations", self.obligations.len());
+            let _span = debug_span!("round", obligations = self.obligations.len()).entered();
 
             // Take the list of `obligations` to solve this round and replace it
             // with an empty vector. Iterate through each obligation to solve
@@ -363,19 +367,19 @@ impl<'s, 'db, I: Interner> Fulfill<'s, 'db, I> {
                         solution.is_ambig()
                     }
                     Obligation::Refute(ref goal) => {
-                        let answer = self.refute(goal)?;
+                        let answer = self.refute(goal, minimums)?;
                         answer == NegativeSolution::Ambiguous
                     }
                 };
 
                 if ambiguous {
-                    debug!("ambiguous result: {:?}", obligation);
+                    debug!(?obligation, "ambiguous result");
                     obligations.push(obligation);
                 }
             }
 
             self.obligations.extend(obligations.drain(..));
-            debug!("end of round, {} obligations left", self.obligations.len());
+            debug!(obligations = self.obligations.len(), "end of round");
         }
 
         // At the end of this process, `self.obligations` should have
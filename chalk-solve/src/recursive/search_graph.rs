@@ -1,14 +1,17 @@
+use std::mem;
 use std::ops::Add;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::sync::Arc;
 use std::usize;
 
+use super::cache::Cache;
 use super::stack::StackDepth;
-use super::{Minimums, UCanonicalGoal};
-use crate::Solution;
+use super::{Minimums, SourcedSolution, UCanonicalGoal};
+use crate::dependencies::ItemId;
 use chalk_base::results::{Fallible, NoSolution};
 use chalk_ir::{interner::Interner, ClausePriority};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 pub(super) struct SearchGraph<I: Interner> {
     indices: FxHashMap<UCanonicalGoal<I>, DepthFirstNumber>,
@@ -23,7 +26,7 @@ pub(super) struct DepthFirstNumber {
 pub(super) struct Node<I: Interner> {
     pub(crate) goal: UCanonicalGoal<I>,
 
-    pub(crate) solution: Fallible<Solution<I>>,
+    pub(crate) solution: Fallible<SourcedSolution<I>>,
     pub(crate) solution_priority: ClausePriority,
 
     /// This is `Some(X)` if we are actively exploring this node, or
@@ -35,6 +38,11 @@ pub(super) struct Node<I: Interner> {
     /// from the stack, it contains the DFN of the minimal ancestor
     /// that the table reached (or MAX if no cycle was encountered).
     pub(crate) links: Minimums,
+
+    /// The items of the program that the solution depends on: those
+    /// the program clauses of this goal were derived from, plus the
+    /// dependencies of all the subgoals solved along the way.
+    pub(crate) dependencies: FxHashSet<ItemId<I>>,
 }
 
 impl<I: Interner> SearchGraph<I> {
@@ -68,7 +76,11 @@ impl<I: Interner> SearchGraph<I> {
             solution: Err(NoSolution),
             solution_priority: ClausePriority::High,
             stack_depth: Some(stack_depth),
-            links: Minimums { positive: dfn },
+            links: Minimums {
+                positive: dfn,
+                overflowed: false,
+            },
+            dependencies: FxHashSet::default(),
         };
         self.nodes.push(node);
         let previous_index = self.indices.insert(goal.clone(), dfn);
@@ -78,25 +90,36 @@ impl<I: Interner> SearchGraph<I> {
 
     /// Clears all nodes with a depth-first number greater than or equal `dfn`.
     pub(crate) fn rollback_to(&mut self, dfn: DepthFirstNumber) {
-        debug!("rollback_to(dfn={:?})", dfn);
+        debug!(?dfn, "rollback_to");
         self.indices.retain(|_key, value| *value < dfn);
         self.nodes.truncate(dfn.index);
     }
 
     /// Removes all nodes with a depth-first-number greater than or
     /// equal to `dfn`, adding their final solutions into the cache.
-    pub(crate) fn move_to_cache(
-        &mut self,
-        dfn: DepthFirstNumber,
-        cache: &mut FxHashMap<UCanonicalGoal<I>, Fallible<Solution<I>>>,
-    ) {
-        debug!("move_to_cache(dfn={:?})", dfn);
+    ///
+    /// The dependencies of the node `dfn` include those of all nodes
+    /// after it, since they are its subgoals; those are the
+    /// dependencies that get cached for the whole strongly connected
+    /// component, as the results of its nodes depend on each other.
+    pub(crate) fn move_to_cache(&mut self, dfn: DepthFirstNumber, cache: &Cache<I>) {
+        debug!(?dfn, "move_to_cache");
         self.indices.retain(|_key, value| *value < dfn);
+        let dependencies = Arc::new(mem::take(&mut self[dfn].dependencies));
         for node in self.nodes.drain(dfn.index..) {
             assert!(node.stack_depth.is_none());
             assert!(node.links.positive >= dfn);
-            debug!("caching solution {:?} for {:?}", node.solution, node.goal);
-            cache.insert(node.goal, node.solution);
+            debug!(goal = ?node.goal, result = ?node.solution, "caching solution");
+            cache.insert(node.goal, node.solution, dependencies.clone());
+        }
+    }
+
+    /// Adds the dependencies of the node `from` to those of the node `to`.
+    pub(crate) fn propagate_dependencies(&mut self, from: DepthFirstNumber, to: DepthFirstNumber) {
+        if from != to {
+            let dependencies = mem::take(&mut self[from].dependencies);
+            self[to].dependencies.extend(dependencies.iter().cloned());
+            self[from].dependencies = dependencies;
         }
     }
 }
@@ -38,10 +38,19 @@ impl Stack {
         }
     }
 
+    pub(crate) fn overflow_depth(&self) -> usize {
+        self.overflow_depth
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
 
+    /// True if pushing another goal would exceed the overflow depth.
+    pub(crate) fn is_full(&self) -> bool {
+        self.entries.len() >= self.overflow_depth
+    }
+
     pub(crate) fn push<I: Interner>(
         &mut self,
         program: &dyn RustIrDatabase<I>,
@@ -51,12 +60,9 @@ impl Stack {
             depth: self.entries.len(),
         };
 
-        if depth.depth >= self.overflow_depth {
-            // This shoudl perhaps be a result or something, though
-            // really I'd prefer to move to subgoal abstraction for
-            // guaranteeing termination. -nmatsakis
-            panic!("overflow depth reached")
-        }
+        // Callers check `is_full` first and report an overflow
+        // instead of pushing.
+        assert!(!self.is_full(), "overflow depth reached");
 
         let coinductive_goal = goal.is_coinductive(program);
         self.entries.push(StackEntry {
@@ -34,6 +34,10 @@ impl<I: Interner> ImplDatum<I> {
         self.polarity.is_positive()
     }
 
+    pub fn is_negative(&self) -> bool {
+        self.polarity.is_negative()
+    }
+
     pub fn trait_id(&self) -> TraitId<I> {
         self.binders.skip_binders().trait_ref.trait_id
     }
@@ -555,13 +559,25 @@ pub struct OpaqueTyDatumBound<I: Interner> {
 pub enum Polarity {
     Positive,
     Negative,
+
+    /// A reservation impl like `#[reservation] impl Foo for Bar` reserves
+    /// the right to add the impl later: it never holds, but it counts
+    /// when checking that impls do not overlap.
+    Reservation,
 }
 
 impl Polarity {
     pub fn is_positive(&self) -> bool {
         match *self {
             Polarity::Positive => true,
-            Polarity::Negative => false,
+            Polarity::Negative | Polarity::Reservation => false,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match *self {
+            Polarity::Negative => true,
+            Polarity::Positive | Polarity::Reservation => false,
         }
     }
 }
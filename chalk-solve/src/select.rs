@@ -0,0 +1,399 @@
+//! Impl selection: given a trait reference whose types are all known,
+//! figures out *which* impl (or other source) implements it. This is
+//! what an interpreter or code generator needs to find the code to
+//! run for a method call, as opposed to the solver, which only says
+//! whether the trait reference holds.
+
+use crate::coherence::{CoherenceError, CoherenceSolver, SpecializationPriorities};
+use crate::infer::ucanonicalize::{UCanonicalized, UniverseMap};
+use crate::infer::InferenceTable;
+use crate::rust_ir::AssociatedTyValueId;
+use crate::solve::{Solution, Solver, SolverChoice};
+use crate::split::Split;
+use crate::RustIrDatabase;
+use chalk_ir::cast::Cast;
+use chalk_ir::interner::Interner;
+use chalk_ir::visit::visitors::FindAny;
+use chalk_ir::visit::{Visit, Visitor};
+use chalk_ir::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The result of selecting the impl for a trait reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selection<I: Interner> {
+    /// The trait reference is implemented by the impl `impl_id`;
+    /// `substitution` gives the values of the generic parameters of
+    /// the impl. The substitution is canonical because the values of
+    /// lifetime parameters are not always determined.
+    Impl {
+        impl_id: ImplId<I>,
+        substitution: Canonical<Substitution<I>>,
+    },
+
+    /// The trait reference is proven by something other than an impl
+    /// written by the user, like the where clauses in the environment
+    /// or a builtin impl. The source is never `ClauseSource::Impl`.
+    Source(ClauseSource<I>),
+
+    /// More than one candidate might apply.
+    Ambiguous,
+}
+
+/// The result of selecting the associated type value for a projection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValueSelection<I: Interner> {
+    /// The projection is normalized with the associated type value
+    /// `value_id`; `substitution` gives the values of its generic
+    /// parameters, the parameters of the associated type followed
+    /// by those of the impl.
+    Value {
+        value_id: AssociatedTyValueId<I>,
+        substitution: Canonical<Substitution<I>>,
+    },
+
+    /// The trait reference of the projection is proven by something
+    /// other than an impl written by the user (see `Selection::Source`).
+    Source(ClauseSource<I>),
+
+    /// More than one candidate might apply.
+    Ambiguous,
+}
+
+/// Selects impls for fully known trait references. The selector keeps
+/// a solver and the specialization priorities of the traits it has
+/// seen, so reuse it to select the impls of many trait references in
+/// the same program.
+pub struct ImplSelector<'db, I: Interner> {
+    db: &'db dyn RustIrDatabase<I>,
+    solver_choice: SolverChoice,
+    solver: Solver<I>,
+    priorities: BTreeMap<TraitId<I>, Arc<SpecializationPriorities<I>>>,
+}
+
+impl<'db, I: Interner> ImplSelector<'db, I> {
+    /// Constructs a new `ImplSelector`.
+    pub fn new(db: &'db dyn RustIrDatabase<I>, solver_choice: SolverChoice) -> Self {
+        Self {
+            db,
+            solver_choice,
+            solver: solver_choice.into_solver(),
+            priorities: BTreeMap::new(),
+        }
+    }
+
+    /// Selects the impl that implements `trait_ref` in `environment`.
+    /// The trait reference must not contain any inference or bound
+    /// variables, but it may contain placeholders from the environment.
+    ///
+    /// As in rustc, the where clauses in the environment take precedence
+    /// over impls, unless `trait_ref` contains no placeholders: a where
+    /// clause like `Foo: Clone` says nothing about which code to run,
+    /// so the impl is selected then. When several impls apply, the most
+    /// specialized one is selected; an error is returned if the impls
+    /// of the trait do not form a valid specialization hierarchy.
+    ///
+    /// # Returns
+    ///
+    /// - `None` if `trait_ref` does not hold.
+    /// - `Some(selection)` otherwise.
+    pub fn select_impl(
+        &mut self,
+        trait_ref: &TraitRef<I>,
+        environment: &Environment<I>,
+    ) -> Result<Option<Selection<I>>, CoherenceError<I>> {
+        self.select_impl_among(trait_ref, environment, |_| true)
+    }
+
+    /// Like `select_impl`, but only considers the impls for which
+    /// `candidate` returns true.
+    fn select_impl_among(
+        &mut self,
+        trait_ref: &TraitRef<I>,
+        environment: &Environment<I>,
+        candidate: impl std::ops::Fn(ImplId<I>) -> bool,
+    ) -> Result<Option<Selection<I>>, CoherenceError<I>> {
+        debug_heading!("select_impl(trait_ref={:?})", trait_ref);
+        let interner = self.db.interner();
+
+        let goal: Goal<I> = trait_ref.clone().cast(interner);
+        let source = match self.solve(CanonicalVarKinds::new(interner), environment, goal) {
+            None => return Ok(None),
+            Some((Solution::Unique(_), source, _)) => source,
+            Some(_) => return Ok(Some(Selection::Ambiguous)),
+        };
+
+        let is_global = !has_placeholders(interner, trait_ref);
+        let from_environment = match source {
+            Some(ClauseSource::Environment) => !is_global,
+            // The solver only reports the source of one of the clauses
+            // that prove `trait_ref`, so the environment may prove it too.
+            _ => !is_global && self.holds_in_environment(trait_ref, environment),
+        };
+        if from_environment {
+            return Ok(Some(Selection::Source(ClauseSource::Environment)));
+        }
+
+        let priorities = self.priorities(trait_ref.trait_id)?;
+        let priority = |impl_id: ImplId<I>| priorities.get(impl_id).unwrap_or_default();
+        let candidates: Vec<_> = self
+            .db
+            .impls_for_trait(
+                trait_ref.trait_id,
+                trait_ref.substitution.parameters(interner),
+            )
+            .into_iter()
+            .filter(|&impl_id| self.db.impl_datum(impl_id).is_positive() && candidate(impl_id))
+            .collect();
+
+        // Find every impl that applies and keep the most special. If the
+        // solver proved `trait_ref` with an impl that no other candidate
+        // is as special as, that impl is selected, so we only need its
+        // substitution. Otherwise, we try all the candidates.
+        let candidates = match source {
+            Some(ClauseSource::Impl(impl_id))
+                if candidates.contains(&impl_id)
+                    && candidates
+                        .iter()
+                        .all(|&other| other == impl_id || priority(other) < priority(impl_id)) =>
+            {
+                vec![impl_id]
+            }
+            _ => candidates,
+        };
+        let mut applicable = vec![];
+        for impl_id in candidates {
+            match self.impl_applies(impl_id, trait_ref, environment) {
+                None => {}
+                Some(Some(substitution)) => applicable.push((impl_id, substitution)),
+                Some(None) => return Ok(Some(Selection::Ambiguous)),
+            }
+        }
+
+        let max_priority = match applicable.iter().map(|&(id, _)| priority(id)).max() {
+            Some(max_priority) => max_priority,
+            None => {
+                return Ok(Some(match source {
+                    Some(ClauseSource::Impl(_)) | None => Selection::Ambiguous,
+                    Some(source) => Selection::Source(source),
+                }))
+            }
+        };
+        applicable.retain(|&(id, _)| priority(id) == max_priority);
+        debug!("select_impl: most special impls = {:?}", applicable);
+
+        if applicable.len() != 1 {
+            return Ok(Some(Selection::Ambiguous));
+        }
+        let (impl_id, substitution) = applicable.pop().unwrap();
+        Ok(Some(Selection::Impl {
+            impl_id,
+            substitution,
+        }))
+    }
+
+    /// Selects the associated type value that normalizes `projection`
+    /// in `environment`, by selecting the impl of the trait reference
+    /// of the projection (see `select_impl`). A specializing impl that
+    /// does not define the associated type inherits the value of the
+    /// impl it specializes, so only the impls that define it are
+    /// considered.
+    pub fn select_associated_ty_value(
+        &mut self,
+        projection: &ProjectionTy<I>,
+        environment: &Environment<I>,
+    ) -> Result<Option<ValueSelection<I>>, CoherenceError<I>> {
+        let db = self.db;
+        let interner = db.interner();
+        let trait_ref = db.trait_ref_from_projection(projection);
+        let associated_ty_id = projection.associated_ty_id;
+        let defines_value =
+            |impl_id| associated_ty_value_id(db, impl_id, associated_ty_id).is_some();
+        let selection = self.select_impl_among(&trait_ref, environment, defines_value)?;
+        let (impl_id, impl_substitution) = match selection {
+            None => return Ok(None),
+            Some(Selection::Impl {
+                impl_id,
+                substitution,
+            }) => (impl_id, substitution),
+            Some(Selection::Source(source)) => return Ok(Some(ValueSelection::Source(source))),
+            Some(Selection::Ambiguous) => return Ok(Some(ValueSelection::Ambiguous)),
+        };
+
+        let value_id = match associated_ty_value_id(db, impl_id, associated_ty_id) {
+            Some(value_id) => value_id,
+            None => return Ok(Some(ValueSelection::Ambiguous)),
+        };
+
+        // The parameters of the associated type itself come first,
+        // followed by those of the impl (see `Split`).
+        let (_, _, other_params) = db.split_projection(projection);
+        let substitution = Canonical {
+            binders: impl_substitution.binders,
+            value: Substitution::from(
+                interner,
+                other_params
+                    .iter()
+                    .chain(impl_substitution.value.iter(interner))
+                    .cloned(),
+            ),
+        };
+        Ok(Some(ValueSelection::Value {
+            value_id,
+            substitution,
+        }))
+    }
+
+    /// Tests whether the impl `impl_id` implements `trait_ref`.
+    ///
+    /// # Returns
+    ///
+    /// - `None` if the impl does not apply.
+    /// - `Some(Some(substitution))` if it does, with the values of its
+    ///   generic parameters.
+    /// - `Some(None)` if it might.
+    fn impl_applies(
+        &mut self,
+        impl_id: ImplId<I>,
+        trait_ref: &TraitRef<I>,
+        environment: &Environment<I>,
+    ) -> Option<Option<Canonical<Substitution<I>>>> {
+        let interner = self.db.interner();
+        let impl_datum = self.db.impl_datum(impl_id);
+        let (impl_binders, impl_bound) = impl_datum.binders.as_ref().into();
+
+        // exists<P0..Pn> { trait_ref = impl_trait_ref, WC }, where the
+        // binders of the impl become the canonical variables of the goal
+        let eq_goals = trait_ref
+            .substitution
+            .iter(interner)
+            .zip(impl_bound.trait_ref.substitution.iter(interner))
+            .map(|(a, b)| {
+                EqGoal {
+                    a: a.clone(),
+                    b: b.clone(),
+                }
+                .cast(interner)
+            });
+        let where_clause_goals = impl_bound
+            .where_clauses
+            .iter()
+            .map(|wc| wc.clone().cast(interner));
+        let goal = Goal::all(interner, eq_goals.chain(where_clause_goals));
+        let binders = CanonicalVarKinds::from(
+            interner,
+            impl_binders
+                .iter(interner)
+                .map(|kind| WithKind::new(kind.clone(), UniverseIndex::ROOT)),
+        );
+
+        match self.solve(binders, environment, goal) {
+            None => None,
+            Some((Solution::Unique(subst), _, universes)) => {
+                let substitution = Canonical {
+                    binders: subst.binders,
+                    value: subst.value.subst,
+                };
+                Some(Some(universes.map_from_canonical(interner, &substitution)))
+            }
+            Some(_) => Some(None),
+        }
+    }
+
+    /// Tests whether the where clauses in `environment` prove
+    /// `trait_ref`, either directly or through their supertraits.
+    fn holds_in_environment(
+        &mut self,
+        trait_ref: &TraitRef<I>,
+        environment: &Environment<I>,
+    ) -> bool {
+        let interner = self.db.interner();
+        let goal: Goal<I> = trait_ref.clone().from_env().cast(interner);
+        match self.solve(CanonicalVarKinds::new(interner), environment, goal) {
+            Some((solution, _, _)) => solution.is_unique(),
+            None => false,
+        }
+    }
+
+    fn solve(
+        &mut self,
+        binders: CanonicalVarKinds<I>,
+        environment: &Environment<I>,
+        goal: Goal<I>,
+    ) -> Option<(Solution<I>, Option<ClauseSource<I>>, UniverseMap)> {
+        let interner = self.db.interner();
+        let canonical_goal = Canonical {
+            binders,
+            value: InEnvironment::new(environment, goal),
+        };
+        let UCanonicalized {
+            quantified,
+            universes,
+        } = InferenceTable::new().u_canonicalize(interner, &canonical_goal);
+        let solution = self.solver.solve_with_source(self.db, &quantified);
+        debug!("select: goal = {:?}, solution = {:?}", quantified, solution);
+        solution.map(|(solution, source)| (solution, source, universes))
+    }
+
+    fn priorities(
+        &mut self,
+        trait_id: TraitId<I>,
+    ) -> Result<Arc<SpecializationPriorities<I>>, CoherenceError<I>> {
+        if let Some(priorities) = self.priorities.get(&trait_id) {
+            return Ok(priorities.clone());
+        }
+        let priorities = CoherenceSolver::new(self.db, self.solver_choice, trait_id)
+            .specialization_priorities()?;
+        self.priorities.insert(trait_id, priorities.clone());
+        Ok(priorities)
+    }
+}
+
+/// Tests whether `trait_ref` refers to placeholders, like the type
+/// parameters of the function that it is selected in.
+fn has_placeholders<I: Interner>(interner: &I, trait_ref: &TraitRef<I>) -> bool {
+    trait_ref
+        .visit_with(
+            &mut PlaceholderFinder { interner },
+            DebruijnIndex::INNERMOST,
+        )
+        .to_bool()
+}
+
+struct PlaceholderFinder<'i, I: Interner> {
+    interner: &'i I,
+}
+
+impl<'i, I: Interner> Visitor<'i, I> for PlaceholderFinder<'i, I> {
+    type Result = FindAny;
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'i, I, Result = Self::Result> {
+        self
+    }
+
+    fn interner(&self) -> &'i I {
+        self.interner
+    }
+
+    fn visit_free_placeholder(
+        &mut self,
+        _universe: PlaceholderIndex,
+        _outer_binder: DebruijnIndex,
+    ) -> FindAny {
+        FindAny::FOUND
+    }
+}
+
+/// The value that the impl `impl_id` defines for the associated type
+/// `associated_ty_id`, if any.
+fn associated_ty_value_id<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    impl_id: ImplId<I>,
+    associated_ty_id: AssocTypeId<I>,
+) -> Option<AssociatedTyValueId<I>> {
+    db.impl_datum(impl_id)
+        .associated_ty_value_ids
+        .iter()
+        .copied()
+        .find(|&value_id| db.associated_ty_value(value_id).associated_ty_id == associated_ty_id)
+}
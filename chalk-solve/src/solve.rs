@@ -1,23 +1,40 @@
+use crate::dependencies::ItemId;
 use crate::RustIrDatabase;
+use anti_unify::{is_trivial, merge_into_guidance};
+pub use chalk_base::stats::SolverStats;
+use chalk_derive::Encode;
+#[cfg(feature = "slg-solver")]
+pub use chalk_engine::forest::TableBudget;
+#[cfg(feature = "slg-solver")]
+pub use chalk_engine::schedule::StrandSchedule;
+use chalk_ir::encode::EncodeInterner;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
+use rustc_hash::FxHashSet;
 use std::fmt;
+use std::io::{self, Read, Write};
 
 #[cfg(feature = "slg-solver")]
 use {
     crate::solve::slg::SlgContext,
-    chalk_engine::forest::{Forest, SubstitutionResult},
+    chalk_engine::forest::{Forest, SubstitutionResult, Truncated},
+    std::sync::Arc,
 };
 
 #[cfg(feature = "recursive-solver")]
 use crate::recursive::RecursiveContext;
 
+pub(crate) mod anti_unify;
+pub(crate) mod persist;
 #[cfg(feature = "slg-solver")]
 mod slg;
 pub(crate) mod truncate;
 
+pub use persist::LoadError;
+pub use truncate::{TruncationLimit, TruncationPolicy};
+
 /// A (possible) solution for a proposed goal.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode)]
 pub enum Solution<I: Interner> {
     /// The goal indeed holds, and there is a unique value for all existential
     /// variables. In this case, we also record a set of lifetime constraints
@@ -29,11 +46,19 @@ pub enum Solution<I: Interner> {
     /// constraints, since we have not "committed" to any particular solution
     /// yet.
     Ambig(Guidance<I>),
+
+    /// The goal is neither provable nor disprovable: under the
+    /// well-founded semantics, it is *undefined*, because it depends on
+    /// itself through negation (e.g., `P :- not { P }`). Along with the
+    /// substitution and region constraints, this contains the residual
+    /// delayed subgoals, the `not { G }` goals it is conditional on.
+    /// Only the SLG solver distinguishes this from ambiguity.
+    Undefined(Canonical<AnswerSubst<I>>),
 }
 
 /// When a goal holds ambiguously (e.g., because there are multiple possible
 /// solutions), we issue a set of *guidance* back to type inference.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode)]
 pub enum Guidance<I: Interner> {
     /// The existential variables *must* have the given values if the goal is
     /// ever to hold, but that alone isn't enough to guarantee the goal will
@@ -60,24 +85,24 @@ impl<I: Interner> Solution<I> {
     /// There are multiple candidate solutions, which may or may not agree on
     /// the values for existential variables; attempt to combine them. This
     /// operation does not depend on the order of its arguments.
+    /// `root_goal_binders` are the canonical variables of the goal that both
+    /// solutions are for.
+    ///
+    /// If the candidates disagree, the guidance is what they agree on: the
+    /// least general generalization of their substitutions.
     //
-    // This actually isn't as precise as it could be, in two ways:
-    //
-    // a. It might be that while there are multiple distinct candidates, they
-    //    all agree about *some things*. To be maximally precise, we would
-    //    compute the intersection of what they agree on. It's not clear though
-    //    that this is actually what we want Rust's inference to do, and it's
-    //    certainly not what it does today.
-    //
-    // b. There might also be an ambiguous candidate and a successful candidate,
-    //    both with the same refined-goal. In that case, we could probably claim
-    //    success, since if the conditions of the ambiguous candidate were met,
-    //    we know the success would apply.  Example: `?0: Clone` yields ambiguous
-    //    candidate `Option<?0>: Clone` and successful candidate `Option<?0>:
-    //    Clone`.
-    //
-    // But you get the idea.
-    pub(crate) fn combine(self, other: Solution<I>, interner: &I) -> Solution<I> {
+    // This actually isn't as precise as it could be: there might be an
+    // ambiguous candidate and a successful candidate, both with the same
+    // refined-goal. In that case, we could probably claim success, since if
+    // the conditions of the ambiguous candidate were met, we know the success
+    // would apply.  Example: `?0: Clone` yields ambiguous candidate
+    // `Option<?0>: Clone` and successful candidate `Option<?0>: Clone`.
+    pub(crate) fn combine(
+        self,
+        other: Solution<I>,
+        root_goal_binders: &CanonicalVarKinds<I>,
+        interner: &I,
+    ) -> Solution<I> {
         use self::Guidance::*;
 
         if self == other {
@@ -90,18 +115,45 @@ impl<I: Interner> Solution<I> {
             other.display(interner)
         );
 
+        // If both solutions agree on the substitution, and only differ
+        // in their region constraints, either set of constraints will do:
+        if let (Solution::Unique(constrained1), Solution::Unique(constrained2)) = (&self, &other) {
+            if constrained1.binders == constrained2.binders
+                && constrained1.value.subst == constrained2.value.subst
+            {
+                let constraints = disjunction(
+                    interner,
+                    vec![
+                        constrained1.value.constraints.clone(),
+                        constrained2.value.constraints.clone(),
+                    ],
+                );
+                return Solution::Unique(Canonical {
+                    value: ConstrainedSubst {
+                        subst: constrained1.value.subst.clone(),
+                        constraints,
+                    },
+                    binders: constrained1.binders.clone(),
+                });
+            }
+        }
+
         // Otherwise, always downgrade to Ambig:
 
-        let guidance = match (self.into_guidance(), other.into_guidance()) {
-            (Definite(ref subst1), Definite(ref subst2)) if subst1 == subst2 => {
-                Definite(subst1.clone())
+        let merge = |subst1, subst2: Canonical<Substitution<I>>| {
+            let subst = merge_into_guidance(interner, root_goal_binders, subst1, &subst2.value);
+            if !subst.value.is_empty(interner) && is_trivial(interner, &subst) {
+                None
+            } else {
+                Some(subst)
             }
-            (Suggested(ref subst1), Suggested(ref subst2)) if subst1 == subst2 => {
-                Suggested(subst1.clone())
-            }
-            _ => Unknown,
         };
-        Solution::Ambig(guidance)
+        let guidance = match (self.into_guidance(), other.into_guidance()) {
+            (Definite(subst1), Definite(subst2)) => merge(subst1, subst2).map(Definite),
+            (Suggested(subst1), Suggested(subst2)) => merge(subst1, subst2).map(Suggested),
+            _ => None,
+        };
+        Solution::Ambig(guidance.unwrap_or(Unknown))
     }
 
     /// View this solution purely in terms of type inference guidance
@@ -112,6 +164,10 @@ impl<I: Interner> Solution<I> {
                 binders: constrained.binders,
             }),
             Solution::Ambig(guidance) => guidance,
+            Solution::Undefined(answer) => Guidance::Suggested(Canonical {
+                value: answer.value.subst,
+                binders: answer.binders,
+            }),
         }
     }
 
@@ -130,7 +186,7 @@ impl<I: Interner> Solution<I> {
                     binders: canonical.binders.clone(),
                 })
             }
-            Solution::Ambig(_) => None,
+            Solution::Ambig(_) | Solution::Undefined(_) => None,
         }
     }
 
@@ -144,6 +200,10 @@ impl<I: Interner> Solution<I> {
         }
     }
 
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, Solution::Undefined(_))
+    }
+
     pub(crate) fn is_ambig(&self) -> bool {
         match *self {
             Solution::Ambig(_) => true,
@@ -159,6 +219,50 @@ impl<I: Interner> Solution<I> {
     }
 }
 
+/// Combines several sets of region constraints into one that holds if
+/// any of them holds. Alternatives that are disjunctions themselves
+/// are flattened, and an alternative is dropped if some other one
+/// requires only a subset of its constraints. If a single alternative
+/// remains (e.g., because one of them is empty), it is returned as is.
+pub(crate) fn disjunction<I: Interner>(
+    interner: &I,
+    alternatives: impl IntoIterator<Item = Vec<InEnvironment<Constraint<I>>>>,
+) -> Vec<InEnvironment<Constraint<I>>> {
+    let mut flattened = vec![];
+    for alternative in alternatives {
+        match alternative.as_slice() {
+            [InEnvironment {
+                goal: Constraint::Or(nested),
+                ..
+            }] => flattened.extend(nested.iter().cloned()),
+            _ => flattened.push(alternative),
+        }
+    }
+
+    // Looking at the smallest alternatives first, any alternative that
+    // includes one we already kept is redundant.
+    flattened.sort_by_key(|alternative| alternative.len());
+    let mut minimal: Vec<Vec<_>> = vec![];
+    for alternative in flattened {
+        let is_redundant = minimal.iter().any(|kept| {
+            kept.iter()
+                .all(|constraint| alternative.contains(constraint))
+        });
+        if !is_redundant {
+            minimal.push(alternative);
+        }
+    }
+
+    if minimal.len() == 1 {
+        minimal.pop().unwrap()
+    } else {
+        vec![InEnvironment::new(
+            &Environment::new(interner),
+            Constraint::Or(minimal),
+        )]
+    }
+}
+
 pub struct SolutionDisplay<'a, I: Interner> {
     solution: &'a Solution<I>,
     interner: &'a I,
@@ -180,17 +284,58 @@ impl<'a, I: Interner> fmt::Display for SolutionDisplay<'a, I> {
                 subst.display(interner)
             ),
             Solution::Ambig(Guidance::Unknown) => write!(f, "Ambiguous; no inference guidance"),
+            Solution::Undefined(answer) => write!(f, "Undefined; {}", answer.display(interner)),
         }
     }
 }
 
+/// A goal that a solver gave up on because it reached one of its
+/// limits. The solutions of the goal and of everything that depends on
+/// it are ambiguous, even though the goal might well have a solution.
+/// See `Solver::overflows`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Overflow<I: Interner> {
+    /// The goal that overflowed.
+    pub goal: UCanonical<InEnvironment<Goal<I>>>,
+
+    /// The limit that the goal reached.
+    pub limit: OverflowLimit,
+}
+
+/// The solver limits that can cause an `Overflow`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OverflowLimit {
+    /// Solving the goal would have nested deeper than the given depth
+    /// (the `overflow_depth` of the recursive solver).
+    Depth(usize),
+
+    /// The goal exceeded the given limit and was truncated.
+    Goal(TruncationLimit),
+
+    /// An answer to the goal exceeded the given limit and was
+    /// truncated.
+    Answer(TruncationLimit),
+}
+
 #[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum SolverChoice {
-    /// Run the SLG solver, producing a Solution.
+    /// Run the SLG solver, producing a Solution. Built with
+    /// `SolverChoice::slg` and the `with_*` methods, so that options can
+    /// be added without breaking users. (The `max_size` of earlier
+    /// versions is now `truncation`; see `SolverChoice::slg`.)
     #[cfg(feature = "slg-solver")]
+    #[non_exhaustive]
     SLG {
-        max_size: usize,
+        /// Decides which goals and answers are truncated; see
+        /// `TruncationPolicy`.
+        truncation: TruncationPolicy,
         expected_answers: Option<usize>,
+        /// Limits the number of tables (or answers) kept between
+        /// queries; see `TableBudget`.
+        table_budget: TableBudget,
+        /// Decides which strand of a table is pursued next; see
+        /// `StrandSchedule`.
+        strand_schedule: StrandSchedule,
     },
     /// Run the recursive solver.
     #[cfg(feature = "recursive-solver")]
@@ -201,13 +346,51 @@ pub enum SolverChoice {
 }
 
 impl SolverChoice {
-    /// Returns specific SLG parameters.
+    /// Returns specific SLG parameters, truncating goals and answers
+    /// with types bigger than `max_size`.
     #[cfg(feature = "slg-solver")]
     pub fn slg(max_size: usize, expected_answers: Option<usize>) -> Self {
         SolverChoice::SLG {
-            max_size,
+            truncation: TruncationPolicy::max_size(max_size),
             expected_answers,
+            table_budget: TableBudget::Unlimited,
+            strand_schedule: StrandSchedule::DepthFirst,
+        }
+    }
+
+    /// Replaces the truncation policy of the SLG solver, which
+    /// `SolverChoice::slg` bases on `max_size`. Has no effect on the
+    /// recursive solver.
+    #[cfg(feature = "slg-solver")]
+    pub fn with_truncation(mut self, policy: TruncationPolicy) -> Self {
+        if let SolverChoice::SLG { truncation, .. } = &mut self {
+            *truncation = policy;
+        }
+        self
+    }
+
+    /// Replaces the table budget of the SLG solver, which defaults to
+    /// `TableBudget::Unlimited`. Has no effect on the recursive solver.
+    #[cfg(feature = "slg-solver")]
+    pub fn with_table_budget(mut self, budget: TableBudget) -> Self {
+        if let SolverChoice::SLG { table_budget, .. } = &mut self {
+            *table_budget = budget;
+        }
+        self
+    }
+
+    /// Replaces the strand schedule of the SLG solver, which defaults to
+    /// `StrandSchedule::DepthFirst`. Has no effect on the recursive
+    /// solver.
+    #[cfg(feature = "slg-solver")]
+    pub fn with_strand_schedule(mut self, schedule: StrandSchedule) -> Self {
+        if let SolverChoice::SLG {
+            strand_schedule, ..
+        } = &mut self
+        {
+            *strand_schedule = schedule;
         }
+        self
     }
 
     /// Returns the default SLG parameters.
@@ -230,11 +413,18 @@ impl SolverChoice {
         match self {
             #[cfg(feature = "slg-solver")]
             SolverChoice::SLG {
-                max_size,
+                truncation,
                 expected_answers,
-            } => Solver(SolverImpl::Slg {
-                forest: Box::new(Forest::new(SlgContext::new(max_size, expected_answers))),
-            }),
+                table_budget,
+                strand_schedule,
+            } => {
+                let mut forest = Forest::new(SlgContext::new(truncation, expected_answers));
+                forest.set_table_budget(table_budget);
+                forest.set_strand_scheduler(Arc::new(strand_schedule));
+                Solver(SolverImpl::Slg {
+                    forest: Box::new(forest),
+                })
+            }
             #[cfg(feature = "recursive-solver")]
             SolverChoice::Recursive {
                 overflow_depth,
@@ -265,6 +455,11 @@ impl Default for SolverChoice {
 /// out what sets of types implement which traits. Also, between
 /// queries, this struct stores the cached state from previous solver
 /// attempts, which can then be re-used later.
+///
+/// A `Solver` is `Send` and `Sync`, but solving requires `&mut self`.
+/// To solve goals from several threads, give each thread its own
+/// solver created with `fork`: the forks share all *completed* work,
+/// but each one keeps its in-progress work to itself.
 pub struct Solver<I: Interner>(SolverImpl<I>);
 
 enum SolverImpl<I: Interner> {
@@ -285,7 +480,7 @@ impl<I: Interner> Solver<I> {
     /// - `program` -- defines the program clauses in scope.
     ///   - **Important:** You must supply the same set of program clauses
     ///     each time you invoke `solve`, as otherwise the cached data may be
-    ///     invalid.
+    ///     invalid. If the program changes, call `invalidate` first.
     /// - `goal` the goal to solve
     ///
     /// # Returns
@@ -298,6 +493,20 @@ impl<I: Interner> Solver<I> {
         program: &dyn RustIrDatabase<I>,
         goal: &UCanonical<InEnvironment<Goal<I>>>,
     ) -> Option<Solution<I>> {
+        self.solve_with_source(program, goal)
+            .map(|(solution, _)| solution)
+    }
+
+    /// Like `solve`, but also returns the source of the program clause
+    /// that the goal was proven with (e.g., the impl that applies), if
+    /// the solution is unique and there is a single such clause. The
+    /// goal must be a domain goal for it to be proven with a clause;
+    /// there is no source for a conjunction, say.
+    pub fn solve_with_source(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Option<(Solution<I>, Option<ClauseSource<I>>)> {
         match &mut self.0 {
             #[cfg(feature = "slg-solver")]
             SolverImpl::Slg { forest } => {
@@ -320,7 +529,7 @@ impl<I: Interner> Solver<I> {
     /// - `program` -- defines the program clauses in scope.
     ///   - **Important:** You must supply the same set of program clauses
     ///     each time you invoke `solve`, as otherwise the cached data may be
-    ///     invalid.
+    ///     invalid. If the program changes, call `invalidate` first.
     /// - `goal` the goal to solve
     /// - `should_continue` if `false` is returned, the no further solving
     ///   will be done. A `Guidance(Suggested(...))` will be returned a
@@ -341,12 +550,17 @@ impl<I: Interner> Solver<I> {
             #[cfg(feature = "slg-solver")]
             SolverImpl::Slg { forest } => {
                 let ops = forest.context().ops(program);
-                forest.solve(&ops, goal, should_continue)
+                forest
+                    .solve(&ops, goal, should_continue)
+                    .map(|(solution, _)| solution)
             }
             #[cfg(feature = "recursive-solver")]
             SolverImpl::Recursive(ctx) => {
                 // TODO support should_continue in recursive solver
-                ctx.solver(program).solve_root_goal(goal).ok()
+                ctx.solver(program)
+                    .solve_root_goal(goal)
+                    .ok()
+                    .map(|(solution, _)| solution)
             }
         }
     }
@@ -361,7 +575,7 @@ impl<I: Interner> Solver<I> {
     /// - `program` -- defines the program clauses in scope.
     ///   - **Important:** You must supply the same set of program clauses
     ///     each time you invoke `solve`, as otherwise the cached data may be
-    ///     invalid.
+    ///     invalid. If the program changes, call `invalidate` first.
     /// - `goal` the goal to solve
     /// - `f` -- function to proceed solution. New solutions will be generated
     /// while function returns `true`.
@@ -378,17 +592,199 @@ impl<I: Interner> Solver<I> {
         &mut self,
         program: &dyn RustIrDatabase<I>,
         goal: &UCanonical<InEnvironment<Goal<I>>>,
-        f: impl FnMut(SubstitutionResult<Canonical<ConstrainedSubst<I>>>, bool) -> bool,
+        mut f: impl FnMut(SubstitutionResult<Canonical<ConstrainedSubst<I>>>, bool) -> bool,
+    ) -> bool {
+        self.solve_multiple_with_source(program, goal, |subst, _, has_next| f(subst, has_next))
+    }
+
+    /// Like `solve_multiple`, but along with each solution, `f` also
+    /// gets the source of the program clause it was derived with, if
+    /// any (see `solve_with_source`).
+    #[cfg(feature = "slg-solver")]
+    pub fn solve_multiple_with_source(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+        f: impl FnMut(
+            SubstitutionResult<Canonical<ConstrainedSubst<I>>>,
+            Option<ClauseSource<I>>,
+            bool,
+        ) -> bool,
     ) -> bool {
         match &mut self.0 {
             SolverImpl::Slg { forest } => {
                 let ops = forest.context().ops(program);
-                forest.solve_multiple(&ops, goal, f)
+                forest.solve_multiple_with_source(&ops, goal, f)
             }
             #[cfg(feature = "recursive-solver")]
             SolverImpl::Recursive(_ctx) => unimplemented!(),
         }
     }
+
+    /// Creates a new solver for the same program, which shares the
+    /// completed work of this solver: the completed tables of the SLG
+    /// solver, or the cache of the recursive solver. Work completed by
+    /// either solver later on becomes visible to the other (and to any
+    /// other fork). Tables or search graph nodes that are still in
+    /// progress are never shared.
+    ///
+    /// The same caveat as for `solve` applies: all forks must be used
+    /// with the same set of program clauses.
+    pub fn fork(&mut self) -> Solver<I> {
+        match &mut self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => {
+                let completed = forest.completed_tables();
+                let context = forest.context().clone();
+                let mut fork = Forest::with_completed_tables(context, completed);
+                fork.set_table_budget(forest.table_budget());
+                fork.set_strand_scheduler(forest.strand_scheduler());
+                Solver(SolverImpl::Slg {
+                    forest: Box::new(fork),
+                })
+            }
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => Solver(SolverImpl::Recursive(Box::new(ctx.fork()))),
+        }
+    }
+
+    /// Discards the cached work that depends on any of the `changed`
+    /// items, so that the solver can keep being used after the program
+    /// was edited. An item counts as changed if it was added, removed,
+    /// or modified; for a trait, this includes adding or removing one
+    /// of its impls (see `ItemId`). The work shared with forks of this
+    /// solver is invalidated for all of them.
+    pub fn invalidate(&mut self, changed: impl IntoIterator<Item = ItemId<I>>) {
+        let changed: FxHashSet<_> = changed.into_iter().collect();
+        match &mut self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => {
+                forest.context().invalidate(&changed);
+                forest.invalidate(&changed);
+            }
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => {
+                ctx.invalidate(&changed);
+            }
+        }
+    }
+
+    /// Saves the completed work of this solver to `writer`, so that it
+    /// can be loaded into a solver of the same kind in a later process
+    /// (see `load`). Along with each cached result, this saves a
+    /// fingerprint of the items it was derived from, as found in
+    /// `program`; results that depend on an item missing from `program`
+    /// are not saved.
+    ///
+    /// The SLG solver saves its completed tables, with their answers;
+    /// the recursive solver saves its cache of final results, which is
+    /// empty unless caching is enabled.
+    pub fn save(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        mut writer: impl Write,
+    ) -> io::Result<()>
+    where
+        I: EncodeInterner,
+    {
+        match &mut self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => {
+                let tables = forest.completed_tables().export();
+                writer.write_all(&persist::save_tables(program, tables))
+            }
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => {
+                let results = ctx.export_cache();
+                writer.write_all(&persist::save_cache(program, results))
+            }
+        }
+    }
+
+    /// Loads work saved by `save` into this solver, which must be of
+    /// the same kind as the solver that saved it. Results that depend
+    /// on an item that is missing from `program`, or whose name or
+    /// program clauses are different than when they were saved, are
+    /// skipped, as are results the solver has already. Returns the
+    /// number of results loaded.
+    pub fn load(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        mut reader: impl Read,
+    ) -> Result<usize, LoadError>
+    where
+        I: EncodeInterner,
+    {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        match &mut self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => {
+                let tables = persist::load_tables(program, &bytes)?;
+                Ok(forest.completed_tables().import(tables))
+            }
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => {
+                let results = persist::load_cache(program, &bytes)?;
+                Ok(ctx.import_cache(results))
+            }
+        }
+    }
+
+    /// Returns the statistics gathered by this solver since it was
+    /// created, or since the last call to `reset_stats`. Since cached
+    /// work is reused across queries, call `reset_stats` before a
+    /// `solve` to measure the cost of that query alone.
+    pub fn stats(&self) -> SolverStats {
+        match &self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => *forest.stats(),
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => *ctx.stats(),
+        }
+    }
+
+    /// Returns the goals that overflowed since the solver was created,
+    /// or since the last call to `reset_stats`, in the order in which
+    /// they first overflowed. An ambiguous solution can be told apart
+    /// from a solution that is ambiguous only because the solver gave
+    /// up by checking whether any goal overflowed while solving it.
+    pub fn overflows(&self) -> Vec<Overflow<I>> {
+        match &self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => {
+                let truncation = forest.context().truncation();
+                forest
+                    .truncated_goals()
+                    .iter()
+                    .map(|(goal, truncated)| {
+                        // A goal is only truncated if there is a limit.
+                        let limit = match truncated {
+                            Truncated::Goal => OverflowLimit::Goal(truncation.goals.unwrap()),
+                            Truncated::Answer => OverflowLimit::Answer(truncation.answers.unwrap()),
+                        };
+                        Overflow {
+                            goal: goal.clone(),
+                            limit,
+                        }
+                    })
+                    .collect()
+            }
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => ctx.overflows().to_vec(),
+        }
+    }
+
+    /// Resets all statistics to zero and forgets the goals that
+    /// overflowed. Cached solver state is kept.
+    pub fn reset_stats(&mut self) {
+        match &mut self.0 {
+            #[cfg(feature = "slg-solver")]
+            SolverImpl::Slg { forest } => forest.reset_stats(),
+            #[cfg(feature = "recursive-solver")]
+            SolverImpl::Recursive(ctx) => ctx.reset_stats(),
+        }
+    }
 }
 
 impl<I: Interner> std::fmt::Debug for Solver<I> {
@@ -0,0 +1,443 @@
+//! Saving the work of a solver to disk, and loading it back in a later
+//! process; see `Solver::save` and `Solver::load`.
+//!
+//! Cached results are only valid for the program they were computed
+//! for. So each result is saved along with the items it depends on
+//! (see `ItemId`) and a *fingerprint* of each of those items: a hash of
+//! the name of the item (see `RustIrDatabase::item_name`) and of the
+//! program clauses derived from it. When loading, the fingerprints are
+//! computed again for the current program, and the results that depend
+//! on an item whose fingerprint differs, or that no longer exists, are
+//! skipped. Hashing the name keeps a result from being reused when the
+//! id of one of its items now refers to another item.
+
+use crate::clauses::builder::ClauseBuilder;
+use crate::clauses::program_clauses::ToProgramClauses;
+use crate::dependencies::ItemId;
+use crate::rust_ir::{AssociatedTyValueId, WellKnownTrait};
+use crate::RustIrDatabase;
+use chalk_derive::Encode;
+use chalk_ir::encode::{self, DecodeError, Decoder, Encode, EncodeInterner, Encoder};
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+use rustc_hash::FxHashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "slg-solver")]
+use {
+    crate::solve::slg::SlgContext,
+    chalk_engine::{completed::ExportedTable, Answer},
+};
+
+#[cfg(feature = "recursive-solver")]
+use {
+    crate::recursive::{SourcedSolution, UCanonicalGoal},
+    crate::Solution,
+    chalk_base::results::{Fallible, NoSolution},
+    rustc_hash::FxHashSet,
+    std::sync::Arc,
+};
+
+/// Identifies the data written by `Solver::save`.
+const MAGIC: &[u8] = b"chalk-solver";
+
+/// The version of the format, to be bumped whenever the encoding of
+/// the IR or of the saved work changes.
+const VERSION: u32 = 2;
+
+/// The solver whose work is saved.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SavedSolver {
+    Slg,
+    Recursive,
+}
+
+/// The error returned by `Solver::load`.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+
+    /// The data was not saved by a solver of the same kind, or by a
+    /// different version of chalk.
+    WrongFormat,
+
+    /// The data is corrupted.
+    Decode(DecodeError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::WrongFormat => write!(f, "not the saved work of a solver of this kind"),
+            LoadError::Decode(error) => write!(f, "corrupted solver data: {}", error),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl From<DecodeError> for LoadError {
+    fn from(error: DecodeError) -> Self {
+        LoadError::Decode(error)
+    }
+}
+
+pub(crate) fn write_header<I: EncodeInterner>(encoder: &mut Encoder<'_, I>, solver: SavedSolver) {
+    encoder.write_bytes(MAGIC);
+    encoder.write_u32(VERSION);
+    encoder.write_u8(solver as u8);
+}
+
+pub(crate) fn read_header<I: EncodeInterner>(
+    decoder: &mut Decoder<'_, I>,
+    solver: SavedSolver,
+) -> Result<(), LoadError> {
+    let header = (|| {
+        Ok::<_, DecodeError>((
+            decoder.read_bytes(MAGIC.len())?,
+            decoder.read_u32()?,
+            decoder.read_u8()?,
+        ))
+    })();
+    match header {
+        Ok((magic, version, saved)) if magic == MAGIC && version == VERSION => {
+            if saved == solver as u8 {
+                Ok(())
+            } else {
+                Err(LoadError::WrongFormat)
+            }
+        }
+        _ => Err(LoadError::WrongFormat),
+    }
+}
+
+impl<I: EncodeInterner> Encode<I> for AssociatedTyValueId<I> {
+    fn encode(&self, encoder: &mut Encoder<'_, I>) {
+        encoder.interner().encode_def_id(self.0, encoder);
+    }
+
+    fn decode(decoder: &mut Decoder<'_, I>) -> Result<Self, DecodeError> {
+        Ok(AssociatedTyValueId(
+            decoder.interner().decode_def_id(decoder)?,
+        ))
+    }
+}
+
+/// The well-known traits, whose mapping to the traits of the program
+/// makes up the `ItemId::WellKnownTraits` item.
+const WELL_KNOWN_TRAITS: &[WellKnownTrait] = &[
+    WellKnownTrait::SizedTrait,
+    WellKnownTrait::CopyTrait,
+    WellKnownTrait::CloneTrait,
+    WellKnownTrait::DropTrait,
+];
+
+/// Computes (and caches) the fingerprints of the items of a program.
+pub(crate) struct Fingerprints<'db, I: Interner> {
+    db: &'db dyn RustIrDatabase<I>,
+    fingerprints: FxHashMap<ItemId<I>, Option<u64>>,
+}
+
+impl<'db, I: EncodeInterner> Fingerprints<'db, I> {
+    pub(crate) fn new(db: &'db dyn RustIrDatabase<I>) -> Self {
+        Fingerprints {
+            db,
+            fingerprints: FxHashMap::default(),
+        }
+    }
+
+    /// Returns the fingerprint of `item`, or `None` if the program has
+    /// no such item.
+    pub(crate) fn get(&mut self, item: ItemId<I>) -> Option<u64> {
+        let db = self.db;
+        *self
+            .fingerprints
+            .entry(item)
+            .or_insert_with(|| fingerprint(db, item))
+    }
+
+    /// Pairs each of `items` with its fingerprint, or returns `None` if
+    /// one of them does not exist.
+    pub(crate) fn of(
+        &mut self,
+        items: impl IntoIterator<Item = ItemId<I>>,
+    ) -> Option<Vec<(ItemId<I>, u64)>> {
+        items
+            .into_iter()
+            .map(|item| Some((item, self.get(item)?)))
+            .collect()
+    }
+
+    /// True if every item still exists and has the given fingerprint.
+    pub(crate) fn matches(&mut self, items: &[(ItemId<I>, u64)]) -> bool {
+        items
+            .iter()
+            .all(|&(item, fingerprint)| self.get(item) == Some(fingerprint))
+    }
+}
+
+/// Hashes the name of `item` and the program clauses derived from it,
+/// along with the properties of the item that affect solving without
+/// showing up in its clauses (e.g., the flags of a trait, or the set of
+/// its impls). Returns `None` if the program has no such item.
+fn fingerprint<I: EncodeInterner>(db: &dyn RustIrDatabase<I>, item: ItemId<I>) -> Option<u64> {
+    let name = db.item_name(item)?;
+    let interner = db.interner();
+    let mut encoder = Encoder::new(interner);
+    name.len().encode(&mut encoder);
+    encoder.write_bytes(name.as_bytes());
+    let mut clauses = vec![];
+    let mut builder = ClauseBuilder::new(db, &mut clauses);
+    match item {
+        ItemId::Trait(trait_id) => {
+            let datum = db.trait_datum(trait_id);
+            datum.to_program_clauses(&mut builder);
+            let flags = &datum.flags;
+            for &flag in &[
+                flags.auto,
+                flags.marker,
+                flags.upstream,
+                flags.fundamental,
+                flags.non_enumerable,
+                flags.coinductive,
+            ] {
+                flag.encode(&mut encoder);
+            }
+            datum
+                .well_known
+                .map(|well_known| well_known as u32)
+                .encode(&mut encoder);
+            db.is_object_safe(trait_id).encode(&mut encoder);
+            let parameters = datum.binders.identity_substitution(interner);
+            let mut impls = db.impls_for_trait(trait_id, parameters.parameters(interner));
+            impls.sort();
+            impls.encode(&mut encoder);
+        }
+        ItemId::Impl(impl_id) => {
+            let datum = db.impl_datum(impl_id);
+            datum.to_program_clauses(&mut builder);
+            (datum.polarity as u32).encode(&mut encoder);
+            (datum.impl_type as u32).encode(&mut encoder);
+        }
+        ItemId::Adt(adt_id) => db.adt_datum(adt_id).to_program_clauses(&mut builder),
+        ItemId::FnDef(fn_def_id) => db.fn_def_datum(fn_def_id).to_program_clauses(&mut builder),
+        ItemId::AssocTy(assoc_ty_id) => db
+            .associated_ty_data(assoc_ty_id)
+            .to_program_clauses(&mut builder),
+        ItemId::AssocTyValue(value_id) => db
+            .associated_ty_value(value_id)
+            .to_program_clauses(&mut builder),
+        ItemId::OpaqueTy(opaque_ty_id) => db
+            .opaque_ty_data(opaque_ty_id)
+            .to_program_clauses(&mut builder),
+        ItemId::CustomClauses => db.custom_clauses().encode(&mut encoder),
+        ItemId::WellKnownTraits => {
+            for &well_known in WELL_KNOWN_TRAITS {
+                db.well_known_trait_id(well_known).encode(&mut encoder);
+            }
+        }
+    }
+    clauses.encode(&mut encoder);
+    Some(fnv1a(encoder.bytes()))
+}
+
+/// The 64-bit FNV-1a hash of `bytes`. Unlike the hashers of the
+/// standard library, it is guaranteed to be the same across processes
+/// and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A completed table of the SLG solver, as saved.
+#[cfg(feature = "slg-solver")]
+#[derive(Encode)]
+struct SavedTable<I: Interner> {
+    goal: UCanonical<InEnvironment<Goal<I>>>,
+    floundered: bool,
+    answers: Vec<SavedAnswer<I>>,
+    dependencies: Vec<(ItemId<I>, u64)>,
+}
+
+/// An answer of a completed table, as saved.
+#[cfg(feature = "slg-solver")]
+#[derive(Encode)]
+struct SavedAnswer<I: Interner> {
+    subst: Canonical<AnswerSubst<I>>,
+    ambiguous: bool,
+    source: Option<ClauseSource<I>>,
+}
+
+/// Encodes the completed `tables` of an SLG forest, except for those
+/// that depend on an item missing from `db`.
+#[cfg(feature = "slg-solver")]
+pub(crate) fn save_tables<I: EncodeInterner>(
+    db: &dyn RustIrDatabase<I>,
+    tables: Vec<ExportedTable<SlgContext<I>>>,
+) -> Vec<u8> {
+    let mut fingerprints = Fingerprints::new(db);
+    let tables: Vec<SavedTable<I>> = tables
+        .into_iter()
+        .filter_map(|table| {
+            Some(SavedTable {
+                dependencies: fingerprints.of(table.dependencies)?,
+                goal: table.goal,
+                floundered: table.floundered,
+                answers: table
+                    .answers
+                    .into_iter()
+                    .map(|answer| SavedAnswer {
+                        subst: answer.subst,
+                        ambiguous: answer.ambiguous,
+                        source: answer.source,
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+
+    let mut encoder = Encoder::new(db.interner());
+    write_header(&mut encoder, SavedSolver::Slg);
+    tables.encode(&mut encoder);
+    encoder.into_bytes()
+}
+
+/// Decodes the tables encoded by `save_tables`, keeping those whose
+/// dependencies still exist and have the same fingerprints in `db`.
+#[cfg(feature = "slg-solver")]
+pub(crate) fn load_tables<I: EncodeInterner>(
+    db: &dyn RustIrDatabase<I>,
+    bytes: &[u8],
+) -> Result<Vec<ExportedTable<SlgContext<I>>>, LoadError> {
+    let mut decoder = Decoder::new(db.interner(), bytes);
+    read_header(&mut decoder, SavedSolver::Slg)?;
+    let tables: Vec<SavedTable<I>> = Encode::decode(&mut decoder)?;
+    if !decoder.is_empty() {
+        return Err(DecodeError::TrailingData.into());
+    }
+
+    let mut fingerprints = Fingerprints::new(db);
+    Ok(tables
+        .into_iter()
+        .filter(|table| fingerprints.matches(&table.dependencies))
+        .map(|table| ExportedTable {
+            goal: table.goal,
+            floundered: table.floundered,
+            answers: table
+                .answers
+                .into_iter()
+                .map(|answer| Answer {
+                    subst: answer.subst,
+                    ambiguous: answer.ambiguous,
+                    source: answer.source,
+                })
+                .collect(),
+            dependencies: table
+                .dependencies
+                .into_iter()
+                .map(|(item, _)| item)
+                .collect(),
+        })
+        .collect())
+}
+
+/// A cached result of the recursive solver, as saved; a `None` result
+/// stands for `NoSolution`.
+#[cfg(feature = "recursive-solver")]
+#[derive(Encode)]
+struct SavedResult<I: Interner> {
+    goal: UCanonicalGoal<I>,
+    result: Option<Solution<I>>,
+    source: Option<ClauseSource<I>>,
+    dependencies: Vec<(ItemId<I>, u64)>,
+}
+
+/// A cached result of the recursive solver, along with the items it
+/// depends on.
+#[cfg(feature = "recursive-solver")]
+pub(crate) type CachedResult<I> = (
+    UCanonicalGoal<I>,
+    (Fallible<SourcedSolution<I>>, Arc<FxHashSet<ItemId<I>>>),
+);
+
+/// Encodes the cached `results` of a recursive solver, except for
+/// those that depend on an item missing from `db`.
+#[cfg(feature = "recursive-solver")]
+pub(crate) fn save_cache<I: EncodeInterner>(
+    db: &dyn RustIrDatabase<I>,
+    results: Vec<CachedResult<I>>,
+) -> Vec<u8> {
+    let mut fingerprints = Fingerprints::new(db);
+    let mut results: Vec<SavedResult<I>> = results
+        .into_iter()
+        .filter_map(|(goal, (result, dependencies))| {
+            let mut dependencies: Vec<_> = dependencies.iter().copied().collect();
+            dependencies.sort();
+            let (result, source) = match result {
+                Ok((solution, source)) => (Some(solution), source),
+                Err(NoSolution) => (None, None),
+            };
+            Some(SavedResult {
+                goal,
+                result,
+                source,
+                dependencies: fingerprints.of(dependencies)?,
+            })
+        })
+        .collect();
+    // The cache is a hash map; sort the results so that saving the
+    // same cache always yields the same bytes.
+    results.sort_by_cached_key(|result| encode::encode(db.interner(), &result.goal));
+
+    let mut encoder = Encoder::new(db.interner());
+    write_header(&mut encoder, SavedSolver::Recursive);
+    results.encode(&mut encoder);
+    encoder.into_bytes()
+}
+
+/// Decodes the results encoded by `save_cache`, keeping those whose
+/// dependencies still exist and have the same fingerprints in `db`.
+#[cfg(feature = "recursive-solver")]
+pub(crate) fn load_cache<I: EncodeInterner>(
+    db: &dyn RustIrDatabase<I>,
+    bytes: &[u8],
+) -> Result<Vec<CachedResult<I>>, LoadError> {
+    let mut decoder = Decoder::new(db.interner(), bytes);
+    read_header(&mut decoder, SavedSolver::Recursive)?;
+    let results: Vec<SavedResult<I>> = Encode::decode(&mut decoder)?;
+    if !decoder.is_empty() {
+        return Err(DecodeError::TrailingData.into());
+    }
+
+    let mut fingerprints = Fingerprints::new(db);
+    Ok(results
+        .into_iter()
+        .filter(|result| fingerprints.matches(&result.dependencies))
+        .map(|result| {
+            let dependencies = result
+                .dependencies
+                .into_iter()
+                .map(|(item, _)| item)
+                .collect();
+            let source = result.source;
+            let solution = result.result.map(|solution| (solution, source));
+            (
+                result.goal,
+                (solution.ok_or(NoSolution), Arc::new(dependencies)),
+            )
+        })
+        .collect())
+}
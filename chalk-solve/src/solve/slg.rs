@@ -1,9 +1,9 @@
-use crate::clauses::program_clauses_for_goal;
 use crate::coinductive_goal::IsCoinductive;
+use crate::dependencies::{program_clauses_for_goal_with_dependencies, EnvDependencies, ItemId};
 use crate::infer::ucanonicalize::{UCanonicalized, UniverseMap};
 use crate::infer::unify::UnificationResult;
 use crate::infer::InferenceTable;
-use crate::solve::truncate;
+use crate::solve::truncate::{self, TruncationPolicy};
 use crate::solve::Solution;
 use crate::RustIrDatabase;
 use chalk_base::results::{Fallible, Floundered};
@@ -15,38 +15,54 @@ use chalk_ir::cast::Cast;
 use chalk_ir::cast::Caster;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
+use rustc_hash::FxHashSet;
 
 use std::fmt::Debug;
-use std::marker::PhantomData;
 
 mod aggregate;
 mod resolvent;
 
 #[derive(Clone, Debug, HasInterner)]
 pub(crate) struct SlgContext<I: Interner> {
-    max_size: usize,
+    truncation: TruncationPolicy,
     /// The expected number of answers for a solution.
     /// Only really sseful for tests, since `make_solution`
     /// will panic if the number of cached answers does not
     /// equal this when a solution is made.
     expected_answers: Option<usize>,
-    phantom: PhantomData<I>,
+    /// Shared with the clones of this context, like those of the forks
+    /// of a forest.
+    env_dependencies: EnvDependencies<I>,
 }
 
 impl<I: Interner> SlgContext<I> {
-    pub(crate) fn new(max_size: usize, expected_answers: Option<usize>) -> SlgContext<I> {
+    pub(crate) fn new(
+        truncation: TruncationPolicy,
+        expected_answers: Option<usize>,
+    ) -> SlgContext<I> {
         SlgContext {
-            max_size,
+            truncation,
             expected_answers,
-            phantom: PhantomData,
+            env_dependencies: EnvDependencies::new(),
         }
     }
 
+    pub(crate) fn truncation(&self) -> TruncationPolicy {
+        self.truncation
+    }
+
+    /// Forgets the program clause dependencies of the environments that
+    /// depend on one of the `changed` items.
+    pub(crate) fn invalidate(&self, changed: &FxHashSet<ItemId<I>>) {
+        self.env_dependencies.invalidate(changed);
+    }
+
     pub(crate) fn ops<'p>(&self, program: &'p dyn RustIrDatabase<I>) -> SlgContextOps<'p, I> {
         SlgContextOps {
             program,
-            max_size: self.max_size,
+            truncation: self.truncation,
             expected_answers: self.expected_answers,
+            env_dependencies: self.env_dependencies.clone(),
         }
     }
 }
@@ -54,13 +70,14 @@ impl<I: Interner> SlgContext<I> {
 #[derive(Clone, Debug)]
 pub(crate) struct SlgContextOps<'me, I: Interner> {
     program: &'me dyn RustIrDatabase<I>,
-    max_size: usize,
+    truncation: TruncationPolicy,
     expected_answers: Option<usize>,
+    env_dependencies: EnvDependencies<I>,
 }
 
 #[derive(Clone)]
 pub struct TruncatingInferenceTable<I: Interner> {
-    max_size: usize,
+    truncation: TruncationPolicy,
     infer: InferenceTable<I>,
 }
 
@@ -70,7 +87,9 @@ impl<I: Interner> context::Context for SlgContext<I> {
     type UCanonicalGoalInEnvironment = UCanonical<InEnvironment<Goal<I>>>;
     type UniverseMap = UniverseMap;
     type InferenceNormalizedSubst = Substitution<I>;
-    type Solution = Solution<I>;
+    /// The solution, along with the source of the program clause it was
+    /// proven with if it is unique and there is a single such clause.
+    type Solution = (Solution<I>, Option<ClauseSource<I>>);
     type InferenceTable = TruncatingInferenceTable<I>;
     type Environment = Environment<I>;
     type DomainGoal = DomainGoal<I>;
@@ -86,6 +105,8 @@ impl<I: Interner> context::Context for SlgContext<I> {
     type RegionConstraint = InEnvironment<Constraint<I>>;
     type Variance = ();
     type Interner = I;
+    type ItemId = ItemId<I>;
+    type ClauseSource = ClauseSource<I>;
 
     fn goal_in_environment(environment: &Environment<I>, goal: Goal<I>) -> InEnvironment<Goal<I>> {
         InEnvironment::new(environment, goal)
@@ -146,6 +167,10 @@ impl<I: Interner> context::Context for SlgContext<I> {
         // to pick things like `?T: Sized` if we can help it.
         ex_clause.subgoals.len() - 1
     }
+
+    fn num_subgoals(canonical_ex_clause: &Canonical<ExClause<SlgContext<I>>>) -> usize {
+        canonical_ex_clause.value.subgoals.len()
+    }
 }
 
 impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me, I> {
@@ -174,13 +199,22 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
         environment: &Environment<I>,
         goal: &DomainGoal<I>,
         _infer: &mut TruncatingInferenceTable<I>,
+        dependencies: &mut FxHashSet<ItemId<I>>,
     ) -> Result<Vec<ProgramClause<I>>, Floundered> {
-        let clauses: Vec<_> = program_clauses_for_goal(self.program, environment, goal)?;
-
-        Ok(clauses)
+        program_clauses_for_goal_with_dependencies(
+            self.program,
+            &self.env_dependencies,
+            environment,
+            goal,
+            dependencies,
+        )
     }
 
     // Used by: simplify
+    fn clause_source(&self, clause: &ProgramClause<I>) -> ClauseSource<I> {
+        clause.source(self.interner())
+    }
+
     fn add_clauses(&self, env: &Environment<I>, clauses: ProgramClauses<I>) -> Environment<I> {
         let interner = self.interner();
         env.add_clauses(interner, clauses.iter(interner).cloned())
@@ -197,7 +231,7 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
     ) {
         let (infer, subst, InEnvironment { environment, goal }) =
             InferenceTable::from_canonical(self.program.interner(), arg.universes, &arg.canonical);
-        let infer_table = TruncatingInferenceTable::new(self.max_size, infer);
+        let infer_table = TruncatingInferenceTable::new(self.truncation, infer);
         (infer_table, subst, environment, goal)
     }
 
@@ -211,7 +245,7 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
             num_universes,
             canonical_ex_clause,
         );
-        let infer_table = TruncatingInferenceTable::new(self.max_size, infer);
+        let infer_table = TruncatingInferenceTable::new(self.truncation, infer);
         (infer_table, ex_cluse)
     }
 
@@ -234,7 +268,7 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
                 delayed_subgoals,
             },
         ) = InferenceTable::from_canonical(self.program.interner(), num_universes, answer);
-        let infer_table = TruncatingInferenceTable::new(self.max_size, infer);
+        let infer_table = TruncatingInferenceTable::new(self.truncation, infer);
         (infer_table, subst, constraints, delayed_subgoals)
     }
 
@@ -274,6 +308,19 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
         domain_goal.cast(self.program.interner())
     }
 
+    fn negate_goal(&self, goal: &InEnvironment<Goal<I>>) -> InEnvironment<Goal<I>> {
+        let interner = self.program.interner();
+        let negated = GoalData::Not(goal.goal.clone()).intern(interner);
+        InEnvironment::new(&goal.environment, negated)
+    }
+
+    fn negated_goal(&self, goal: &InEnvironment<Goal<I>>) -> Option<InEnvironment<Goal<I>>> {
+        match goal.goal.data(self.program.interner()) {
+            GoalData::Not(negated) => Some(InEnvironment::new(&goal.environment, negated.clone())),
+            _ => None,
+        }
+    }
+
     fn is_trivial_substitution(
         &self,
         u_canon: &UCanonical<InEnvironment<Goal<I>>>,
@@ -303,18 +350,24 @@ impl<'me, I: Interner> context::ContextOps<SlgContext<I>> for SlgContextOps<'me,
 }
 
 impl<I: Interner> TruncatingInferenceTable<I> {
-    fn new(max_size: usize, infer: InferenceTable<I>) -> Self {
-        Self { max_size, infer }
+    fn new(truncation: TruncationPolicy, infer: InferenceTable<I>) -> Self {
+        Self { truncation, infer }
     }
 }
 
 impl<I: Interner> context::TruncateOps<SlgContext<I>> for TruncatingInferenceTable<I> {
     fn goal_needs_truncation(&mut self, interner: &I, subgoal: &InEnvironment<Goal<I>>) -> bool {
-        truncate::needs_truncation(interner, &mut self.infer, self.max_size, &subgoal)
+        match self.truncation.goals {
+            Some(limit) => truncate::needs_truncation(interner, &mut self.infer, limit, subgoal),
+            None => false,
+        }
     }
 
     fn answer_needs_truncation(&mut self, interner: &I, subst: &Substitution<I>) -> bool {
-        truncate::needs_truncation(interner, &mut self.infer, self.max_size, subst)
+        match self.truncation.answers {
+            Some(limit) => truncate::needs_truncation(interner, &mut self.infer, limit, subst),
+            None => false,
+        }
     }
 }
 
@@ -9,6 +9,7 @@ use chalk_ir::*;
 
 use chalk_engine::context;
 use chalk_engine::{ExClause, Literal, TimeStamp};
+use tracing::debug_span;
 
 ///////////////////////////////////////////////////////////////////////////
 // SLG RESOLVENTS
@@ -69,13 +70,7 @@ impl<I: Interner> context::ResolventOps<SlgContext<I>> for TruncatingInferenceTa
         //   - Also, we always select the first literal in `ex_clause.literals`, so `i` is 0.
         // - `clause` is C, except with binders for any existential variables.
 
-        debug_heading!(
-            "resolvent_clause(\
-             \n    goal={:?},\
-             \n    clause={:?})",
-            goal,
-            clause,
-        );
+        let _span = debug_span!("resolvent_clause", ?goal, ?clause).entered();
 
         // C' in the description above is `consequence :- conditions`.
         //
@@ -84,14 +79,14 @@ impl<I: Interner> context::ResolventOps<SlgContext<I>> for TruncatingInferenceTa
             consequence,
             conditions,
             priority: _,
+            source: _,
         } = match clause.data(interner) {
             ProgramClauseData::Implies(implication) => implication.clone(),
             ProgramClauseData::ForAll(implication) => self
                 .infer
                 .instantiate_binders_existentially(interner, implication),
         };
-        debug!("consequence = {:?}", consequence);
-        debug!("conditions = {:?}", conditions);
+        debug!(?consequence, ?conditions, "instantiated clause");
 
         // Unify the selected literal Li with C'.
         let unification_result = self
@@ -211,14 +206,14 @@ impl<I: Interner> context::ResolventOps<SlgContext<I>> for TruncatingInferenceTa
         answer_table_goal: &Canonical<InEnvironment<Goal<I>>>,
         canonical_answer_subst: &Canonical<AnswerSubst<I>>,
     ) -> Fallible<()> {
-        debug_heading!("apply_answer_subst()");
-        debug!("ex_clause={:?}", ex_clause);
-        debug!(
-            "selected_goal={:?}",
-            self.infer.normalize_deep(interner, selected_goal)
-        );
-        debug!("answer_table_goal={:?}", answer_table_goal);
-        debug!("canonical_answer_subst={:?}", canonical_answer_subst);
+        let _span = debug_span!(
+            "apply_answer_subst",
+            clause = ?ex_clause,
+            goal = ?self.infer.normalize_deep(interner, selected_goal),
+            table_goal = ?answer_table_goal,
+            result = ?canonical_answer_subst,
+        )
+        .entered();
 
         // C' is now `answer`. No variables in common with G.
         let AnswerSubst {
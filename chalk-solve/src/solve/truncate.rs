@@ -4,18 +4,65 @@ use crate::infer::InferenceTable;
 use chalk_ir::interner::Interner;
 use chalk_ir::visit::{SuperVisit, Visit, Visitor};
 use chalk_ir::*;
+use rustc_hash::FxHashSet;
 use std::cmp::max;
 
+/// Decides which goals and answers the SLG solver truncates. A
+/// truncated subgoal flounders, and a table with a truncated answer
+/// is ambiguous; in exchange, the solver terminates even for programs
+/// that produce ever bigger goals or answers. Goals and answers are
+/// limited independently, and a limit of `None` never truncates,
+/// trading termination for completeness.
+#[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct TruncationPolicy {
+    /// The limit for the subgoals that the solver selects.
+    pub goals: Option<TruncationLimit>,
+
+    /// The limit for the answers that are added to tables.
+    pub answers: Option<TruncationLimit>,
+}
+
+impl TruncationPolicy {
+    /// Limits both goals and answers to types of at most `max_size`
+    /// (see `TruncationLimit::Size`).
+    pub fn max_size(max_size: usize) -> Self {
+        TruncationPolicy {
+            goals: Some(TruncationLimit::Size(max_size)),
+            answers: Some(TruncationLimit::Size(max_size)),
+        }
+    }
+}
+
+/// How big a goal or answer may get before it is truncated.
+#[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub enum TruncationLimit {
+    /// Limits the size of each type, i.e., the number of types that
+    /// it is made of. For example, `(u32, (u32, u32))` has size 5.
+    Size(usize),
+
+    /// Limits how deeply types nest. For example, `(u32, (u32, u32))`
+    /// has depth 3.
+    Depth(usize),
+
+    /// Limits the number of distinct inference variables (of any kind)
+    /// that are still unresolved.
+    Variables(usize),
+}
+
 pub(crate) fn needs_truncation<I: Interner>(
     interner: &I,
     infer: &mut InferenceTable<I>,
-    max_size: usize,
+    limit: TruncationLimit,
     value: impl Visit<I>,
 ) -> bool {
     let mut visitor = TySizeVisitor::new(interner, infer);
     value.visit_with(&mut visitor, DebruijnIndex::INNERMOST);
 
-    visitor.max_size > max_size
+    match limit {
+        TruncationLimit::Size(max_size) => visitor.max_size > max_size,
+        TruncationLimit::Depth(max_depth) => visitor.max_depth > max_depth,
+        TruncationLimit::Variables(max_variables) => visitor.variables.len() > max_variables,
+    }
 }
 
 struct TySizeVisitor<'infer, 'i, I: Interner> {
@@ -24,6 +71,8 @@ struct TySizeVisitor<'infer, 'i, I: Interner> {
     size: usize,
     depth: usize,
     max_size: usize,
+    max_depth: usize,
+    variables: FxHashSet<InferenceVar>,
 }
 
 impl<'infer, 'i, I: Interner> TySizeVisitor<'infer, 'i, I> {
@@ -34,6 +83,8 @@ impl<'infer, 'i, I: Interner> TySizeVisitor<'infer, 'i, I> {
             size: 0,
             depth: 0,
             max_size: 0,
+            max_depth: 0,
+            variables: FxHashSet::default(),
         }
     }
 }
@@ -55,6 +106,7 @@ impl<'infer, 'i, I: Interner> Visitor<'i, I> for TySizeVisitor<'infer, 'i, I> {
         self.max_size = max(self.size, self.max_size);
 
         self.depth += 1;
+        self.max_depth = max(self.depth, self.max_depth);
         ty.super_visit_with(self, outer_binder);
         self.depth -= 1;
 
@@ -65,6 +117,18 @@ impl<'infer, 'i, I: Interner> Visitor<'i, I> for TySizeVisitor<'infer, 'i, I> {
         }
     }
 
+    fn visit_inference_var(&mut self, var: InferenceVar, outer_binder: DebruijnIndex) {
+        // Types are normalized in `visit_ty`, but lifetimes and consts
+        // may still refer to bound variables.
+        match self.infer.probe_var(var) {
+            Some(value) => value.visit_with(self, outer_binder),
+            None => {
+                let root = self.infer.inference_var_root(var);
+                self.variables.insert(root);
+            }
+        }
+    }
+
     fn interner(&self) -> &'i I {
         self.interner
     }
@@ -115,4 +179,55 @@ mod tests {
         vec![&ty0, &ty1].visit_with(&mut visitor, DebruijnIndex::INNERMOST);
         assert!(visitor.max_size == 5);
     }
+
+    #[test]
+    fn depth() {
+        use chalk_integration::interner::ChalkIr;
+        let interner = &ChalkIr;
+        let mut table = InferenceTable::<chalk_integration::interner::ChalkIr>::new();
+        let _u1 = table.new_universe();
+
+        // Vec<Vec<Vec<T>>>, Vec<T>
+        let ty0 = ty!(apply (item 0)
+                      (apply (item 0)
+                       (apply (item 0)
+                        (placeholder 1))));
+        let ty1 = ty!(apply (item 0) (placeholder 1));
+
+        let mut visitor = TySizeVisitor::new(interner, &mut table);
+        vec![&ty0, &ty1].visit_with(&mut visitor, DebruijnIndex::INNERMOST);
+        assert!(visitor.max_depth == 4);
+    }
+
+    #[test]
+    fn variables() {
+        use chalk_integration::interner::ChalkIr;
+        let interner = &ChalkIr;
+        let mut table = InferenceTable::<chalk_integration::interner::ChalkIr>::new();
+        let environment0 = &Environment::new(interner);
+        let a = table.new_variable(UniverseIndex::ROOT).to_ty(interner);
+        let b = table.new_variable(UniverseIndex::ROOT).to_ty(interner);
+        let c = table.new_variable(UniverseIndex::ROOT).to_ty(interner);
+        table.unify(interner, environment0, &a, &b).unwrap();
+
+        // Vec<A>, Vec<B>, Vec<C>, where A = B
+        let tys = vec![
+            ty!(apply (item 0) (expr a)),
+            ty!(apply (item 0) (expr b)),
+            ty!(apply (item 0) (expr c)),
+        ];
+
+        assert!(needs_truncation(
+            interner,
+            &mut table,
+            TruncationLimit::Variables(1),
+            &tys
+        ));
+        assert!(!needs_truncation(
+            interner,
+            &mut table,
+            TruncationLimit::Variables(2),
+            &tys
+        ));
+    }
 }
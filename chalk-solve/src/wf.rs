@@ -12,31 +12,168 @@ use chalk_ir::interner::Interner;
 use chalk_ir::visit::{Visit, Visitor};
 use chalk_ir::*;
 
+/// An item that does not meet its well-formedness requirements, along
+/// with the obligations of the item that could not be proven.
 #[derive(Debug)]
 pub enum WfError<I: Interner> {
-    IllFormedTypeDecl(chalk_ir::AdtId<I>),
-    IllFormedTraitImpl(chalk_ir::TraitId<I>),
+    IllFormedTypeDecl {
+        adt_id: AdtId<I>,
+        obligations: Vec<WfObligation<I>>,
+    },
+    IllFormedTraitImpl {
+        trait_id: TraitId<I>,
+        impl_id: ImplId<I>,
+        obligations: Vec<WfObligation<I>>,
+    },
+    IllFormedTraitDecl {
+        trait_id: TraitId<I>,
+        obligations: Vec<WfObligation<I>>,
+    },
+    IllFormedFnDef {
+        fn_def_id: FnDefId<I>,
+        obligations: Vec<WfObligation<I>>,
+    },
+    IllFormedOpaqueTy {
+        opaque_ty_id: OpaqueTyId<I>,
+        obligations: Vec<WfObligation<I>>,
+    },
+}
+
+impl<I: Interner> WfError<I> {
+    /// The obligations of the item that could not be proven.
+    pub fn obligations(&self) -> &[WfObligation<I>] {
+        match self {
+            WfError::IllFormedTypeDecl { obligations, .. }
+            | WfError::IllFormedTraitImpl { obligations, .. }
+            | WfError::IllFormedTraitDecl { obligations, .. }
+            | WfError::IllFormedFnDef { obligations, .. }
+            | WfError::IllFormedOpaqueTy { obligations, .. } => obligations,
+        }
+    }
 }
 
 impl<I: Interner> fmt::Display for WfError<I> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            WfError::IllFormedTypeDecl(id) => write!(
+            WfError::IllFormedTypeDecl { adt_id, .. } => write!(
                 f,
                 "type declaration `{:?}` does not meet well-formedness requirements",
-                id
-            ),
-            WfError::IllFormedTraitImpl(id) => write!(
+                adt_id
+            )?,
+            WfError::IllFormedTraitImpl { trait_id, .. } => write!(
                 f,
                 "trait impl for `{:?}` does not meet well-formedness requirements",
-                id
-            ),
+                trait_id
+            )?,
+            WfError::IllFormedTraitDecl { trait_id, .. } => write!(
+                f,
+                "trait declaration `{:?}` does not meet well-formedness requirements",
+                trait_id
+            )?,
+            WfError::IllFormedFnDef { fn_def_id, .. } => write!(
+                f,
+                "function definition `{:?}` does not meet well-formedness requirements",
+                fn_def_id
+            )?,
+            WfError::IllFormedOpaqueTy { opaque_ty_id, .. } => write!(
+                f,
+                "opaque type `{:?}` does not meet well-formedness requirements",
+                opaque_ty_id
+            )?,
+        }
+
+        let obligations = self.obligations();
+        if obligations.is_empty() {
+            return Ok(());
+        }
+        write!(f, ":")?;
+        if let WfError::IllFormedTraitImpl { impl_id, .. } = self {
+            write!(f, " impl `{:?}`", impl_id)?;
+        }
+        write!(f, " cannot prove ")?;
+        for (index, obligation) in obligations.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", obligation)?;
         }
+        Ok(())
     }
 }
 
 impl<I: Interner> std::error::Error for WfError<I> {}
 
+/// A goal that an item must satisfy to be well-formed, like
+/// `WellFormed(Vec<T>)` or `Implemented(T: Copy)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WfObligation<I: Interner> {
+    /// The part of the item that the obligation comes from.
+    pub origin: WfOrigin<I>,
+
+    /// The goal. It refers to the binders of the item, or for an
+    /// associated type, to those of the associated type value or
+    /// declaration.
+    pub goal: Goal<I>,
+}
+
+impl<I: Interner> fmt::Display for WfObligation<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{:?}` (required by {})", self.goal, self.origin)
+    }
+}
+
+/// The part of an item that a well-formedness obligation comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WfOrigin<I: Interner> {
+    /// The field of an ADT with this index.
+    Field(usize),
+
+    /// The where clause of the item with this index.
+    WhereClause(usize),
+
+    /// The requirement that all fields of an ADT but the last are `Sized`.
+    SizedFields,
+
+    /// The trait reference of an impl.
+    TraitRef,
+
+    /// The rules for impls of a well-known trait, like `Copy`.
+    WellKnownTrait,
+
+    /// An associated type: its value in an impl, or its bounds and
+    /// where clauses in a trait declaration.
+    AssociatedTy(AssocTypeId<I>),
+
+    /// The argument of a function with this index.
+    Argument(usize),
+
+    /// The return type of a function.
+    ReturnType,
+
+    /// The hidden type of an opaque type.
+    HiddenTy,
+
+    /// The bound of an opaque type with this index.
+    Bound(usize),
+}
+
+impl<I: Interner> fmt::Display for WfOrigin<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WfOrigin::Field(index) => write!(f, "field {}", index),
+            WfOrigin::WhereClause(index) => write!(f, "where clause {}", index),
+            WfOrigin::SizedFields => write!(f, "the fields that must be `Sized`"),
+            WfOrigin::TraitRef => write!(f, "the trait reference"),
+            WfOrigin::WellKnownTrait => write!(f, "the rules for impls of the trait"),
+            WfOrigin::AssociatedTy(id) => write!(f, "associated type `{:?}`", id),
+            WfOrigin::Argument(index) => write!(f, "argument {}", index),
+            WfOrigin::ReturnType => write!(f, "the return type"),
+            WfOrigin::HiddenTy => write!(f, "the hidden type"),
+            WfOrigin::Bound(index) => write!(f, "bound {}", index),
+        }
+    }
+}
+
 pub struct WfSolver<'db, I: Interner> {
     db: &'db dyn RustIrDatabase<I>,
     solver_choice: SolverChoice,
@@ -89,9 +226,13 @@ impl<'i, I: Interner> Visitor<'i, I> for InputTypeCollector<'i, I> {
     fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) {
         let interner = self.interner();
 
+        // Types that refer to binders within the value, like `&'a u32` in
+        // `forall<'a> { T: Foo<&'a u32> }`, cannot be proven well-formed outside of those
+        // binders, so we leave them out, like the higher-kinded types below.
         let mut push_ty = || {
-            self.types
-                .push(ty.shifted_out_to(interner, outer_binder).unwrap())
+            if let Ok(ty) = ty.shifted_out_to(interner, outer_binder) {
+                self.types.push(ty)
+            }
         };
         match ty.data(interner) {
             TyData::Apply(apply) => {
@@ -135,6 +276,57 @@ impl<'i, I: Interner> Visitor<'i, I> for InputTypeCollector<'i, I> {
     }
 }
 
+/// Collects the obligations of an item that share the same binders and
+/// environment. Each obligation is proven as a separate goal of the form
+/// `forall<binders> { if (environment) { goal } }`, so that we can
+/// report exactly which ones do not hold.
+struct ObligationCollector<'i, I: Interner> {
+    interner: &'i I,
+    binders: VariableKinds<I>,
+    environment: ProgramClauses<I>,
+    obligations: Vec<(WfObligation<I>, Goal<I>)>,
+}
+
+impl<'i, I: Interner> ObligationCollector<'i, I> {
+    fn new<C>(
+        interner: &'i I,
+        binders: VariableKinds<I>,
+        environment: impl IntoIterator<Item = C>,
+    ) -> Self
+    where
+        C: CastTo<ProgramClause<I>>,
+    {
+        Self {
+            interner,
+            binders,
+            environment: ProgramClauses::from(interner, environment),
+            obligations: Vec::new(),
+        }
+    }
+
+    /// Adds the obligation that `goal` holds.
+    fn push(&mut self, origin: WfOrigin<I>, goal: impl CastTo<Goal<I>>) {
+        let interner = self.interner;
+        let goal: Goal<I> = goal.cast(interner);
+        let closed_goal = GoalData::Implies(self.environment.clone(), goal.clone())
+            .intern(interner)
+            .quantify(interner, QuantifierKind::ForAll, self.binders.clone());
+        self.obligations
+            .push((WfObligation { origin, goal }, closed_goal));
+    }
+
+    /// Adds the obligations that each of `types` is well-formed.
+    fn push_well_formed(&mut self, origin: WfOrigin<I>, types: Vec<Ty<I>>) {
+        for ty in types {
+            self.push(origin.clone(), ty.well_formed());
+        }
+    }
+
+    fn into_obligations(self) -> Vec<(WfObligation<I>, Goal<I>)> {
+        self.obligations
+    }
+}
+
 impl<'db, I> WfSolver<'db, I>
 where
     I: Interner,
@@ -155,147 +347,315 @@ where
         //     data: Vec<T>
         // }
         // ```
-        let struct_datum = self.db.adt_datum(adt_id);
+        let adt_datum = self.db.adt_datum(adt_id);
+        let (binders, adt) = adt_datum.binders.as_ref().into();
 
-        let mut gb = GoalBuilder::new(self.db);
-        let struct_data = struct_datum
-            .binders
-            .map_ref(|b| (&b.fields, &b.where_clauses));
-
-        // We make a goal like...
+        // We make obligations like...
         //
-        // forall<T> { ... }
-        let wg_goal = gb.forall(&struct_data, (), |gb, _, (fields, where_clauses), ()| {
-            let interner = gb.interner();
+        // forall<T> { if (FromEnv(T: Eq)) { WellFormed(Vec<T>) } }
+        let mut obligations = ObligationCollector::new(
+            interner,
+            binders,
+            adt.where_clauses
+                .iter()
+                .cloned()
+                .map(|wc| wc.into_from_env_goal(interner)),
+        );
 
-            // struct is well-formed in terms of Sized
-            let sized_constraint_goal = WfWellKnownGoals::struct_sized_constraint(gb.db(), fields);
+        // WellFormed(Vec<T>), for each field type `Vec<T>` or type that appears in the where clauses
+        for (index, field) in adt.fields.iter().enumerate() {
+            obligations.push_well_formed(
+                WfOrigin::Field(index),
+                InputTypeCollector::types_in(interner, field),
+            );
+        }
+        for (index, where_clause) in adt.where_clauses.iter().enumerate() {
+            obligations.push_well_formed(
+                WfOrigin::WhereClause(index),
+                InputTypeCollector::types_in(interner, where_clause),
+            );
+        }
+
+        // struct is well-formed in terms of Sized
+        if let Some(goal) = WfWellKnownGoals::struct_sized_constraint(self.db, &adt.fields) {
+            obligations.push(WfOrigin::SizedFields, goal);
+        }
 
-            // (FromEnv(T: Eq) => ...)
-            gb.implies(
-                where_clauses
+        let obligations = self.unproven(obligations.into_obligations());
+        if obligations.is_empty() {
+            Ok(())
+        } else {
+            Err(WfError::IllFormedTypeDecl {
+                adt_id,
+                obligations,
+            })
+        }
+    }
+
+    pub fn verify_trait_impl(&self, impl_id: ImplId<I>) -> Result<(), WfError<I>> {
+        let impl_datum = self.db.impl_datum(impl_id);
+        let trait_id = impl_datum.trait_id();
+
+        let obligations = impl_header_wf_obligations(self.db, impl_id)
+            .into_iter()
+            .chain(
+                impl_datum
+                    .associated_ty_value_ids
                     .iter()
-                    .cloned()
-                    .map(|wc| wc.into_from_env_goal(interner)),
-                |gb| {
-                    // WellFormed(Vec<T>), for each field type `Vec<T>` or type that appears in the where clauses
-                    let types =
-                        InputTypeCollector::types_in(gb.interner(), (&fields, &where_clauses));
-
-                    gb.all(
-                        types
-                            .into_iter()
-                            .map(|ty| ty.well_formed().cast(interner))
-                            .chain(sized_constraint_goal.into_iter()),
-                    )
-                },
+                    .flat_map(|&id| compute_assoc_ty_obligations(self.db, id)),
             )
-        });
+            .collect();
+
+        let obligations = self.unproven(obligations);
+        if obligations.is_empty() {
+            Ok(())
+        } else {
+            Err(WfError::IllFormedTraitImpl {
+                trait_id,
+                impl_id,
+                obligations,
+            })
+        }
+    }
 
-        let wg_goal = wg_goal.into_closed_goal(interner);
+    pub fn verify_trait_decl(&self, trait_id: TraitId<I>) -> Result<(), WfError<I>> {
+        let interner = self.db.interner();
 
-        let is_legal = match self.solver_choice.into_solver().solve(self.db, &wg_goal) {
-            Some(sol) => sol.is_unique(),
-            None => false,
+        // Given a trait like
+        //
+        // ```rust
+        // trait Foo<T>: Bar<HashSet<T>> where T: Eq {
+        //     type Item: Baz<Vec<Self::Item>> where T: Clone;
+        // }
+        // ```
+        let trait_datum = self.db.trait_datum(trait_id);
+        let (binders, trait_bound) = trait_datum.binders.as_ref().into();
+        let trait_ref = TraitRef {
+            trait_id,
+            substitution: trait_datum.binders.identity_substitution(interner),
         };
 
-        if !is_legal {
-            Err(WfError::IllFormedTypeDecl(adt_id))
-        } else {
+        // We make obligations like...
+        //
+        // forall<Self, T> { if (FromEnv(Self: Foo<T>), FromEnv(Self: Bar<HashSet<T>>), ...) { ... } }
+        let mut obligations = ObligationCollector::new(
+            interner,
+            binders,
+            trait_wf_environment(interner, &trait_ref, &trait_bound.where_clauses),
+        );
+
+        // WellFormed(HashSet<T>), for each type that appears in the where clauses, including the
+        // supertraits
+        for (index, where_clause) in trait_bound.where_clauses.iter().enumerate() {
+            obligations.push_well_formed(
+                WfOrigin::WhereClause(index),
+                trait_decl_input_types(self.db, &trait_ref, where_clause),
+            );
+        }
+
+        // ...along with the obligations of each of its associated types.
+        let mut obligations = obligations.into_obligations();
+        for &id in &trait_datum.associated_ty_ids {
+            obligations.extend(compute_assoc_ty_decl_obligations(self.db, id));
+        }
+
+        let obligations = self.unproven(obligations);
+        if obligations.is_empty() {
             Ok(())
+        } else {
+            Err(WfError::IllFormedTraitDecl {
+                trait_id,
+                obligations,
+            })
         }
     }
 
-    pub fn verify_trait_impl(&self, impl_id: ImplId<I>) -> Result<(), WfError<I>> {
+    pub fn verify_fn_def(&self, fn_def_id: FnDefId<I>) -> Result<(), WfError<I>> {
         let interner = self.db.interner();
 
-        let impl_datum = self.db.impl_datum(impl_id);
-        let trait_id = impl_datum.trait_id();
+        // Given a function like
+        //
+        // ```rust
+        // fn foo<T>(set: HashSet<T>) -> Vec<T> where T: Clone;
+        // ```
+        let fn_def_datum = self.db.fn_def_datum(fn_def_id);
+        let (binders, fn_def) = fn_def_datum.binders.as_ref().into();
 
-        let impl_goal = Goal::all(
+        // We make obligations like...
+        //
+        // forall<T> { if (FromEnv(T: Clone)) { WellFormed(HashSet<T>) } }
+        let mut obligations = ObligationCollector::new(
             interner,
-            impl_header_wf_goal(self.db, impl_id).into_iter().chain(
-                impl_datum
-                    .associated_ty_value_ids
-                    .iter()
-                    .filter_map(|&id| compute_assoc_ty_goal(self.db, id)),
-            ),
+            binders,
+            fn_def
+                .where_clauses
+                .iter()
+                .cloned()
+                .map(|wc| wc.into_from_env_goal(interner)),
         );
 
-        debug!("WF trait goal: {:?}", impl_goal);
+        // WellFormed(HashSet<T>), for each type that appears in the signature or the where clauses
+        for (index, ty) in fn_def.argument_types.iter().enumerate() {
+            obligations.push_well_formed(
+                WfOrigin::Argument(index),
+                InputTypeCollector::types_in(interner, ty),
+            );
+        }
+        obligations.push_well_formed(
+            WfOrigin::ReturnType,
+            InputTypeCollector::types_in(interner, &fn_def.return_type),
+        );
+        for (index, where_clause) in fn_def.where_clauses.iter().enumerate() {
+            obligations.push_well_formed(
+                WfOrigin::WhereClause(index),
+                InputTypeCollector::types_in(interner, where_clause),
+            );
+        }
 
-        let is_legal = match self
-            .solver_choice
-            .into_solver()
-            .solve(self.db, &impl_goal.into_closed_goal(interner))
-        {
-            Some(sol) => sol.is_unique(),
-            None => false,
-        };
+        let obligations = self.unproven(obligations.into_obligations());
+        if obligations.is_empty() {
+            Ok(())
+        } else {
+            Err(WfError::IllFormedFnDef {
+                fn_def_id,
+                obligations,
+            })
+        }
+    }
+
+    pub fn verify_opaque_ty(&self, opaque_ty_id: OpaqueTyId<I>) -> Result<(), WfError<I>> {
+        let interner = self.db.interner();
+
+        // Given an opaque type like
+        //
+        // ```rust
+        // opaque type Foo<T>: Clone = Vec<T>;
+        // ```
+        let opaque_ty_datum = self.db.opaque_ty_data(opaque_ty_id);
+        let (binders, bound) = opaque_ty_datum.bound.as_ref().into();
+
+        // We make obligations like...
+        //
+        // forall<T> { WellFormed(Vec<T>) }
+        let mut obligations = ObligationCollector::new(interner, binders, None::<ProgramClause<I>>);
+
+        // WellFormed(Vec<T>), for each type that appears in the hidden type
+        obligations.push_well_formed(
+            WfOrigin::HiddenTy,
+            InputTypeCollector::types_in(interner, &bound.hidden_ty),
+        );
+
+        // The bounds of the opaque type, with the hidden type as the self type, are
+        // `Vec<T>: Clone` here, which we convert to `WellFormed(Vec<T>: Clone)`.
+        let bounds = bound
+            .bounds
+            .substitute(interner, &[bound.hidden_ty.clone().cast(interner)]);
+        for (index, qwc) in bounds.into_iter().enumerate() {
+            obligations.push(WfOrigin::Bound(index), qwc.into_well_formed_goal(interner));
+        }
 
-        if is_legal {
+        let obligations = self.unproven(obligations.into_obligations());
+        if obligations.is_empty() {
             Ok(())
         } else {
-            Err(WfError::IllFormedTraitImpl(trait_id))
+            Err(WfError::IllFormedOpaqueTy {
+                opaque_ty_id,
+                obligations,
+            })
         }
     }
+
+    /// Solves the goals of `obligations`, and returns the obligations
+    /// whose goals do not hold. A goal holds only if it has a unique
+    /// solution.
+    ///
+    /// Most declarations are well-formed, so we first solve all the
+    /// goals at once, and only solve them one by one to find the
+    /// culprits if that fails.
+    fn unproven(&self, obligations: Vec<(WfObligation<I>, Goal<I>)>) -> Vec<WfObligation<I>> {
+        let interner = self.db.interner();
+        let solver = &mut self.solver_choice.into_solver();
+        let mut holds = |goal: Goal<I>| {
+            debug!("WF goal: {:?}", goal);
+            match solver.solve(self.db, &goal.into_closed_goal(interner)) {
+                Some(sol) => sol.is_unique(),
+                None => false,
+            }
+        };
+
+        if obligations.len() > 1
+            && holds(Goal::all(
+                interner,
+                obligations.iter().map(|(_, goal)| goal.clone()),
+            ))
+        {
+            return vec![];
+        }
+
+        obligations
+            .into_iter()
+            .filter(|(_, goal)| !holds(goal.clone()))
+            .map(|(obligation, _)| obligation)
+            .collect()
+    }
 }
 
-fn impl_header_wf_goal<I: Interner>(
+fn impl_header_wf_obligations<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     impl_id: ImplId<I>,
-) -> Option<Goal<I>> {
+) -> Vec<(WfObligation<I>, Goal<I>)> {
+    let interner = db.interner();
     let impl_datum = db.impl_datum(impl_id);
 
     if !impl_datum.is_positive() {
-        return None;
+        return vec![];
     }
 
-    let impl_fields = impl_datum
-        .binders
-        .map_ref(|v| (&v.trait_ref, &v.where_clauses));
-
-    let mut gb = GoalBuilder::new(db);
-    // forall<P0...Pn> {...}
-    let well_formed_goal = gb.forall(&impl_fields, (), |gb, _, (trait_ref, where_clauses), ()| {
-        let interner = gb.interner();
-
-        let trait_constraint_goal = WfWellKnownGoals::inside_impl(gb.db(), &trait_ref);
-
-        // if (WC && input types are well formed) { ... }
-        gb.implies(
-            impl_wf_environment(interner, &where_clauses, &trait_ref),
-            |gb| {
-                // We retrieve all the input types of the where clauses appearing on the trait impl,
-                // e.g. in:
-                // ```
-                // impl<T, K> Foo for (T, K) where T: Iterator<Item = (HashSet<K>, Vec<Box<T>>)> { ... }
-                // ```
-                // we would retrieve `HashSet<K>`, `Box<T>`, `Vec<Box<T>>`, `(HashSet<K>, Vec<Box<T>>)`.
-                // We will have to prove that these types are well-formed (e.g. an additional `K: Hash`
-                // bound would be needed here).
-                let types = InputTypeCollector::types_in(gb.interner(), &where_clauses);
-
-                // Things to prove well-formed: input types of the where-clauses, projection types
-                // appearing in the header, associated type values, and of course the trait ref.
-                debug!("verify_trait_impl: input_types={:?}", types);
-                let goals = types
-                    .into_iter()
-                    .map(|ty| ty.well_formed().cast(interner))
-                    .chain(Some((*trait_ref).clone().well_formed().cast(interner)))
-                    .chain(trait_constraint_goal.into_iter());
-
-                gb.all::<_, Goal<I>>(goals)
-            },
-        )
-    });
+    let (binders, impl_bound) = impl_datum.binders.as_ref().into();
+    let ImplDatumBound {
+        trait_ref,
+        where_clauses,
+    } = impl_bound;
+
+    // forall<P0...Pn> { if (WC && input types are well formed) { ... } }
+    let mut obligations = ObligationCollector::new(
+        interner,
+        binders,
+        impl_wf_environment(interner, where_clauses, trait_ref),
+    );
+
+    // We retrieve all the input types of the where clauses appearing on the trait impl,
+    // e.g. in:
+    // ```
+    // impl<T, K> Foo for (T, K) where T: Iterator<Item = (HashSet<K>, Vec<Box<T>>)> { ... }
+    // ```
+    // we would retrieve `HashSet<K>`, `Box<T>`, `Vec<Box<T>>`, `(HashSet<K>, Vec<Box<T>>)`.
+    // We will have to prove that these types are well-formed (e.g. an additional `K: Hash`
+    // bound would be needed here).
+    for (index, where_clause) in where_clauses.iter().enumerate() {
+        obligations.push_well_formed(
+            WfOrigin::WhereClause(index),
+            InputTypeCollector::types_in(interner, where_clause),
+        );
+    }
 
-    Some(
-        gb.all(
-            iter::once(well_formed_goal)
-                .chain(WfWellKnownGoals::outside_impl(db, &impl_datum).into_iter()),
-        ),
-    )
+    // Things to prove well-formed: input types of the where-clauses, projection types
+    // appearing in the header, associated type values, and of course the trait ref.
+    obligations.push(WfOrigin::TraitRef, trait_ref.clone().well_formed());
+    if let Some(goal) = WfWellKnownGoals::inside_impl(db, trait_ref) {
+        obligations.push(WfOrigin::WellKnownTrait, goal);
+    }
+
+    // The goals that do not assume anything about the environment are closed already.
+    let mut obligations = obligations.into_obligations();
+    if let Some(goal) = WfWellKnownGoals::outside_impl(db, &impl_datum) {
+        let obligation = WfObligation {
+            origin: WfOrigin::WellKnownTrait,
+            goal: goal.clone(),
+        };
+        obligations.push((obligation, goal));
+    }
+    obligations
 }
 
 /// Creates the conditions that an impl (and its contents of an impl)
@@ -332,8 +692,120 @@ fn impl_wf_environment<'i, I: Interner>(
     wc.chain(types_wf)
 }
 
+/// Creates the conditions that a trait declaration can assume to be
+/// true when proving that it is well-formed: the trait reference
+/// `Self: Trait<..>` itself, and the where clauses of the trait.
+fn trait_wf_environment<'i, I: Interner>(
+    interner: &'i I,
+    trait_ref: &TraitRef<I>,
+    where_clauses: &'i [QuantifiedWhereClause<I>],
+) -> impl Iterator<Item = ProgramClause<I>> + 'i {
+    let trait_ref_from_env = trait_ref.clone().from_env().cast(interner);
+
+    iter::once(trait_ref_from_env).chain(
+        where_clauses
+            .iter()
+            .cloned()
+            .map(move |qwc| qwc.into_from_env_goal(interner).cast(interner)),
+    )
+}
+
+/// Returns the input types of `value`, a part of the declaration of the
+/// trait `trait_ref`, where `trait_ref` is `Self: Trait<..>`. Projections
+/// of the trait's own associated types, like `<Self as Trait<..>>::Item`,
+/// are left out: they are well-formed because `Self: Trait<..>` holds,
+/// but proving `WellFormed(Self: Trait<..>)` would need the declaration
+/// to be well-formed already. The types within them are still returned.
+fn trait_decl_input_types<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_ref: &TraitRef<I>,
+    value: impl Visit<I>,
+) -> Vec<Ty<I>> {
+    let interner = db.interner();
+    let mut types = InputTypeCollector::types_in(interner, value);
+    types.retain(|ty| match ty.data(interner) {
+        TyData::Alias(AliasTy::Projection(projection)) => {
+            db.trait_ref_from_projection(projection) != *trait_ref
+        }
+        _ => true,
+    });
+    types
+}
+
+/// Creates the obligations of the declaration of an associated type,
+/// quantified over the parameters of the associated type and of its
+/// trait. For example, in:
+///
+/// ```ignore
+/// trait Foo<T> {
+///     type Item: Baz<Vec<Self::Item>> where T: Clone;
+/// }
+/// ```
+///
+/// we would issue the obligation:
+///
+/// ```ignore
+/// forall<Self, T> {
+///     if (FromEnv(Self: Foo<T>), FromEnv(T: Clone)) {
+///         WellFormed(Vec<<Self as Foo<T>>::Item>)
+///     }
+/// }
+/// ```
+///
+/// Unlike for impls, the bounds themselves need not hold: they are
+/// proven by every impl of the trait (see `compute_assoc_ty_obligations`).
+fn compute_assoc_ty_decl_obligations<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    assoc_ty_id: AssocTypeId<I>,
+) -> Vec<(WfObligation<I>, Goal<I>)> {
+    let interner = db.interner();
+    let assoc_ty_datum = db.associated_ty_data(assoc_ty_id);
+    let (binders, assoc_ty) = assoc_ty_datum.binders.as_ref().into();
+
+    let projection = ProjectionTy {
+        associated_ty_id: assoc_ty_id,
+        substitution: assoc_ty_datum.binders.identity_substitution(interner),
+    };
+    let trait_ref = db.trait_ref_from_projection(&projection);
+    let trait_datum = db.trait_datum(trait_ref.trait_id);
+    let trait_where_clauses = trait_datum
+        .where_clauses()
+        .substitute(interner, &trait_ref.substitution);
+
+    // forall<Self, T> { if (/* trait WF environment */, /* where clauses on the associated type */) { ... } }
+    let mut obligations = ObligationCollector::new(
+        interner,
+        binders,
+        trait_wf_environment(interner, &trait_ref, &trait_where_clauses).chain(
+            assoc_ty
+                .where_clauses
+                .iter()
+                .cloned()
+                .map(|qwc| qwc.into_from_env_goal(interner).cast(interner)),
+        ),
+    );
+
+    // The bounds, with the projection as the self type, like
+    // `<Self as Foo<T>>::Item: Baz<Vec<<Self as Foo<T>>::Item>>`
+    let self_ty = TyData::Alias(AliasTy::Projection(projection)).intern(interner);
+    let bounds: Vec<_> = assoc_ty
+        .bounds
+        .iter()
+        .cloned()
+        .flat_map(|qb| qb.into_where_clauses(interner, self_ty.clone()))
+        .collect();
+
+    // We require that `WellFormed(T)` for each type that appears in the bounds or the where
+    // clauses
+    obligations.push_well_formed(
+        WfOrigin::AssociatedTy(assoc_ty_id),
+        trait_decl_input_types(db, &trait_ref, (&bounds, &assoc_ty.where_clauses)),
+    );
+    obligations.into_obligations()
+}
+
 /// Associated type values are special because they can be parametric (independently of
-/// the impl), so we issue a special goal which is quantified using the binders of the
+/// the impl), so we issue special obligations which are quantified using the binders of the
 /// associated type value, for example in:
 ///
 /// ```ignore
@@ -346,115 +818,103 @@ fn impl_wf_environment<'i, I: Interner>(
 /// }
 /// ```
 ///
-/// we would issue the following subgoal: `forall<'a> { WellFormed(Box<&'a T>) }`.
-///
-/// Note that there is no binder for `T` in the above: the goal we
-/// generate is expected to be exected in the context of the
-/// larger WF goal for the impl, which already has such a
-/// binder. So the entire goal for the impl might be:
+/// we would issue the following obligations, where the binders of the
+/// value include those of the impl:
 ///
 /// ```ignore
-/// forall<T> {
-///     WellFormed(Box<T>) /* this comes from the impl, not this routine */,
-///     forall<'a> { WellFormed(Box<&'a T>) },
+/// forall<T, 'a> {
+///     if (/* impl WF environment */, FromEnv(Box<T>: 'a)) {
+///         WellFormed(Box<&'a T>)
+///     }
+/// }
+///
+/// forall<T, 'a> {
+///     if (/* impl WF environment */, FromEnv(Box<T>: 'a)) {
+///         WellFormed(Box<&'a T>: Clone)
+///     }
 /// }
 /// ```
-fn compute_assoc_ty_goal<I: Interner>(
+fn compute_assoc_ty_obligations<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     assoc_ty_id: AssociatedTyValueId<I>,
-) -> Option<Goal<I>> {
-    let mut gb = GoalBuilder::new(db);
+) -> Vec<(WfObligation<I>, Goal<I>)> {
+    let interner = db.interner();
     let assoc_ty = &db.associated_ty_value(assoc_ty_id);
 
     // Create `forall<T, 'a> { .. }`
-    Some(gb.forall(
-        &assoc_ty.value.map_ref(|v| &v.ty),
-        assoc_ty_id,
-        |gb, assoc_ty_substitution, value_ty, assoc_ty_id| {
-            let interner = gb.interner();
-            let db = gb.db();
-
-            // Hmm, because `Arc<AssociatedTyValue>` does not implement `Fold`, we can't pass this value through,
-            // just the id, so we have to fetch `assoc_ty` from the database again.
-            // Implementing `Fold` for `AssociatedTyValue` doesn't *quite* seem right though, as that
-            // would result in a deep clone, and the value is inert. We could do some more refatoring
-            // (move the `Arc` behind a newtype, for example) to fix this, but for now doesn't
-            // seem worth it.
-            let assoc_ty = &db.associated_ty_value(assoc_ty_id);
-
-            let (impl_parameters, projection) = db
-                .impl_parameters_and_projection_from_associated_ty_value(
-                    &assoc_ty_substitution.parameters(interner),
-                    assoc_ty,
-                );
-
-            // If (/* impl WF environment */) { ... }
-            let impl_id = assoc_ty.impl_id;
-            let impl_datum = &db.impl_datum(impl_id);
-            let ImplDatumBound {
-                trait_ref: impl_trait_ref,
-                where_clauses: impl_where_clauses,
-            } = impl_datum.binders.substitute(interner, impl_parameters);
-            let impl_wf_clauses =
-                impl_wf_environment(interner, &impl_where_clauses, &impl_trait_ref);
-            gb.implies(impl_wf_clauses, |gb| {
-                // Get the bounds and where clauses from the trait
-                // declaration, substituted appropriately.
-                //
-                // From our example:
-                //
-                // * bounds
-                //     * original in trait, `Clone`
-                //     * after substituting impl parameters, `Clone`
-                //     * note that the self-type is not yet supplied for bounds,
-                //       we will do that later
-                // * where clauses
-                //     * original in trait, `Self: 'a`
-                //     * after substituting impl parameters, `Box<!T>: '!a`
-                let assoc_ty_datum = db.associated_ty_data(projection.associated_ty_id);
-                let AssociatedTyDatumBound {
-                    bounds: defn_bounds,
-                    where_clauses: defn_where_clauses,
-                } = assoc_ty_datum
-                    .binders
-                    .substitute(interner, &projection.substitution);
-
-                // Create `if (/* where clauses on associated type value */) { .. }`
-                gb.implies(
-                    defn_where_clauses
-                        .iter()
-                        .cloned()
-                        .map(|qwc| qwc.into_from_env_goal(interner)),
-                    |gb| {
-                        let types = InputTypeCollector::types_in(gb.interner(), value_ty);
-
-                        // We require that `WellFormed(T)` for each type that appears in the value
-                        let wf_goals = types
-                            .into_iter()
-                            .map(|ty| ty.well_formed())
-                            .casted(interner);
-
-                        // Check that the `value_ty` meets the bounds from the trait.
-                        // Here we take the substituted bounds (`defn_bounds`) and we
-                        // supply the self-type `value_ty` to yield the final result.
-                        //
-                        // In our example, the bound was `Clone`, so the combined
-                        // result is `Box<!T>: Clone`. This is then converted to a
-                        // well-formed goal like `WellFormed(Box<!T>: Clone)`.
-                        let bound_goals = defn_bounds
-                            .iter()
-                            .cloned()
-                            .flat_map(|qb| qb.into_where_clauses(interner, (*value_ty).clone()))
-                            .map(|qwc| qwc.into_well_formed_goal(interner))
-                            .casted(interner);
-
-                        // Concatenate the WF goals of inner types + the requirements from trait
-                        gb.all::<_, Goal<I>>(wf_goals.chain(bound_goals))
-                    },
-                )
-            })
-        },
-    ))
+    let (binders, value) = assoc_ty.value.as_ref().into();
+    let value_ty = &value.ty;
+    let assoc_ty_substitution = assoc_ty.value.identity_substitution(interner);
+    let (impl_parameters, projection) = db.impl_parameters_and_projection_from_associated_ty_value(
+        assoc_ty_substitution.parameters(interner),
+        assoc_ty,
+    );
+
+    // If (/* impl WF environment */) { ... }
+    let impl_id = assoc_ty.impl_id;
+    let impl_datum = &db.impl_datum(impl_id);
+    let ImplDatumBound {
+        trait_ref: impl_trait_ref,
+        where_clauses: impl_where_clauses,
+    } = impl_datum.binders.substitute(interner, impl_parameters);
+    let impl_wf_clauses = impl_wf_environment(interner, &impl_where_clauses, &impl_trait_ref);
+
+    // Get the bounds and where clauses from the trait
+    // declaration, substituted appropriately.
+    //
+    // From our example:
+    //
+    // * bounds
+    //     * original in trait, `Clone`
+    //     * after substituting impl parameters, `Clone`
+    //     * note that the self-type is not yet supplied for bounds,
+    //       we will do that later
+    // * where clauses
+    //     * original in trait, `Self: 'a`
+    //     * after substituting impl parameters, `Box<!T>: '!a`
+    let assoc_ty_datum = db.associated_ty_data(projection.associated_ty_id);
+    let AssociatedTyDatumBound {
+        bounds: defn_bounds,
+        where_clauses: defn_where_clauses,
+    } = assoc_ty_datum
+        .binders
+        .substitute(interner, &projection.substitution);
+
+    // Create `if (/* where clauses on associated type value */) { .. }`
+    let mut obligations = ObligationCollector::new(
+        interner,
+        binders,
+        impl_wf_clauses.chain(
+            defn_where_clauses
+                .iter()
+                .cloned()
+                .map(|qwc| qwc.into_from_env_goal(interner).cast(interner)),
+        ),
+    );
+    let origin = WfOrigin::AssociatedTy(projection.associated_ty_id);
+
+    // We require that `WellFormed(T)` for each type that appears in the value
+    obligations.push_well_formed(
+        origin.clone(),
+        InputTypeCollector::types_in(interner, value_ty),
+    );
+
+    // Check that the `value_ty` meets the bounds from the trait.
+    // Here we take the substituted bounds (`defn_bounds`) and we
+    // supply the self-type `value_ty` to yield the final result.
+    //
+    // In our example, the bound was `Clone`, so the combined
+    // result is `Box<!T>: Clone`. This is then converted to a
+    // well-formed goal like `WellFormed(Box<!T>: Clone)`.
+    for qwc in defn_bounds
+        .iter()
+        .cloned()
+        .flat_map(|qb| qb.into_where_clauses(interner, value_ty.clone()))
+    {
+        obligations.push(origin.clone(), qwc.into_well_formed_goal(interner));
+    }
+
+    obligations.into_obligations()
 }
 
 /// Defines methods to compute well-formedness goals for well-known
@@ -9,6 +9,7 @@ use chalk_integration::db::ChalkDatabase;
 use chalk_integration::interner::ChalkIr;
 use chalk_integration::lowering::*;
 use chalk_integration::query::LoweringDatabase;
+use chalk_integration::trace::{self, TraceHandle};
 use chalk_solve::ext::*;
 use chalk_solve::{RustIrDatabase, SolverChoice};
 use docopt::Docopt;
@@ -29,6 +30,8 @@ Options:
   --goal=GOAL         Specifies a goal to evaluate (may be given more than once).
   --overflow-depth=N  Specifies the overflow depth [default: 10].
   --multiple          Output multiple answers instead of ambiguous solution.
+  --future-compat-warnings
+                      Accept impls that only overlap once upstream crates add impls, with a warning.
 ";
 
 /// This struct represents the various command line options available.
@@ -38,6 +41,7 @@ struct Args {
     flag_goal: Vec<String>,
     flag_overflow_depth: usize,
     flag_multiple: bool,
+    flag_future_compat_warnings: bool,
 }
 
 /// A loaded and parsed program.
@@ -51,11 +55,28 @@ impl LoadedProgram {
     /// a [`SolverChoice`].
     ///
     /// [`SolverChoice`]: struct.solve.SolverChoice.html
-    fn new(text: String, solver_choice: SolverChoice) -> Result<LoadedProgram> {
-        let db = ChalkDatabase::with(&text, solver_choice);
+    fn new(
+        text: String,
+        solver_choice: SolverChoice,
+        future_compat_warnings: bool,
+    ) -> Result<LoadedProgram> {
+        let mut db = ChalkDatabase::with(&text, solver_choice);
+        db.set_future_compat_warnings(future_compat_warnings);
         Ok(LoadedProgram { text, db })
     }
 
+    /// Checks the program, and prints the warnings of the check.
+    fn check(&self) -> Result<()> {
+        let _ = self.db.checked_program()?;
+        let coherence = self.db.coherence()?;
+        self.db.with_program(|_| {
+            for warning in &coherence.warnings {
+                eprintln!("warning: {}", warning);
+            }
+        });
+        Ok(())
+    }
+
     /// Parse a goal and attempt to solve it, using the specified solver.
     fn goal(
         &self,
@@ -115,6 +136,9 @@ fn run() -> Result<()> {
         exit(1);
     }
 
+    // Write solver traces to stderr, as filtered by `CHALK_DEBUG`.
+    let trace = trace::init();
+
     // Load the .chalk file, if given.
     let mut prog = None;
     if let Some(program) = &args.flag_program {
@@ -130,7 +154,7 @@ fn run() -> Result<()> {
     if args.flag_goal.is_empty() {
         // The user specified no goal. Enter interactive mode.
         readline_loop(&mut rustyline::Editor::new(), "?- ", |rl, line| {
-            if let Err(e) = process(args, line, rl, &mut prog) {
+            if let Err(e) = process(args, &trace, line, rl, &mut prog) {
                 eprintln!("error: {}", e);
             }
         })
@@ -194,6 +218,7 @@ where
 // still has Strings where it should have Enums... (e.g. solver_choice)
 fn process(
     args: &Args,
+    trace: &TraceHandle,
     command: &str,
     rl: &mut rustyline::Editor<()>,
     prog: &mut Option<LoadedProgram>,
@@ -206,21 +231,25 @@ fn process(
         help()
     } else if command == "program" {
         // Load a .chalk file via stdin, until EOF is found.
-        let chalk_prog = LoadedProgram::new(read_program(rl)?, args.solver_choice())?;
+        let chalk_prog = LoadedProgram::new(
+            read_program(rl)?,
+            args.solver_choice(),
+            args.flag_future_compat_warnings,
+        )?;
         // Let's do a sanity check before going forward.
-        let _ = chalk_prog.db.checked_program()?;
+        chalk_prog.check()?;
         *prog = Some(chalk_prog);
     } else if command.starts_with("load ") {
         // Load a .chalk file.
         let filename = &command["load ".len()..];
         let chalk_prog = load_program(args, filename)?;
         // Let's do a sanity check before going forward.
-        let _ = chalk_prog.db.checked_program()?;
+        chalk_prog.check()?;
         *prog = Some(chalk_prog);
     } else if command.starts_with("debug ") {
         match command.split_whitespace().nth(1) {
-            Some(level) => std::env::set_var("CHALK_DEBUG", level),
-            None => println!("debug <level> set debug level to <level>"),
+            Some(level) => trace.set_filter(level)?,
+            None => println!("debug <level> set debug level (or trace filter) to <level>"),
         }
     } else {
         // The command is either "print", "lowered", or a goal.
@@ -257,7 +286,11 @@ fn process(
 fn load_program(args: &Args, filename: &str) -> Result<LoadedProgram> {
     let mut text = String::new();
     File::open(filename)?.read_to_string(&mut text)?;
-    Ok(LoadedProgram::new(text, args.solver_choice())?)
+    Ok(LoadedProgram::new(
+        text,
+        args.solver_choice(),
+        args.flag_future_compat_warnings,
+    )?)
 }
 
 /// Print out help for commands in interpreter mode.
@@ -270,7 +303,7 @@ fn help() {
     println!("  print         print the current program");
     println!("  lowered       print the lowered program");
     println!("  <goal>        attempt to solve <goal>");
-    println!("  debug <level> set debug level to <level>");
+    println!("  debug <level> set debug level (or trace filter) to <level>");
 }
 
 /// Read a program from the command-line. Stop reading when EOF is read. If
@@ -287,10 +320,7 @@ fn read_program(rl: &mut rustyline::Editor<()>) -> Result<String> {
 
 impl Args {
     fn solver_choice(&self) -> SolverChoice {
-        SolverChoice::SLG {
-            max_size: self.flag_overflow_depth,
-            expected_answers: None,
-        }
+        SolverChoice::slg(self.flag_overflow_depth, None)
     }
 }
 
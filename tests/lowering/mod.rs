@@ -310,7 +310,7 @@ fn gat_parse() {
             }
 
             trait Baz {
-                type Item<'a, 'b, T>: Foo<Item<'b, T> = Container<T>> + Clone;
+                type Item<'a, 'b, T>: Foo<Item<'b, T> = Container<T>> + Clone + Sized;
             }
 
             trait Quux {
@@ -584,3 +584,78 @@ fn fn_defs() {
         }
     }
 }
+
+#[test]
+fn cyclic_supertraits() {
+    lowering_error! {
+        program {
+            trait A where Self: B { }
+            trait B where Self: C { }
+            trait C where Self: A { }
+        }
+        error_msg {
+            "cycle in the supertraits of trait `A`: `A` -> `B` -> `C` -> `A`"
+        }
+    }
+
+    // The cycle is reported for the first trait on it, not for the
+    // traits that merely lead to it.
+    lowering_error! {
+        program {
+            trait A where Self: B { }
+            trait B where Self: C { }
+            trait C where Self: B { }
+        }
+        error_msg {
+            "cycle in the supertraits of trait `B`: `B` -> `C` -> `B`"
+        }
+    }
+
+    // Reaching the same trait along several paths is not a cycle.
+    lowering_success! {
+        program {
+            trait A { }
+            trait B where Self: A { }
+            trait C where Self: A { }
+            trait D where Self: B, Self: C { }
+        }
+    }
+}
+
+#[test]
+fn cyclic_associated_types() {
+    lowering_error! {
+        program {
+            trait A {
+                type X;
+                type Y;
+            }
+            trait B where Self: A<X = <Self as A>::Y>, Self: A<Y = <Self as A>::X> { }
+        }
+        error_msg {
+            "cycle in the bounds of associated type `A::X`: `A::X` -> `A::Y` -> `A::X`"
+        }
+    }
+
+    // The equalities may come from several supertraits.
+    lowering_error! {
+        program {
+            trait A { type X; }
+            trait B { type Y; }
+            trait C where Self: A<X = <Self as B>::Y> { }
+            trait D where Self: C, Self: B<Y = <Self as A>::X> { }
+        }
+        error_msg {
+            "cycle in the bounds of associated type `B::Y`: `B::Y` -> `A::X` -> `B::Y`"
+        }
+    }
+
+    // Equalities between projections with different parameters are not a cycle.
+    lowering_success! {
+        program {
+            struct Vec<T> { }
+            trait A<T> { type X; }
+            trait B<T> where Self: A<T, X = <Self as A<Vec<T>>>::X> { }
+        }
+    }
+}
@@ -10,7 +10,7 @@ fn two_impls_for_same_type() {
             impl Foo for Bar { }
         }
         error_msg {
-            "overlapping impls of trait `Foo`"
+            "overlapping impls of trait `Foo`: `ImplId(#2)` and `ImplId(#3)` both apply to `Bar as Foo`"
         }
     }
 
@@ -76,7 +76,7 @@ fn two_blanket_impls() {
             impl Baz for Quux { }
         }
         error_msg {
-            "overlapping impls of trait `Foo`"
+            "overlapping impls of trait `Foo`: `ImplId(#3)` and `ImplId(#4)` both apply to `Quux as Foo`"
         }
     }
 }
@@ -92,7 +92,7 @@ fn two_blanket_impls_open_ended() {
             impl<T> Foo for T where T: Baz { }
         }
         error_msg {
-            "overlapping impls of trait `Foo`"
+            "overlapping impls of trait `Foo`: `ImplId(#3)` and `ImplId(#4)` may both apply to `^0.0 as Foo` in a downstream or future crate"
         }
     }
 }
@@ -133,7 +133,7 @@ fn multiple_parameters() {
             impl<T> Foo<Baz> for T { }
             impl<T> Foo<T> for Baz { }
         } error_msg {
-            "overlapping impls of trait `Foo`"
+            "overlapping impls of trait `Foo`: `ImplId(#2)` and `ImplId(#3)` both apply to `Baz as Foo<Baz>`"
         }
     }
 }
@@ -224,6 +224,21 @@ fn overlapping_negative_positive_impls() {
     }
 }
 
+#[test]
+fn negative_impl_does_not_specialize_positive_impl() {
+    lowering_error! {
+        program {
+            trait Foo { }
+            struct Bar { }
+
+            impl<T> Foo for T { }
+            impl !Foo for Bar { }
+        } error_msg {
+            "overlapping impls of trait `Foo`"
+        }
+    }
+}
+
 #[test]
 fn overlapping_negative_impls() {
     lowering_success! {
@@ -272,7 +287,7 @@ fn downstream_impl_of_fundamental_43355() {
             // This makes the first impl now apply to A, which means that both of these impls now
             // overlap for A even though they didn't overlap in the original crate where A is defined.
         } error_msg {
-            "overlapping impls of trait `Trait1`"
+            "overlapping impls of trait `Trait1`: `ImplId(#4)` and `ImplId(#5)` may both apply to `A as Trait1<Box<^0.0>>` in a downstream or future crate"
         }
     }
 }
@@ -294,6 +309,21 @@ fn fundamental_traits() {
         }
     }
 
+    // They only overlap if the upstream crate adds `impl Sized for Foo`, so when asked for
+    // future-compatibility warnings, this is a warning instead.
+    lowering_warnings! {
+        program {
+            #[upstream] trait Sized { }
+            #[upstream] struct Foo { }
+            trait Bar { }
+            impl Bar for Foo { }
+            impl<T> Bar for T where T: Sized { }
+        } warning_msgs {
+            "impls of trait `Bar` may overlap in the future: `ImplId(#3)` and `ImplId(#4)` \
+             may both apply to `Foo as Bar` once upstream crates add impls"
+        }
+    }
+
     // If we make Sized fundamental, we're telling the Rust compiler that it can reason negatively
     // about it. That means that `not { Foo: Sized }` is provable. With that change, these two impls
     // are now valid.
@@ -308,6 +338,121 @@ fn fundamental_traits() {
     }
 }
 
+#[test]
+fn negative_impls_in_coherence() {
+    // A negative impl is a promise by the upstream crate that it will
+    // never implement the trait for that type, so the same negative
+    // reasoning works without making the trait fundamental.
+    lowering_success! {
+        program {
+            #[upstream] trait Sized { }
+            #[upstream] struct Foo { }
+            #[upstream] impl !Sized for Foo { }
+            trait Bar { }
+            impl Bar for Foo { }
+            impl<T> Bar for T where T: Sized { }
+        }
+    }
+
+    // The negative impl only covers the types it names.
+    lowering_error! {
+        program {
+            #[upstream] trait Sized { }
+            #[upstream] struct Foo { }
+            #[upstream] struct Baz { }
+            #[upstream] impl !Sized for Baz { }
+            trait Bar { }
+            impl Bar for Foo { }
+            impl<T> Bar for T where T: Sized { }
+        } error_msg {
+            "overlapping impls of trait `Bar`"
+        }
+    }
+}
+
+#[test]
+fn future_compat_overlap_warnings() {
+    // No warnings for impls that do not overlap.
+    lowering_warnings! {
+        program {
+            #[upstream] #[fundamental] trait Sized { }
+            #[upstream] struct Foo { }
+            trait Bar { }
+            impl Bar for Foo { }
+            impl<T> Bar for T where T: Sized { }
+        } warning_msgs {
+        }
+    }
+
+    // A downstream crate could implement both traits for one of its
+    // types today, so this is still an error.
+    lowering_error! {
+        program {
+            #[upstream] trait Display { }
+            #[upstream] trait Debug { }
+            trait Foo { }
+            impl<T> Foo for T where T: Display { }
+            impl<T> Foo for T where T: Debug { }
+        } error_msg {
+            "overlapping impls of trait `Foo`"
+        }
+    }
+
+    // The warnings do not affect the specialization priorities.
+    lowering_warnings! {
+        program {
+            #[upstream] trait Sized { }
+            #[upstream] struct Foo { }
+            trait Bar { }
+            impl Bar for Foo { }
+            impl<T> Bar for T where T: Sized { }
+            impl<T> Bar for Vec<T> where T: Sized { }
+            struct Vec<T> { }
+        } warning_msgs {
+            "impls of trait `Bar` may overlap in the future: `ImplId(#3)` and `ImplId(#4)`"
+        }
+    }
+}
+
+#[test]
+fn reservation_impls() {
+    // A reservation impl counts when checking overlap...
+    lowering_error! {
+        program {
+            trait From<T> { }
+            struct Never { }
+            struct Foo { }
+            #[reservation] impl<T> From<Never> for T { }
+            impl From<Never> for Foo { }
+        } error_msg {
+            "overlapping impls of trait `From`: `ImplId(#3)` and `ImplId(#4)` both apply to `Foo as From<Never>`"
+        }
+    }
+
+    // ...also against a negative impl, which promises that the
+    // reserved impl will never be added.
+    lowering_error! {
+        program {
+            trait Send { }
+            struct Foo { }
+            #[reservation] impl Send for Foo { }
+            impl !Send for Foo { }
+        } error_msg {
+            "overlapping impls of trait `Send`: `ImplId(#2)` and `ImplId(#3)` both apply to `Foo as Send`"
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Send { }
+            struct Foo { }
+            #[reservation] impl !Send for Foo { }
+        } error_msg {
+            "reservation impl for trait `Send` cannot be negative"
+        }
+    }
+}
+
 #[test]
 fn orphan_check() {
     // These tests are largely adapted from the compile-fail coherence-*.rs tests from rustc
@@ -319,7 +464,7 @@ fn orphan_check() {
 
             impl Foo for Bar { }
         } error_msg {
-            "impl for trait `Foo` violates the orphan rules"
+            "impl for trait `Foo` violates the orphan rules: the first non-local input type of `ImplId(#2)` is `Bar`, and none of its input types is local"
         }
     }
 
@@ -340,7 +485,7 @@ fn orphan_check() {
 
             impl<T> Foo<Bar> for T { }
         } error_msg {
-            "impl for trait `Foo` violates the orphan rules"
+            "impl for trait `Foo` violates the orphan rules: the first non-local input type of `ImplId(#2)` is the self type, and the 1st impl parameter appears uncovered in the self type before any local type"
         }
     }
 
@@ -398,7 +543,7 @@ fn orphan_check() {
             // This impl should fail because it contains only upstream type
             impl TheTrait<TypeB> for TypeA { }
         } error_msg {
-            "impl for trait `TheTrait` violates the orphan rules"
+            "impl for trait `TheTrait` violates the orphan rules: the first non-local input type of `ImplId(#7)` is `TypeA`, and none of its input types is local"
         }
     }
 
@@ -437,7 +582,7 @@ fn orphan_check() {
 
             impl<T, U> Remote1<Pair<T, Local<U>>> for TypeA { }
         } error_msg {
-            "impl for trait `Remote1` violates the orphan rules"
+            "impl for trait `Remote1` violates the orphan rules: the first non-local input type of `ImplId(#4)` is `TypeA`, and the 1st impl parameter appears uncovered in the 1st type argument of the trait before any local type"
         }
     }
 
@@ -480,3 +625,110 @@ fn orphan_check() {
         }
     }
 }
+
+#[test]
+fn overlap_check_with_many_impls() {
+    // Only impls whose self types could be the same are compared; the
+    // blanket impl is compared with all of them.
+    lowering_success! {
+        program {
+            trait Foo { }
+            trait Bar { }
+            struct A { } struct B { } struct C { } struct D { }
+            struct Vec<T> { } struct Box<T> { }
+            impl Foo for A { } impl Foo for B { } impl Foo for C { }
+            impl Foo for Vec<A> { } impl Foo for Vec<B> { }
+            impl Foo for Box<A> { } impl<T> Foo for Box<Vec<T>> { }
+            impl<T> Foo for T where T: Bar { }
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Foo { }
+            struct A { } struct B { } struct C { }
+            struct Vec<T> { } struct Box<T> { }
+            impl Foo for A { } impl Foo for B { } impl Foo for C { }
+            impl Foo for Vec<A> { } impl Foo for Box<A> { }
+            impl Foo for Box<Vec<C>> { } impl Foo for Box<Vec<C>> { }
+        } error_msg {
+            "overlapping impls of trait `Foo`: `ImplId(#11)` and `ImplId(#12)` both apply to `Box<Vec<C>> as Foo`"
+        }
+    }
+}
+
+#[test]
+fn lattice_specialization() {
+    // The first two impls overlap, but the third specializes both and
+    // applies to every type that both apply to.
+    lowering_success! {
+        program {
+            trait Foo { }
+            impl<T> Foo for (T, u8) { }
+            impl<U> Foo for (u8, U) { }
+            impl Foo for (u8, u8) { }
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Foo { }
+            trait Bar { }
+            impl<T> Foo for (T, u8) { }
+            impl<U> Foo for (u8, U) { }
+            impl Foo for (u8, u8) where u8: Bar { }
+        } error_msg {
+            "overlapping impls of trait `Foo`: `ImplId(#2)` and `ImplId(#3)` both apply"
+        }
+    }
+
+    // An impl may specialize several impls that do not specialize
+    // one another.
+    lowering_success! {
+        program {
+            trait Foo { }
+            trait A { }
+            trait B { }
+            impl<T> Foo for T { }
+            impl<T> Foo for T where T: A { }
+            impl<T> Foo for T where T: B { }
+            impl<T> Foo for T where T: A, T: B { }
+        }
+    }
+}
+
+#[test]
+fn always_applicable_specialization() {
+    lowering_error! {
+        program {
+            trait Foo { }
+            impl<A, B> Foo for (A, B) { }
+            impl<T> Foo for (T, T) { }
+        } error_msg {
+            "impl `ImplId(#2)` of trait `Foo` cannot specialize `ImplId(#1)` because it is not always applicable: parameter `^0.0` is used for several parameters of the specialized impl"
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Foo { }
+            impl<T> Foo for T { }
+            impl<'a> Foo for &'a &'a u8 { }
+        } error_msg {
+            "impl `ImplId(#2)` of trait `Foo` cannot specialize `ImplId(#1)` because it is not always applicable: lifetime `'^0.0` is required to be equal to other lifetimes"
+        }
+    }
+
+    // Parameters that are repeated in the specialized impl may be
+    // repeated in the specializing impl too.
+    lowering_success! {
+        program {
+            trait Foo { }
+            struct Vec<T> { }
+            impl<T> Foo for (T, T) { }
+            impl<T> Foo for (Vec<T>, Vec<T>) { }
+            impl<'a, T> Foo for &'a T { }
+            impl<'a> Foo for &'a u8 { }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Tests for computing what must be live when a value is dropped.
+
+use super::*;
+use chalk_ir::{DomainGoal, GoalData, Mutability, TyData, TypeName, WellFormed};
+use chalk_solve::dropck::dropck_outlives;
+
+const PROGRAM: &str = "
+    #[lang(drop)]
+    trait Drop { }
+
+    struct Ref<'a, T> { value: &'a T }
+    struct Pair<T, U> { first: T, second: U }
+    struct Box<T> { value: T }
+    struct List<T> { value: T, next: Box<List<T>> }
+
+    // Accesses the data of `T` when dropped.
+    struct Vec<T> { }
+    impl<T> Drop for Vec<T> { }
+
+    // Only drops the values of type `T`.
+    struct RawVec<T> { }
+    impl<#[may_dangle] T> Drop for RawVec<T> { }
+
+    // Accesses the data of `'a` when dropped.
+    struct Guard<'a> { }
+    impl<'a> Drop for Guard<'a> { }
+
+    struct Cell<'a, 'b> { }
+    impl<#[may_dangle] 'a, 'b> Drop for Cell<'a, 'b> { }
+";
+
+/// Computes the constraints for dropping a value of type `T` at the end of
+/// `'s`, where `goal_text` is a goal like `forall<'s> { WellFormed(&'s T) }`.
+fn dropck(program: &Arc<Program>, goal_text: &str) -> String {
+    let goal = peeled_goal(program, goal_text);
+    tls::set_current_program(program, || {
+        let ty = match goal.canonical.value.goal.data(&ChalkIr) {
+            GoalData::DomainGoal(DomainGoal::WellFormed(WellFormed::Ty(ty))) => ty.clone(),
+            goal => panic!("expected a `WellFormed` goal, found {:?}", goal),
+        };
+        let (lifetime, ty) = match ty.data(&ChalkIr) {
+            TyData::Apply(apply) if apply.name == TypeName::Ref(Mutability::Not) => (
+                *apply
+                    .substitution
+                    .iter(&ChalkIr)
+                    .find_map(|arg| arg.lifetime(&ChalkIr))
+                    .unwrap(),
+                apply.first_type_parameter(&ChalkIr).unwrap(),
+            ),
+            ty => panic!("expected a reference, found {:?}", ty),
+        };
+        let constraints = dropck_outlives(&**program, &ty).constraints(&ChalkIr, &lifetime);
+        format!("{:?}", constraints)
+    })
+}
+
+#[test]
+fn no_destructor() {
+    let program = lower(PROGRAM);
+
+    // Dropping references, or types without destructors that contain
+    // references, does not access the data they refer to.
+    assert_same(
+        &dropck(&program, "forall<'s, 'a> { WellFormed(&'s &'a u32) }"),
+        "[]",
+    );
+    assert_same(
+        &dropck(&program, "forall<'s, 'a, T> { WellFormed(&'s Ref<'a, T>) }"),
+        "[]",
+    );
+}
+
+#[test]
+fn destructor() {
+    let program = lower(PROGRAM);
+
+    assert_same(
+        &dropck(&program, "forall<'s, 'a> { WellFormed(&'s Vec<&'a u32>) }"),
+        "['!1_1: '!1_0]",
+    );
+    assert_same(
+        &dropck(&program, "forall<'s, 'a> { WellFormed(&'s Guard<'a>) }"),
+        "['!1_1: '!1_0]",
+    );
+
+    // The destructors of fields run as well.
+    assert_same(
+        &dropck(
+            &program,
+            "forall<'s, 'a, 'b> { WellFormed(&'s Pair<Guard<'a>, Ref<'b, u32>>) }",
+        ),
+        "['!1_1: '!1_0]",
+    );
+
+    // We do not know what dropping a type parameter does.
+    assert_same(
+        &dropck(&program, "forall<'s, T> { WellFormed(&'s Box<T>) }"),
+        "[!1_1: '!1_0]",
+    );
+}
+
+#[test]
+fn may_dangle() {
+    let program = lower(PROGRAM);
+
+    assert_same(
+        &dropck(
+            &program,
+            "forall<'s, 'a> { WellFormed(&'s RawVec<&'a u32>) }",
+        ),
+        "[]",
+    );
+    assert_same(
+        &dropck(
+            &program,
+            "forall<'s, 'a, 'b> { WellFormed(&'s Cell<'a, 'b>) }",
+        ),
+        "['!1_2: '!1_0]",
+    );
+
+    // Dangling parameters are still dropped.
+    assert_same(
+        &dropck(
+            &program,
+            "forall<'s, 'a> { WellFormed(&'s RawVec<Guard<'a>>) }",
+        ),
+        "['!1_1: '!1_0]",
+    );
+    assert_same(
+        &dropck(&program, "forall<'s, T> { WellFormed(&'s RawVec<T>) }"),
+        "[!1_1: '!1_0]",
+    );
+}
+
+#[test]
+fn recursive_adt() {
+    let program = lower(PROGRAM);
+
+    assert_same(
+        &dropck(
+            &program,
+            "forall<'s, 'a> { WellFormed(&'s List<Guard<'a>>) }",
+        ),
+        "['!1_1: '!1_0]",
+    );
+}
+
+#[test]
+fn may_dangle_outside_drop_impl() {
+    lowering_error! {
+        program {
+            trait Clone { }
+            struct Vec<T> { }
+            impl<#[may_dangle] T> Clone for Vec<T> { }
+        } error_msg {
+            "`#[may_dangle]` is only allowed on the parameters of `Drop` impls, \
+             not of impls of trait `Clone`"
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! Tests related to the implied bounds rules.
 
 use super::*;
+use chalk_integration::Identifier;
+use chalk_ir::{TraitRef, WhereClause};
 
 #[test]
 fn dyn_Clone_is_Clone() {
@@ -116,22 +118,69 @@ fn dyn_super_trait_simple() {
 
 #[test]
 fn dyn_super_trait_cycle() {
-    test! {
+    // Cyclic where clauses are rejected when lowering, so that we never
+    // have to elaborate the supertraits of such a `dyn` type.
+    lowering_error! {
         program {
             trait Foo<T> where Self: Bar<T> {}
             trait Bar<T> where Self: Foo<T> {}
 
             struct A {}
             struct B {}
+        } error_msg {
+            "cycle in the supertraits of trait `Foo`: `Foo` -> `Bar` -> `Foo`"
         }
+    }
+}
 
-        // We currently can't prove this because of the cyclic where clauses.
-        // But importantly, we don't crash or get into an infinite loop.
-        goal {
-            dyn Bar<A>: Bar<A>
-        } yields {
-            "No possible solution"
-        }
+#[test]
+fn dyn_super_trait_cycle_solver() {
+    // Other databases than `chalk_integration` do not reject cyclic
+    // where clauses, so the solver must still cope with them. Build the
+    // cycle of `dyn_super_trait_cycle` behind the back of lowering, by
+    // adding `Self: Foo<T>` to the where clauses of `Bar`.
+    let db = ChalkDatabase::with(
+        "trait Foo<T> where Self: Bar<T> {} trait Bar<T> {} struct A {}",
+        SolverChoice::default(),
+    );
+    let mut program = (*db.program_ir().unwrap()).clone();
+    let foo = program.trait_ids[&Identifier::from("Foo")];
+    let bar = program.trait_ids[&Identifier::from("Bar")];
+    let foo_bound = program.trait_data[&foo]
+        .binders
+        .skip_binders()
+        .where_clauses[0]
+        .clone()
+        .map(|where_clause| match where_clause {
+            WhereClause::Implemented(trait_ref) => WhereClause::Implemented(TraitRef {
+                trait_id: foo,
+                ..trait_ref
+            }),
+            _ => unreachable!(),
+        });
+    let mut bar_datum = (*program.trait_data[&bar]).clone();
+    bar_datum.binders = bar_datum.binders.map(|mut bound| {
+        bound.where_clauses.push(foo_bound);
+        bound
+    });
+    program.trait_data.insert(bar, Arc::new(bar_datum));
+    let program = Arc::new(program);
+
+    // The walk over the supertraits stops at the cycle.
+    let cycle = tls::set_current_program(&program, || {
+        chalk_solve::cycles::super_trait_cycle(&*program, bar)
+    });
+    assert_eq!(cycle, Some(vec![bar, foo, bar]));
+
+    // We can't prove this because of the cyclic where clauses. But
+    // importantly, we don't crash or get into an infinite loop.
+    for &solver_choice in &[SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let result = solve(
+            &mut solver_choice.into_solver(),
+            &program,
+            "dyn Bar<A>: Bar<A>",
+        );
+        assert_result(result, "No possible solution");
     }
 }
 
@@ -639,3 +639,33 @@ fn clauses_in_if_goals() {
         }
     }
 }
+
+#[test]
+fn reservation_impls_never_hold() {
+    test! {
+        program {
+            trait From<T> { }
+            struct Never { }
+            struct Foo { }
+            #[reservation] impl<T> From<Never> for T { }
+        }
+
+        goal {
+            Foo: From<Never>
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            NotImplemented(Foo: From<Never>)
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            not { compatible { Foo: From<Never> } }
+        } yields {
+            "Unique"
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Tests for keeping the solvers' cached state across program edits.
+
+use super::*;
+use chalk_integration::Identifier;
+use chalk_solve::dependencies::ItemId;
+use chalk_solve::{SolverStats, TableBudget};
+
+/// The same program before and after an edit of the `B` impl; the
+/// ids of all items stay the same.
+const BEFORE: &str = "
+    trait A { }
+    trait B { }
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl<T> A for Vec<T> where T: B { }
+    impl B for Foo { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+const AFTER: &str = "
+    trait A { }
+    trait B { }
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl<T> A for Vec<T> where T: B { }
+    impl B for Bar { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+#[test]
+fn changed_items() {
+    let before = lower(BEFORE);
+    let after = lower(AFTER);
+    assert!(before.changed_items(&before).is_empty());
+
+    // The changed impl, and the trait whose impls changed.
+    let changed = before.changed_items(&after);
+    assert_eq!(changed.len(), 3);
+    let trait_b = before.trait_ids[&Identifier::from("B")];
+    assert!(changed.contains(&ItemId::Trait(trait_b)));
+}
+
+/// Solves some goals before the edit, and checks that after the edit,
+/// only the results depending on the `B` impl are recomputed.
+fn check_edit(solver_choice: SolverChoice, reused: impl Fn(SolverStats) -> bool) {
+    let before = lower(BEFORE);
+    let after = lower(AFTER);
+    let mut solver = solver_choice.into_solver();
+    assert!(solve(&mut solver, &before, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert!(solve(&mut solver, &before, "Vec<Foo>: Clone")
+        .unwrap()
+        .is_unique());
+
+    solver.invalidate(before.changed_items(&after));
+    assert!(solver.stats().invalidations > 0);
+
+    solver.reset_stats();
+    assert!(solve(&mut solver, &after, "Vec<Foo>: Clone")
+        .unwrap()
+        .is_unique());
+    assert!(reused(solver.stats()));
+
+    // `Vec<Foo>: A` only depends on the `B` impl through its subgoal
+    // `Foo: B`, but it must be invalidated as well.
+    assert!(solve(&mut solver, &after, "Vec<Foo>: A").is_none());
+    assert!(solve(&mut solver, &after, "Vec<Bar>: A")
+        .unwrap()
+        .is_unique());
+}
+
+#[test]
+fn slg_edit() {
+    check_edit(SolverChoice::slg_default(), |stats| {
+        stats.tables_created == 0
+    });
+}
+
+#[test]
+fn recursive_edit() {
+    check_edit(SolverChoice::recursive(), |stats| {
+        stats.cache_hits == 1 && stats.cache_misses == 0
+    });
+}
+
+#[test]
+fn edit_after_eviction() {
+    let before = lower(BEFORE);
+    let after = lower(AFTER);
+    let mut solver = SolverChoice::slg(10, None)
+        .with_table_budget(TableBudget::Tables(1))
+        .into_solver();
+    assert!(solve(&mut solver, &before, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert!(solver.stats().tables_evicted > 0);
+
+    solver.invalidate(before.changed_items(&after));
+    assert!(solve(&mut solver, &after, "Vec<Foo>: A").is_none());
+}
+
+#[test]
+fn edit_invalidates_forks() {
+    for &solver_choice in &[SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let before = lower(BEFORE);
+        let after = lower(AFTER);
+        let mut solver = solver_choice.into_solver();
+        let mut fork = solver.fork();
+        assert!(solve(&mut fork, &before, "Vec<Foo>: A")
+            .unwrap()
+            .is_unique());
+
+        solver.invalidate(before.changed_items(&after));
+        let mut fork = solver.fork();
+        assert!(solve(&mut fork, &after, "Vec<Foo>: A").is_none());
+    }
+}
+
+/// The solver of a `ChalkDatabase` is kept across edits of the program
+/// text, and brought up to date with the new program.
+#[test]
+fn database_edit() {
+    for &solver_choice in &[SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let mut db = ChalkDatabase::with(BEFORE, solver_choice);
+        let solve = |db: &ChalkDatabase| {
+            db.with_program(|_| {
+                let goal = db.parse_and_lower_goal("Vec<Foo>: A").unwrap();
+                db.solve(&goal.into_peeled_goal(db.interner()))
+            })
+        };
+        let solver = db.solver();
+        assert!(solve(&db).unwrap().is_unique());
+
+        db.set_program_text(Arc::new(AFTER.to_string()));
+        assert!(solve(&db).is_none());
+        assert!(db.solver() == solver);
+    }
+}
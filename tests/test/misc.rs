@@ -555,3 +555,118 @@ fn builtin_impl_enumeration() {
         }
     }
 }
+
+/// Answers that only differ in their region constraints are combined
+/// into a single solution requiring any of them (rust-lang/rust#21974).
+#[test]
+fn region_constraint_disjunction() {
+    test! {
+        program {
+            #[marker] trait Foo { }
+            struct S<'a, 'b, 'c> { }
+
+            impl<'x, 'y> Foo for S<'x, 'x, 'y> { }
+            impl<'x, 'y> Foo for S<'x, 'y, 'x> { }
+        }
+
+        goal {
+            forall<'a, 'b, 'c> { S<'a, 'b, 'c>: Foo }
+        } yields {
+            "Unique; substitution [], lifetime constraints [\
+            InEnvironment { environment: Env([]), goal: Or([\
+                InEnvironment { environment: Env([]), goal: '!1_1: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_1 }\
+            ] || [\
+                InEnvironment { environment: Env([]), goal: '!1_2: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_2 }\
+            ]) }\
+            ]"
+        }
+    }
+}
+
+/// The recursive solver combines the solutions from each impl with
+/// `Solution::combine`; when they agree on the substitution, the
+/// combined solution is still unique.
+#[test]
+fn region_constraint_disjunction_recursive() {
+    test! {
+        program {
+            #[marker] trait Foo { }
+            struct S<'a, 'b, 'c, T> { }
+
+            impl<'x, 'y> Foo for S<'x, 'x, 'y, u32> { }
+            impl<'x, 'y> Foo for S<'x, 'y, 'x, u32> { }
+        }
+
+        goal {
+            forall<'a, 'b, 'c> { exists<T> { S<'a, 'b, 'c, T>: Foo } }
+        } yields[SolverChoice::recursive()] {
+            "Unique; substitution [?0 := Uint(U32)], lifetime constraints [\
+            InEnvironment { environment: Env([]), goal: Or([\
+                InEnvironment { environment: Env([]), goal: '!1_1: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_1 }\
+            ] || [\
+                InEnvironment { environment: Env([]), goal: '!1_2: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_2 }\
+            ]) }\
+            ]"
+        }
+    }
+}
+
+/// With more than two alternatives, an alternative that requires a
+/// superset of the constraints of another one is dropped.
+#[test]
+fn region_constraint_disjunction_minimal() {
+    test! {
+        program {
+            #[marker] trait Foo { }
+            struct S<'a, 'b, 'c> { }
+
+            impl<'x, 'y> Foo for S<'x, 'x, 'y> { }
+            impl<'x, 'y> Foo for S<'x, 'y, 'x> { }
+            impl<'x, 'y> Foo for S<'y, 'x, 'x> { }
+            impl<'x> Foo for S<'x, 'x, 'x> { }
+        }
+
+        goal {
+            forall<'a, 'b, 'c> { S<'a, 'b, 'c>: Foo }
+        } yields {
+            "Unique; substitution [], lifetime constraints [\
+            InEnvironment { environment: Env([]), goal: Or([\
+                InEnvironment { environment: Env([]), goal: '!1_1: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_1 }\
+            ] || [\
+                InEnvironment { environment: Env([]), goal: '!1_2: '!1_0 }, \
+                InEnvironment { environment: Env([]), goal: '!1_0: '!1_2 }\
+            ] || [\
+                InEnvironment { environment: Env([]), goal: '!1_2: '!1_1 }, \
+                InEnvironment { environment: Env([]), goal: '!1_1: '!1_2 }\
+            ]) }\
+            ]"
+        }
+    }
+}
+
+/// When answers disagree, the guidance is the most specific
+/// substitution that covers all of them.
+#[test]
+fn guidance_generalizes_answers() {
+    test! {
+        program {
+            trait Foo { }
+            struct Vec<T> { }
+            struct Pair<T, U> { }
+
+            impl Foo for Pair<Vec<u32>, u32> { }
+            impl Foo for Pair<Vec<i32>, i32> { }
+        }
+
+        goal {
+            exists<T, U> { Pair<T, U>: Foo }
+        } yields {
+            "Ambiguous; definite substitution for<?U0> { [?0 := Vec<^0.0>, ?1 := ^0.0] }"
+        }
+    }
+}
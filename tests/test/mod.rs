@@ -3,13 +3,19 @@
 use chalk_integration::db::ChalkDatabase;
 use chalk_integration::interner::ChalkIr;
 use chalk_integration::lowering::LowerGoal;
+use chalk_integration::program::Program;
 use chalk_integration::query::LoweringDatabase;
+use chalk_integration::tls;
+use chalk_ir::{Goal, ImplId, InEnvironment, UCanonical};
 use chalk_solve::ext::*;
 use chalk_solve::RustIrDatabase;
-use chalk_solve::{Solution, SolverChoice};
+use chalk_solve::{Solution, Solver, SolverChoice};
+use std::sync::Arc;
 
 use crate::test_util::assert_same;
 
+type UCanonicalGoal<I> = UCanonical<InEnvironment<Goal<I>>>;
+
 #[cfg(feature = "bench")]
 mod bench;
 mod coherence;
@@ -296,25 +302,90 @@ fn solve_goal(program_text: &str, goals: Vec<(&str, SolverChoice, TestGoal)>) {
     }
 }
 
+/// Lowers `program_text` and `goal_text`, then invokes `f` with the
+/// program and the peeled goal. Useful for tests that need to drive a
+/// `Solver` directly rather than through `test!`.
+fn with_goal(
+    program_text: &str,
+    goal_text: &str,
+    f: impl FnOnce(&dyn RustIrDatabase<ChalkIr>, &UCanonicalGoal<ChalkIr>),
+) {
+    let db = ChalkDatabase::with(program_text, SolverChoice::default());
+    let program = db.checked_program().unwrap();
+    chalk_integration::tls::set_current_program(&program, || {
+        let goal = chalk_parse::parse_goal(goal_text)
+            .unwrap()
+            .lower(&*program)
+            .unwrap();
+        let peeled_goal = goal.into_peeled_goal(db.interner());
+        f(&*program, &peeled_goal);
+    });
+}
+
+/// Lowers and checks `program_text`, for tests that drive a `Solver`
+/// (or another query) over the same program several times.
+fn lower(program_text: &str) -> Arc<Program> {
+    ChalkDatabase::with(program_text, SolverChoice::default())
+        .checked_program()
+        .unwrap()
+}
+
+/// Lowers `goal_text` in `program` and peels it.
+fn peeled_goal(program: &Arc<Program>, goal_text: &str) -> UCanonicalGoal<ChalkIr> {
+    tls::set_current_program(program, || {
+        chalk_parse::parse_goal(goal_text)
+            .unwrap()
+            .lower(&**program)
+            .unwrap()
+            .into_peeled_goal(&ChalkIr)
+    })
+}
+
+/// Solves `goal_text` in `program` with `solver`.
+fn solve(
+    solver: &mut Solver<ChalkIr>,
+    program: &Arc<Program>,
+    goal_text: &str,
+) -> Option<Solution<ChalkIr>> {
+    let goal = peeled_goal(program, goal_text);
+    tls::set_current_program(program, || solver.solve(&**program, &goal))
+}
+
+/// The `n`th impl of `program`, in the order of the program text.
+fn nth_impl(program: &Program, n: usize) -> ImplId<ChalkIr> {
+    *program.impl_data.keys().nth(n).unwrap()
+}
+
 mod auto_traits;
 mod coherence_goals;
 mod coinduction;
 mod constants;
 mod cycle;
+mod dropck;
 mod existential_types;
 mod functions;
 mod implied_bounds;
 mod impls;
+mod incremental;
 mod misc;
 mod negation;
 mod never;
 mod object_safe;
 mod opaque_types;
+mod persist;
 mod projection;
+mod provenance;
 mod refs;
 mod scalars;
+mod scheduling;
+mod select;
 mod slices;
+mod stats;
 mod string;
+mod table_budget;
+mod threads;
+mod trace;
+mod truncation;
 mod tuples;
 mod unify;
 mod wf_goals;
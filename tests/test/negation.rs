@@ -158,9 +158,9 @@ fn negation_free_vars() {
     }
 }
 
-/// Here, P and Q depend on one another through a negative loop.
+/// Here, P and Q depend on one another through a negative loop, so
+/// both are undefined under the well-founded semantics.
 #[test]
-#[should_panic(expected = "negative cycle")]
 fn negative_loop() {
     test! {
         program {
@@ -175,8 +175,15 @@ fn negative_loop() {
         goal {
             Alice: P
         } yields_all[SolverChoice::slg(10, None)] {
-            // Negative cycle -> panic
-            ""
+            "Undefined(substitution [], lifetime constraints [])"
+        }
+
+        goal {
+            Alice: P
+        } yields[SolverChoice::slg(10, None)] {
+            "Undefined; substitution [], lifetime constraints [], delayed subgoals [\
+            InEnvironment { environment: Env([]), goal: not { Implemented(Alice: Q) } }\
+            ]"
         }
     }
 }
@@ -210,7 +217,6 @@ fn example_2_2_EWFS() {
 }
 
 #[test]
-#[should_panic(expected = "negative cycle")]
 #[allow(non_snake_case)]
 fn example_2_3_EWFS() {
     test! {
@@ -233,14 +239,14 @@ fn example_2_3_EWFS() {
         goal {
             a: W
         } yields_all[SolverChoice::slg(3, None)] {
-            // Negative cycle -> panic
-            ""
+            "substitution [], lifetime constraints []"
         }
     }
 }
 
+/// Here, P and Q only depend on each other positively (besides
+/// `not { a: S }`), so they are false, and S is true.
 #[test]
-#[should_panic(expected = "negative cycle")]
 #[allow(non_snake_case)]
 fn example_3_3_EWFS() {
     test! {
@@ -259,8 +265,7 @@ fn example_3_3_EWFS() {
         goal {
             a: S
         } yields_all[SolverChoice::slg(3, None)] {
-            // Negative cycle -> panic
-            ""
+            "substitution [], lifetime constraints []"
         }
     }
 }
@@ -268,7 +273,6 @@ fn example_3_3_EWFS() {
 /// Here, P is neither true nor false. If it were true, then it would
 /// be false, and so forth.
 #[test]
-#[should_panic(expected = "negative cycle")]
 fn contradiction() {
     test! {
         program {
@@ -281,15 +285,22 @@ fn contradiction() {
         goal {
             Alice: P
         } yields_all[SolverChoice::slg(3, None)] {
-            // Negative cycle -> panic
-            ""
+            "Undefined(substitution [], lifetime constraints [])"
+        }
+
+        goal {
+            Alice: P
+        } yields[SolverChoice::slg(3, None)] {
+            "Undefined; substitution [], lifetime constraints [], delayed subgoals [\
+            InEnvironment { environment: Env([]), goal: not { Implemented(Alice: P) } }\
+            ]"
         }
     }
 }
 
-/// Here, P depends on Q negatively, but Q depends only on itself.
+/// Here, P depends on Q negatively, but Q depends only on itself
+/// (negatively), so both are undefined.
 #[test]
-#[should_panic(expected = "negative cycle")]
 fn negative_answer_ambiguous() {
     test! {
         program {
@@ -304,8 +315,7 @@ fn negative_answer_ambiguous() {
         goal {
             Alice: P
         } yields_all[SolverChoice::slg(3, None)] {
-            // Negative cycle -> panic
-            ""
+            "Undefined(substitution [], lifetime constraints [])"
         }
     }
 }
@@ -354,3 +364,111 @@ fn negative_reorder() {
         }
     }
 }
+
+/// Undefined answers are returned after all others, and make the
+/// aggregated solution ambiguous.
+#[test]
+fn undefined_answers_come_last() {
+    test! {
+        program {
+            trait P { }
+            struct Alice { }
+            struct Bob { }
+
+            forall<> { Alice: P if not { Alice: P } }
+            forall<> { Bob: P }
+        }
+
+        goal {
+            exists<T> { T: P }
+        } yields_all[SolverChoice::slg(3, None)] {
+            "substitution [?0 := Bob], lifetime constraints []",
+            "Undefined(substitution [?0 := Alice], lifetime constraints [])"
+        }
+
+        goal {
+            exists<T> { T: P }
+        } yields[SolverChoice::slg(3, None)] {
+            "Ambiguous; no inference guidance"
+        }
+
+        goal {
+            not { Alice: P }
+        } yields[SolverChoice::slg(3, None)] {
+            "Undefined; substitution [], lifetime constraints [], delayed subgoals [\
+            InEnvironment { environment: Env([]), goal: not { Implemented(Alice: P) } }\
+            ]"
+        }
+    }
+}
+
+#[test]
+fn not_implemented_from_negative_impls() {
+    test! {
+        program {
+            trait Foo {}
+            struct Bar {}
+            struct Baz {}
+            struct Vec<T> {}
+            impl !Foo for Bar {}
+            impl<T> !Foo for Vec<T> where T: Foo {}
+            impl Foo for Baz {}
+        }
+
+        goal {
+            NotImplemented(Bar: Foo)
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            NotImplemented(Baz: Foo)
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            NotImplemented(Vec<Baz>: Foo)
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            NotImplemented(Vec<Bar>: Foo)
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<T> { NotImplemented(T: Foo) }
+        } yields {
+            "Ambiguous"
+        }
+    }
+}
+
+#[test]
+fn negative_impls_in_compatible_modality() {
+    test! {
+        program {
+            #[upstream] trait Foo {}
+            #[upstream] struct Bar {}
+            #[upstream] struct Baz {}
+            #[upstream] impl !Foo for Bar {}
+        }
+
+        // An upstream crate may add an impl of `Foo` for `Baz`, but not
+        // for `Bar`, since that would break its negative impl.
+        goal {
+            not { compatible { Bar: Foo } }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            not { compatible { Baz: Foo } }
+        } yields {
+            "Ambiguous"
+        }
+    }
+}
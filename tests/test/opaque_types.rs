@@ -7,6 +7,7 @@ fn opaque_bounds() {
             struct Ty { }
 
             trait Clone { }
+            impl Clone for Ty { }
             opaque type T: Clone = Ty;
         }
 
@@ -27,6 +28,7 @@ fn opaque_reveal() {
             impl Trait for Ty { }
 
             trait Clone { }
+            impl Clone for Ty { }
             opaque type T: Clone = Ty;
         }
 
@@ -78,6 +80,9 @@ fn opaque_generics() {
 
             struct Vec<T> { }
             struct Bar { }
+            impl<T> Iterator for Vec<T> {
+                type Item = T;
+            }
 
             opaque type Foo<X>: Iterator<Item = X> = Vec<X>;
         }
@@ -0,0 +1,218 @@
+//! Tests for saving the work of a solver and loading it into another.
+
+use super::*;
+use chalk_solve::LoadError;
+
+/// The same program before and after an edit of the `B` impl; the
+/// ids of all items stay the same.
+const BEFORE: &str = "
+    trait A { }
+    trait B { }
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl<T> A for Vec<T> where T: B { }
+    impl B for Foo { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+const AFTER: &str = "
+    trait A { }
+    trait B { }
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl<T> A for Vec<T> where T: B { }
+    impl B for Bar { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+/// Solves some goals with a fresh solver for `BEFORE`, and returns
+/// its saved work.
+fn saved_work(solver_choice: SolverChoice) -> Vec<u8> {
+    let before = lower(BEFORE);
+    let mut solver = solver_choice.into_solver();
+    assert!(solve(&mut solver, &before, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert!(solve(&mut solver, &before, "Vec<Vec<Foo>>: Clone")
+        .unwrap()
+        .is_unique());
+    let mut bytes = vec![];
+    solver.save(&*before, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn save_and_load() {
+    let bytes = saved_work(SolverChoice::slg_default());
+    let program = lower(BEFORE);
+    let mut solver = SolverChoice::slg_default().into_solver();
+    let loaded = solver.load(&*program, &bytes[..]).unwrap();
+    assert!(loaded > 0);
+
+    assert!(solve(&mut solver, &program, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert!(solve(&mut solver, &program, "Vec<Vec<Foo>>: Clone")
+        .unwrap()
+        .is_unique());
+    assert_eq!(solver.stats().strands_created, 0);
+
+    // Tables the solver has already are not loaded again.
+    assert_eq!(solver.load(&*program, &bytes[..]).unwrap(), 0);
+}
+
+#[test]
+fn save_to_file() {
+    let path = std::env::temp_dir().join(format!("chalk-persist-{}", std::process::id()));
+    std::fs::write(&path, saved_work(SolverChoice::slg_default())).unwrap();
+
+    let program = lower(BEFORE);
+    let mut solver = SolverChoice::slg_default().into_solver();
+    let file = std::fs::File::open(&path).unwrap();
+    assert!(solver.load(&*program, file).unwrap() > 0);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(solve(&mut solver, &program, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert_eq!(solver.stats().strands_created, 0);
+}
+
+/// Only the tables that do not depend on the edited `B` impl are
+/// loaded for the edited program.
+#[test]
+fn load_after_edit() {
+    let bytes = saved_work(SolverChoice::slg_default());
+    let after = lower(AFTER);
+    let mut all = SolverChoice::slg_default().into_solver();
+    let all_tables = all.load(&*lower(BEFORE), &bytes[..]).unwrap();
+    let mut solver = SolverChoice::slg_default().into_solver();
+    let loaded = solver.load(&*after, &bytes[..]).unwrap();
+    assert!(loaded > 0);
+    assert!(loaded < all_tables);
+
+    assert!(solve(&mut solver, &after, "Vec<Vec<Foo>>: Clone")
+        .unwrap()
+        .is_unique());
+    assert_eq!(solver.stats().strands_created, 0);
+
+    assert!(solve(&mut solver, &after, "Vec<Foo>: A").is_none());
+    assert!(solve(&mut solver, &after, "Vec<Bar>: A")
+        .unwrap()
+        .is_unique());
+}
+
+/// `BEFORE` without the last impl, so that the ids of the other items
+/// stay the same.
+const DELETED: &str = "
+    trait A { }
+    trait B { }
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl<T> A for Vec<T> where T: B { }
+    impl B for Foo { }
+    impl Clone for Foo { }
+";
+
+/// The work that depends on the deleted impl is skipped, as it refers
+/// to an item that no longer exists.
+#[test]
+fn load_after_delete() {
+    for solver_choice in [SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let bytes = saved_work(solver_choice);
+        let deleted = lower(DELETED);
+        let mut solver = solver_choice.into_solver();
+        assert!(solver.load(&*deleted, &bytes[..]).unwrap() > 0);
+
+        assert!(solve(&mut solver, &deleted, "Vec<Foo>: A")
+            .unwrap()
+            .is_unique());
+        assert!(solve(&mut solver, &deleted, "Vec<Vec<Foo>>: Clone").is_none());
+    }
+}
+
+#[test]
+fn load_invalid_data() {
+    let program = lower(BEFORE);
+    let mut solver = SolverChoice::slg_default().into_solver();
+    match solver.load(&*program, &b"not a saved solver"[..]) {
+        Err(LoadError::WrongFormat) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let bytes = saved_work(SolverChoice::slg_default());
+    match solver.load(&*program, &bytes[..bytes.len() - 1]) {
+        Err(LoadError::Decode(_)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn recursive_save_and_load() {
+    let bytes = saved_work(SolverChoice::recursive());
+    let program = lower(BEFORE);
+    let mut solver = SolverChoice::recursive().into_solver();
+    let loaded = solver.load(&*program, &bytes[..]).unwrap();
+    assert!(loaded > 0);
+
+    assert!(solve(&mut solver, &program, "Vec<Foo>: A")
+        .unwrap()
+        .is_unique());
+    assert!(solve(&mut solver, &program, "Vec<Vec<Foo>>: Clone")
+        .unwrap()
+        .is_unique());
+    assert_eq!(solver.stats().cache_misses, 0);
+
+    // Saving the loaded results yields the same bytes.
+    let mut saved = vec![];
+    solver.save(&*program, &mut saved).unwrap();
+    assert_eq!(saved, bytes);
+}
+
+/// Only the results that do not depend on the edited `B` impl (through
+/// the `B` trait) are loaded for the edited program.
+#[test]
+fn recursive_load_after_edit() {
+    let bytes = saved_work(SolverChoice::recursive());
+    let after = lower(AFTER);
+    let mut all = SolverChoice::recursive().into_solver();
+    let all_results = all.load(&*lower(BEFORE), &bytes[..]).unwrap();
+    let mut solver = SolverChoice::recursive().into_solver();
+    let loaded = solver.load(&*after, &bytes[..]).unwrap();
+    assert!(loaded > 0);
+    assert!(loaded < all_results);
+
+    assert!(solve(&mut solver, &after, "Vec<Vec<Foo>>: Clone")
+        .unwrap()
+        .is_unique());
+    assert_eq!(solver.stats().cache_misses, 0);
+
+    assert!(solve(&mut solver, &after, "Vec<Foo>: A").is_none());
+    assert!(solve(&mut solver, &after, "Vec<Bar>: A")
+        .unwrap()
+        .is_unique());
+}
+
+#[test]
+fn load_work_of_other_solver() {
+    let program = lower(BEFORE);
+    let mut solver = SolverChoice::recursive().into_solver();
+    match solver.load(&*program, &saved_work(SolverChoice::slg_default())[..]) {
+        Err(LoadError::WrongFormat) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let mut solver = SolverChoice::slg_default().into_solver();
+    match solver.load(&*program, &saved_work(SolverChoice::recursive())[..]) {
+        Err(LoadError::WrongFormat) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
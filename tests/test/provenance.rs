@@ -0,0 +1,120 @@
+//! Tests for the sources of the program clauses that solutions are
+//! derived with.
+
+use super::*;
+use chalk_ir::ClauseSource;
+
+const PROGRAM: &str = "
+    #[lang(copy)]
+    trait Copy { }
+    trait Clone { }
+    #[auto] trait Send { }
+    #[object_safe] trait Debug { }
+    struct Foo { }
+    struct Vec<T> { }
+    impl Copy for u8 { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+/// The source of the unique solution of `goal`, with each solver.
+fn sources(program: &Arc<Program>, goal_text: &str) -> Vec<Option<ClauseSource<ChalkIr>>> {
+    let goal = peeled_goal(program, goal_text);
+    vec![SolverChoice::slg_default(), SolverChoice::recursive()]
+        .into_iter()
+        .map(|solver_choice| {
+            let (solution, source) = tls::set_current_program(program, || {
+                solver_choice
+                    .into_solver()
+                    .solve_with_source(&**program, &goal)
+            })
+            .unwrap();
+            assert!(solution.is_unique(), "{:?}", solution);
+            source
+        })
+        .collect()
+}
+
+#[test]
+fn impl_source() {
+    let program = lower(PROGRAM);
+    let vec_impl = ClauseSource::Impl(nth_impl(&program, 2));
+    assert_eq!(
+        sources(&program, "Vec<Foo>: Clone"),
+        vec![Some(vec_impl), Some(vec_impl)]
+    );
+    let foo_impl = ClauseSource::Impl(nth_impl(&program, 1));
+    assert_eq!(
+        sources(&program, "Foo: Clone"),
+        vec![Some(foo_impl), Some(foo_impl)]
+    );
+    assert_eq!(
+        sources(&program, "exists<T> { T: Clone, Vec<T> = Vec<Foo> }"),
+        vec![None, None],
+        "a conjunction is not proven with a single clause"
+    );
+}
+
+#[test]
+fn environment_source() {
+    let program = lower(PROGRAM);
+    let environment = Some(ClauseSource::Environment);
+    assert_eq!(
+        sources(&program, "forall<T> { if (T: Clone) { T: Clone } }"),
+        vec![environment, environment]
+    );
+}
+
+#[test]
+fn builtin_source() {
+    let program = lower(PROGRAM);
+    let builtin = Some(ClauseSource::Builtin);
+    assert_eq!(sources(&program, "(u8, u8): Copy"), vec![builtin, builtin]);
+}
+
+#[test]
+fn auto_source() {
+    let program = lower(PROGRAM);
+    let auto = Some(ClauseSource::Auto);
+    assert_eq!(sources(&program, "Foo: Send"), vec![auto, auto]);
+}
+
+#[test]
+fn object_source() {
+    let program = lower(PROGRAM);
+    let object = Some(ClauseSource::Object);
+    assert_eq!(sources(&program, "dyn Debug: Debug"), vec![object, object]);
+}
+
+#[test]
+fn solve_multiple_sources() {
+    let program = lower(PROGRAM);
+    let goal = peeled_goal(&program, "exists<T> { T: Clone }");
+    let mut sources = vec![];
+    tls::set_current_program(&program, || {
+        SolverChoice::slg_default()
+            .into_solver()
+            .solve_multiple_with_source(&*program, &goal, |result, source, _| {
+                let answer = format!("{}", result.as_ref().map(|v| v.display(&ChalkIr)));
+                sources.push((answer, source));
+                sources.len() < 3
+            })
+    });
+    assert_eq!(
+        sources,
+        vec![
+            (
+                "substitution [?0 := Foo], lifetime constraints []".to_string(),
+                Some(ClauseSource::Impl(nth_impl(&program, 1)))
+            ),
+            (
+                "substitution [?0 := Vec<Foo>], lifetime constraints []".to_string(),
+                Some(ClauseSource::Impl(nth_impl(&program, 2)))
+            ),
+            (
+                "substitution [?0 := Vec<Vec<Foo>>], lifetime constraints []".to_string(),
+                Some(ClauseSource::Impl(nth_impl(&program, 2)))
+            ),
+        ]
+    );
+}
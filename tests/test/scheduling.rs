@@ -0,0 +1,135 @@
+//! Tests for the strand schedulers of the SLG solver. The schedule
+//! affects the order in which answers are found, but never the set of
+//! answers.
+
+use super::*;
+use chalk_solve::StrandSchedule;
+
+const SCHEDULES: &[StrandSchedule] = &[
+    StrandSchedule::DepthFirst,
+    StrandSchedule::BreadthFirst,
+    StrandSchedule::CostBased,
+];
+
+fn slg(strand_schedule: StrandSchedule) -> SolverChoice {
+    SolverChoice::slg(10, None).with_strand_schedule(strand_schedule)
+}
+
+/// Returns all the answers to `goal`, in the order in which they are
+/// found (up to the first time the stream flounders), and the
+/// aggregated solution.
+fn answers(
+    strand_schedule: StrandSchedule,
+    program: &dyn RustIrDatabase<ChalkIr>,
+    goal: &UCanonicalGoal<ChalkIr>,
+) -> (Vec<String>, String) {
+    let mut answers = vec![];
+    slg(strand_schedule)
+        .into_solver()
+        .solve_multiple(program, goal, |result, _| {
+            let answer = format!("{}", result.as_ref().map(|v| v.display(&ChalkIr)));
+            // Once the answers are truncated, the stream keeps reporting
+            // that it floundered.
+            let floundered = answer == "Floundered";
+            answers.push(answer);
+            !floundered
+        });
+    let solution = slg(strand_schedule)
+        .into_solver()
+        .solve(program, goal)
+        .map(|solution| solution.display(&ChalkIr).to_string())
+        .unwrap_or_else(|| "No possible solution".to_string());
+    (answers, solution)
+}
+
+/// Checks that every schedule finds the same answers (in any order)
+/// and the same solution for `goal`.
+fn check_same_answers(program_text: &str, goal_text: &str) {
+    with_goal(program_text, goal_text, |program, goal| {
+        let (mut expected, expected_solution) = answers(SCHEDULES[0], program, goal);
+        expected.sort();
+        for &strand_schedule in &SCHEDULES[1..] {
+            let (mut actual, solution) = answers(strand_schedule, program, goal);
+            actual.sort();
+            assert_eq!(expected, actual, "{:?}", strand_schedule);
+            assert_eq!(expected_solution, solution, "{:?}", strand_schedule);
+        }
+    });
+}
+
+const IMPLS_PROGRAM: &str = "
+    trait Foo { }
+    trait Bar { }
+    struct A { }
+    struct B { }
+    struct Pair<T, U> { }
+    impl<T, U> Foo for Pair<T, U> where T: Bar, U: Bar { }
+    impl Foo for A { }
+    impl Bar for A { }
+    impl Bar for B { }
+";
+
+#[test]
+fn same_answers_for_impls() {
+    check_same_answers(IMPLS_PROGRAM, "exists<T> { T: Foo }");
+}
+
+/// The schedules pursue the strands for the `Pair` impl in different
+/// orders, so they find its answers in different orders too.
+#[test]
+fn schedule_changes_answer_order() {
+    with_goal(IMPLS_PROGRAM, "exists<T> { T: Foo }", |program, goal| {
+        let (depth_first, _) = answers(StrandSchedule::DepthFirst, program, goal);
+        let (cost_based, _) = answers(StrandSchedule::CostBased, program, goal);
+        assert_ne!(depth_first, cost_based);
+    });
+}
+
+#[test]
+fn same_answers_for_cycles() {
+    check_same_answers(
+        "
+        trait Edge<T> { }
+        trait Reach<T> { }
+        struct A { }
+        struct B { }
+        struct C { }
+        impl Edge<B> for A { }
+        impl Edge<C> for B { }
+        impl Edge<A> for C { }
+        forall<X, Y> { X: Reach<Y> if X: Edge<Y> }
+        forall<X, Y, Z> { X: Reach<Z> if X: Edge<Y>, Y: Reach<Z> }
+        ",
+        "exists<T> { A: Reach<T> }",
+    );
+}
+
+#[test]
+fn same_answers_for_negation() {
+    check_same_answers(
+        "
+        trait Foo { }
+        trait Bar { }
+        struct A { }
+        struct B { }
+        struct C { }
+        impl Bar for A { }
+        forall<T> { T: Foo if not { T: Bar } }
+        ",
+        "forall<T> { if (T: Foo) { exists<U> { U: Foo } } }",
+    );
+}
+
+#[test]
+fn same_answers_for_ambiguity() {
+    check_same_answers(
+        "
+        trait Foo { }
+        struct A { }
+        struct Vec<T> { }
+        impl Foo for A { }
+        impl<T> Foo for Vec<T> where T: Foo { }
+        ",
+        "exists<T> { Vec<T>: Foo }",
+    );
+}
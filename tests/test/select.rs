@@ -0,0 +1,240 @@
+//! Tests for selecting the impl of a fully known trait reference.
+
+use super::*;
+use chalk_ir::{AliasTy, ClauseSource, DomainGoal, Environment, GoalData, Normalize, WhereClause};
+use chalk_solve::select::{ImplSelector, Selection, ValueSelection};
+
+const PROGRAM: &str = "
+    #[lang(copy)]
+    trait Copy { }
+    trait Clone { }
+    trait Show { }
+    trait Iterator { type Item; }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl Copy for u8 { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+    impl<T> Show for T { }
+    impl Show for Foo { }
+    impl<T> Iterator for Vec<T> { type Item = T; }
+";
+
+/// Peels `goal_text` and returns its environment and domain goal.
+fn peeled_domain_goal(
+    program: &Arc<Program>,
+    goal_text: &str,
+) -> (Environment<ChalkIr>, DomainGoal<ChalkIr>) {
+    let goal = peeled_goal(program, goal_text);
+    assert!(goal.canonical.binders.is_empty(&ChalkIr));
+    let InEnvironment { environment, goal } = goal.canonical.value;
+    match goal.data(&ChalkIr) {
+        GoalData::DomainGoal(domain_goal) => (environment, domain_goal.clone()),
+        goal => panic!("expected a domain goal, found {:?}", goal),
+    }
+}
+
+/// Selects the impl of the trait reference `goal_text` with each solver.
+fn select(program: &Arc<Program>, goal_text: &str) -> Vec<Option<Selection<ChalkIr>>> {
+    let (environment, trait_ref) = match peeled_domain_goal(program, goal_text) {
+        (environment, DomainGoal::Holds(WhereClause::Implemented(trait_ref))) => {
+            (environment, trait_ref)
+        }
+        (_, goal) => panic!("expected a trait reference, found {:?}", goal),
+    };
+    select_with(program, |selector| {
+        selector.select_impl(&trait_ref, &environment).unwrap()
+    })
+}
+
+/// Selects the associated type value of the projection in the
+/// `Normalize` goal `goal_text` with each solver.
+fn select_value(program: &Arc<Program>, goal_text: &str) -> Vec<Option<ValueSelection<ChalkIr>>> {
+    let (environment, projection) = match peeled_domain_goal(program, goal_text) {
+        (
+            environment,
+            DomainGoal::Normalize(Normalize {
+                alias: AliasTy::Projection(projection),
+                ..
+            }),
+        ) => (environment, projection),
+        (_, goal) => panic!("expected a projection, found {:?}", goal),
+    };
+    select_with(program, |selector| {
+        selector
+            .select_associated_ty_value(&projection, &environment)
+            .unwrap()
+    })
+}
+
+fn select_with<T>(
+    program: &Arc<Program>,
+    op: impl Fn(&mut ImplSelector<'_, ChalkIr>) -> T,
+) -> Vec<T> {
+    vec![SolverChoice::slg_default(), SolverChoice::recursive()]
+        .into_iter()
+        .map(|solver_choice| {
+            tls::set_current_program(program, || {
+                op(&mut ImplSelector::new(&**program, solver_choice))
+            })
+        })
+        .collect()
+}
+
+/// The impl selected by both solvers, with its substitution displayed.
+fn selected_impl(
+    program: &Arc<Program>,
+    selections: Vec<Option<Selection<ChalkIr>>>,
+) -> (ImplId<ChalkIr>, String) {
+    let mut selected = selections.into_iter().map(|selection| match selection {
+        Some(Selection::Impl {
+            impl_id,
+            substitution,
+        }) => {
+            assert!(substitution.binders.is_empty(&ChalkIr));
+            let substitution =
+                tls::set_current_program(program, || format!("{:?}", substitution.value));
+            (impl_id, substitution)
+        }
+        selection => panic!("expected an impl, found {:?}", selection),
+    });
+    let first = selected.next().unwrap();
+    assert!(selected.all(|other| other == first));
+    first
+}
+
+#[test]
+fn select_impl_with_substitution() {
+    let program = lower(PROGRAM);
+    assert_eq!(
+        selected_impl(&program, select(&program, "Vec<Foo>: Clone")),
+        (nth_impl(&program, 2), "[?0 := Foo]".to_string())
+    );
+    assert_eq!(
+        selected_impl(&program, select(&program, "Foo: Clone")),
+        (nth_impl(&program, 1), "[]".to_string())
+    );
+}
+
+#[test]
+fn select_most_special_impl() {
+    let program = lower(PROGRAM);
+    assert_eq!(
+        selected_impl(&program, select(&program, "Foo: Show")),
+        (nth_impl(&program, 4), "[]".to_string())
+    );
+    assert_eq!(
+        selected_impl(&program, select(&program, "Bar: Show")),
+        (nth_impl(&program, 3), "[?0 := Bar]".to_string())
+    );
+}
+
+#[test]
+fn select_impl_over_environment() {
+    let program = lower(PROGRAM);
+    assert_eq!(
+        selected_impl(&program, select(&program, "if (Foo: Clone) { Foo: Clone }")),
+        (nth_impl(&program, 1), "[]".to_string())
+    );
+}
+
+#[test]
+fn select_environment_over_impl() {
+    // Like in rustc, a where clause takes precedence over a blanket impl
+    // when the trait reference refers to the parameters of the function.
+    let program = lower(PROGRAM);
+    let environment = Some(Selection::Source(ClauseSource::Environment));
+    assert_eq!(
+        select(&program, "forall<T> { if (T: Show) { T: Show } }"),
+        vec![environment.clone(), environment]
+    );
+
+    // Without it, `T` could still turn out to be `Foo`, which has a more
+    // special impl.
+    let ambiguous = Some(Selection::Ambiguous);
+    assert_eq!(
+        select(&program, "forall<T> { T: Show }"),
+        vec![ambiguous.clone(), ambiguous]
+    );
+}
+
+#[test]
+fn select_other_sources() {
+    let program = lower(PROGRAM);
+    let environment = Some(Selection::Source(ClauseSource::Environment));
+    assert_eq!(
+        select(&program, "forall<T> { if (T: Clone) { T: Clone } }"),
+        vec![environment.clone(), environment]
+    );
+    let builtin = Some(Selection::Source(ClauseSource::Builtin));
+    assert_eq!(
+        select(&program, "(u8, u8): Copy"),
+        vec![builtin.clone(), builtin]
+    );
+}
+
+#[test]
+fn select_unimplemented() {
+    let program = lower(PROGRAM);
+    assert_eq!(select(&program, "Bar: Clone"), vec![None, None]);
+    assert_eq!(select(&program, "Vec<Bar>: Clone"), vec![None, None]);
+}
+
+#[test]
+fn select_associated_ty_value() {
+    let program = lower(PROGRAM);
+    let value_id = *program.associated_ty_values.keys().next().unwrap();
+    for selection in select_value(&program, "Normalize(<Vec<Foo> as Iterator>::Item -> Foo)") {
+        match selection {
+            Some(ValueSelection::Value {
+                value_id: selected,
+                substitution,
+            }) => {
+                assert_eq!(selected, value_id);
+                let substitution =
+                    tls::set_current_program(&program, || format!("{:?}", substitution.value));
+                assert_eq!(substitution, "[?0 := Foo]");
+            }
+            selection => panic!("expected a value, found {:?}", selection),
+        }
+    }
+    let environment = Some(ValueSelection::Source(ClauseSource::Environment));
+    assert_eq!(
+        select_value(
+            &program,
+            "forall<T> { if (T: Iterator) { Normalize(<T as Iterator>::Item -> Foo) } }"
+        ),
+        vec![environment.clone(), environment]
+    );
+}
+
+/// A specializing impl that does not define the associated type
+/// inherits the value of the impl it specializes.
+#[test]
+fn select_inherited_associated_ty_value() {
+    let program = lower(
+        "
+        trait Iterator { type Item; }
+        struct Foo { }
+        struct Vec<T> { }
+        impl<T> Iterator for Vec<T> { type Item = T; }
+        impl Iterator for Vec<Foo> { }
+        ",
+    );
+    let value_id = *program.associated_ty_values.keys().next().unwrap();
+    for selection in select_value(&program, "Normalize(<Vec<Foo> as Iterator>::Item -> Foo)") {
+        match selection {
+            Some(ValueSelection::Value {
+                value_id: selected,
+                substitution,
+            }) => {
+                assert_eq!(selected, value_id);
+                let substitution =
+                    tls::set_current_program(&program, || format!("{:?}", substitution.value));
+                assert_eq!(substitution, "[?0 := Foo]");
+            }
+            selection => panic!("expected a value, found {:?}", selection),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Tests for the statistics gathered by the solvers.
+
+use super::*;
+use chalk_solve::{OverflowLimit, Solver, SolverStats, TruncationLimit};
+
+fn solve(
+    solver: &mut Solver<ChalkIr>,
+    program: &dyn RustIrDatabase<ChalkIr>,
+    goal: &UCanonicalGoal<ChalkIr>,
+) -> SolverStats {
+    solver.reset_stats();
+    solver.solve(program, goal);
+    solver.stats()
+}
+
+const PROGRAM: &str = "
+    trait Clone { }
+    struct Foo { }
+    struct Vec<T> { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+#[test]
+fn slg_stats() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = SolverChoice::slg_default().into_solver();
+        assert_eq!(solver.stats(), SolverStats::default());
+
+        let stats = solve(&mut solver, program, goal);
+        assert!(stats.tables_created > 0);
+        assert!(stats.strands_created >= stats.tables_created);
+        assert!(stats.answers > 0);
+        assert_eq!(stats.truncations, 0);
+        assert_eq!(stats.cache_hits, 0);
+
+        // The tables are reused, so solving again does no new work.
+        let stats = solve(&mut solver, program, goal);
+        assert_eq!(stats, SolverStats::default());
+    });
+}
+
+#[test]
+fn slg_truncation_stats() {
+    let program = "
+        trait Foo { }
+        struct Box<T> { }
+        impl<T> Foo for T where Box<T>: Foo { }
+    ";
+    with_goal(program, "exists<T> { T: Foo }", |program, goal| {
+        let mut solver = SolverChoice::slg(10, None).into_solver();
+        let stats = solve(&mut solver, program, goal);
+        assert!(stats.truncations > 0);
+    });
+}
+
+#[test]
+fn recursive_stats() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = SolverChoice::recursive().into_solver();
+
+        let stats = solve(&mut solver, program, goal);
+        assert!(stats.cache_misses > 0);
+        assert_eq!(stats.tables_created, 0);
+        assert_eq!(stats.overflows, 0);
+
+        // The root goal is now cached.
+        let stats = solve(&mut solver, program, goal);
+        assert_eq!(
+            stats,
+            SolverStats {
+                cache_hits: 1,
+                ..SolverStats::default()
+            }
+        );
+    });
+}
+
+#[test]
+fn recursive_cycle_stats() {
+    let program = "
+        trait Foo { }
+        struct S { }
+        impl<T> Foo for T where T: Foo { }
+    ";
+    with_goal(program, "S: Foo", |program, goal| {
+        let mut solver = SolverChoice::recursive().into_solver();
+        let stats = solve(&mut solver, program, goal);
+        assert!(stats.cycles > 0);
+    });
+}
+
+#[test]
+fn recursive_overflow_depth() {
+    with_goal(
+        PROGRAM,
+        "Vec<Vec<Vec<Vec<Foo>>>>: Clone",
+        |program, goal| {
+            let mut solver = SolverChoice::Recursive {
+                overflow_depth: 3,
+                caching_enabled: true,
+            }
+            .into_solver();
+            solver.reset_stats();
+            let solution = solver.solve(program, goal).unwrap();
+            assert!(!solution.is_unique());
+            assert!(solver.stats().overflows > 0);
+
+            let overflows = solver.overflows();
+            assert!(!overflows.is_empty());
+            assert!(overflows
+                .iter()
+                .all(|overflow| overflow.limit == OverflowLimit::Depth(3)));
+
+            // The results that depend on the overflow are not cached, so
+            // solving again overflows again.
+            let stats = solve(&mut solver, program, goal);
+            assert!(stats.overflows > 0);
+
+            // Without a limit in the way, the goal holds.
+            let mut solver = SolverChoice::recursive().into_solver();
+            assert!(solver.solve(program, goal).unwrap().is_unique());
+            assert!(solver.overflows().is_empty());
+        },
+    );
+}
+
+const OVERFLOW_PROGRAM: &str = "
+    trait Q { }
+    struct Z { }
+    struct G<X> { }
+    struct S<X> { }
+    impl Q for Z { }
+    impl<X> Q for G<X> where X: Q { }
+    impl<X> Q for S<X> where X: Q, S<G<X>>: Q { }
+";
+
+#[test]
+fn recursive_overflow_size() {
+    with_goal(OVERFLOW_PROGRAM, "S<Z>: Q", |program, goal| {
+        let mut solver = SolverChoice::recursive().into_solver();
+        assert!(!solver.solve(program, goal).unwrap().is_unique());
+
+        let overflows = solver.overflows();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(
+            overflows[0].limit,
+            OverflowLimit::Goal(TruncationLimit::Size(30))
+        );
+
+        solver.reset_stats();
+        assert!(solver.overflows().is_empty());
+    });
+}
+
+#[test]
+fn slg_overflow_size() {
+    with_goal(OVERFLOW_PROGRAM, "S<Z>: Q", |program, goal| {
+        let mut solver = SolverChoice::slg(10, None).into_solver();
+        assert!(!solver.solve(program, goal).unwrap().is_unique());
+
+        let overflows = solver.overflows();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(
+            overflows[0].limit,
+            OverflowLimit::Goal(TruncationLimit::Size(10))
+        );
+    });
+}
@@ -0,0 +1,84 @@
+//! Tests for evicting SLG tables under a `TableBudget`.
+
+use super::*;
+use chalk_solve::TableBudget;
+
+const PROGRAM: &str = "
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+fn slg(table_budget: TableBudget) -> SolverChoice {
+    SolverChoice::slg(10, None).with_table_budget(table_budget)
+}
+
+#[test]
+fn unlimited_budget_keeps_tables() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = slg(TableBudget::Unlimited).into_solver();
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        assert_eq!(solver.stats().tables_evicted, 0);
+    });
+}
+
+#[test]
+fn evicted_tables_are_recomputed() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = slg(TableBudget::Tables(1)).into_solver();
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        let stats = solver.stats();
+        assert!(stats.tables_created > 1);
+        assert_eq!(stats.tables_evicted, stats.tables_created - 1);
+
+        // The one table left is the most recently used one; all other
+        // tables have to be recomputed.
+        solver.reset_stats();
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        assert!(solver.stats().tables_created > 0);
+    });
+}
+
+#[test]
+fn answer_budget() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = slg(TableBudget::Answers(0)).into_solver();
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        assert!(solver.stats().tables_evicted > 0);
+
+        // No answers were kept, so they have to be found again.
+        solver.reset_stats();
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        assert!(solver.stats().answers > 0);
+    });
+}
+
+/// Evicting tables must not change the results of later queries, even
+/// when the tables of earlier queries are still in progress.
+#[test]
+fn eviction_preserves_results() {
+    let goals = [
+        "exists<T> { T: Clone }",
+        "Vec<Vec<Foo>>: Clone",
+        "Vec<Bar>: Clone",
+        "exists<T> { Vec<T>: Clone }",
+        "Vec<Foo>: Clone",
+        "exists<T> { T: Clone }",
+    ];
+    let results = |table_budget| {
+        let mut solver = slg(table_budget).into_solver();
+        let mut results = vec![];
+        for goal in &goals {
+            with_goal(PROGRAM, goal, |program, goal| {
+                results.push(solver.solve(program, goal));
+            });
+        }
+        results
+    };
+    let expected = results(TableBudget::Unlimited);
+    assert_eq!(results(TableBudget::Tables(0)), expected);
+    assert_eq!(results(TableBudget::Answers(1)), expected);
+}
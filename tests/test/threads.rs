@@ -0,0 +1,140 @@
+//! Tests for solving goals from several threads.
+
+use super::*;
+use chalk_solve::Solver;
+use salsa::ParallelDatabase;
+use std::thread;
+
+const PROGRAM: &str = "
+    trait Clone { }
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+const GOALS: &[(&str, &str)] = &[
+    ("Vec<Foo>: Clone", "Unique"),
+    ("Vec<Vec<Foo>>: Clone", "Unique"),
+    ("Vec<Bar>: Clone", "No possible solution"),
+    ("exists<T> { Vec<T>: Clone }", "Ambiguous"),
+];
+
+#[test]
+fn solver_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Solver<ChalkIr>>();
+}
+
+#[test]
+fn slg_forks_share_completed_tables() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = SolverChoice::slg_default().into_solver();
+        let mut fork = solver.fork();
+        assert!(fork.solve(program, goal).unwrap().is_unique());
+        assert!(fork.stats().strands_created > 0);
+
+        // The tables completed by the fork are copied, not recomputed.
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        let stats = solver.stats();
+        assert_eq!(stats.tables_created, 0);
+        assert!(stats.tables_reused > 0);
+        assert_eq!(stats.strands_created, 0);
+        assert_eq!(stats.answers, 0);
+    });
+}
+
+#[test]
+fn recursive_forks_share_cache() {
+    with_goal(PROGRAM, "Vec<Vec<Foo>>: Clone", |program, goal| {
+        let mut solver = SolverChoice::recursive().into_solver();
+        let mut fork = solver.fork();
+        assert!(fork.solve(program, goal).unwrap().is_unique());
+
+        assert!(solver.solve(program, goal).unwrap().is_unique());
+        assert_eq!(solver.stats().cache_hits, 1);
+        assert_eq!(solver.stats().cache_misses, 0);
+    });
+}
+
+#[test]
+fn solve_in_parallel() {
+    for solver_choice in [SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let db = ChalkDatabase::with(PROGRAM, solver_choice);
+        let program = db.checked_program().unwrap();
+        let mut solver = solver_choice.into_solver();
+        let goals: Vec<_> = GOALS
+            .iter()
+            .map(|&(goal, expected)| {
+                let goal = chalk_parse::parse_goal(goal)
+                    .unwrap()
+                    .lower(&*program)
+                    .unwrap()
+                    .into_peeled_goal(db.interner());
+                (goal, expected)
+            })
+            .collect();
+
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let program = &*program;
+                let goals = &goals;
+                let mut solver = solver.fork();
+                scope.spawn(move || {
+                    for (goal, expected) in goals {
+                        let result = solver.solve(program, goal);
+                        assert_result_starts_with(result, expected);
+                    }
+                });
+            }
+        });
+
+        // Every goal has been solved by some fork.
+        for (goal, expected) in &goals {
+            assert_result_starts_with(solver.solve(&*program, goal), expected);
+        }
+    }
+}
+
+#[test]
+fn solve_from_snapshots() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goals: Vec<_> = GOALS
+        .iter()
+        .map(|&(goal, expected)| {
+            let goal = db
+                .parse_and_lower_goal(goal)
+                .unwrap()
+                .into_peeled_goal(db.interner());
+            (goal, expected)
+        })
+        .collect();
+
+    // Solving with the database itself uses its solver.
+    let (goal, expected) = &goals[0];
+    assert_result_starts_with(db.solve(goal), expected);
+    assert!(db.solver().stats().strands_created > 0);
+
+    thread::scope(|scope| {
+        for (goal, expected) in &goals {
+            let db = db.snapshot();
+            scope.spawn(move || {
+                assert_result_starts_with(db.solve(goal), expected);
+            });
+        }
+    });
+}
+
+fn assert_result_starts_with(result: Option<Solution<ChalkIr>>, expected: &str) {
+    let result = match result {
+        Some(v) => format!("{}", v.display(&ChalkIr)),
+        None => "No possible solution".to_string(),
+    };
+    assert!(
+        result.starts_with(expected),
+        "expected `{}`, got `{}`",
+        expected,
+        result
+    );
+}
@@ -0,0 +1,67 @@
+//! Tests for the structured trace output of the solvers.
+
+use super::*;
+use chalk_integration::trace;
+
+const PROGRAM: &str = "
+    trait Clone { }
+    struct Foo { }
+    struct Vec<T> { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+const GOAL: &str = "Vec<Foo>: Clone";
+
+fn solve_traced(solver_choice: SolverChoice, directives: &str) -> Vec<String> {
+    let mut events = vec![];
+    with_goal(PROGRAM, GOAL, |program, goal| {
+        let mut solver = solver_choice.into_solver();
+        let (solution, trace) = trace::collect(directives, || solver.solve(program, goal));
+        assert!(solution.unwrap().is_unique());
+        events = trace;
+    });
+    events
+}
+
+#[test]
+fn slg_trace_fields() {
+    let events = solve_traced(SolverChoice::slg_default(), "debug");
+    assert!(events.iter().all(|e| e.starts_with('{')));
+    assert!(events.iter().any(|e| e.contains("\"table\":")));
+    assert!(events.iter().any(|e| e.contains("\"goal\":")));
+    assert!(events.iter().any(|e| e.contains("\"clause\":")));
+    assert!(events.iter().any(|e| e.contains("\"result\":")));
+}
+
+#[test]
+fn recursive_trace_fields() {
+    let events = solve_traced(SolverChoice::recursive(), "info");
+    assert!(events.iter().any(|e| e.contains("\"goal\":")));
+    assert!(events.iter().any(|e| e.contains("\"clause\":")));
+    assert!(events.iter().any(|e| e.contains("\"result\":")));
+    assert!(events.iter().all(|e| !e.contains("\"level\":\"DEBUG\"")));
+}
+
+#[test]
+fn trace_filters() {
+    assert!(solve_traced(SolverChoice::slg_default(), "0").is_empty());
+
+    let events = solve_traced(SolverChoice::slg_default(), "chalk_engine=info");
+    assert!(!events.is_empty());
+    assert!(events
+        .iter()
+        .all(|e| e.contains("\"target\":\"chalk_engine::")));
+}
+
+/// Traces of two identical runs are identical, so that they can be
+/// diffed to compare the behavior of two solver configurations.
+#[test]
+fn trace_is_deterministic() {
+    for solver_choice in [SolverChoice::slg_default(), SolverChoice::recursive()] {
+        let first = solve_traced(solver_choice, "debug");
+        let second = solve_traced(solver_choice, "debug");
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+    }
+}
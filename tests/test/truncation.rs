@@ -0,0 +1,125 @@
+//! Tests for the `TruncationPolicy` of the SLG solver.
+
+use super::*;
+use chalk_solve::{OverflowLimit, TruncationLimit, TruncationPolicy};
+
+fn slg(goals: Option<TruncationLimit>, answers: Option<TruncationLimit>) -> SolverChoice {
+    SolverChoice::slg(10, None).with_truncation(TruncationPolicy { goals, answers })
+}
+
+#[test]
+fn wide_goals() {
+    test! {
+        program {
+            trait Foo { }
+            trait Bar { }
+            impl<A, B, C, D, E, F> Foo for (A, B, C, D, E, F) { }
+            impl<T> Bar for T where (T, T, T, T, T, T): Foo { }
+        }
+
+        // The subgoal `(u32, u32, u32, u32, u32, u32): Foo` has size 7
+        // but depth 2.
+        goal {
+            u32: Bar
+        } yields[slg(Some(TruncationLimit::Size(5)), None)] {
+            "Ambiguous; no inference guidance"
+        } yields[slg(Some(TruncationLimit::Depth(5)), None)] {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn deep_goals() {
+    test! {
+        program {
+            trait Foo { }
+            trait Bar { }
+            struct Box<T> { }
+            impl<T> Foo for T { }
+            impl<T> Bar for T where Box<Box<Box<Box<T>>>>: Foo { }
+        }
+
+        goal {
+            u32: Bar
+        } yields[slg(Some(TruncationLimit::Depth(4)), None)] {
+            "Ambiguous; no inference guidance"
+        } yields[slg(Some(TruncationLimit::Depth(5)), None)] {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn goals_with_many_variables() {
+    test! {
+        program {
+            trait Foo { }
+            trait Bar { }
+            impl<A, B, C> Foo for (A, B, C) { }
+            impl<A, B, C> Bar for (A, B, C) where (A, B, C): Foo { }
+        }
+
+        goal {
+            exists<A, B, C> { (A, B, C): Bar }
+        } yields[slg(Some(TruncationLimit::Variables(2)), None)] {
+            "Ambiguous; no inference guidance"
+        } yields[slg(Some(TruncationLimit::Variables(3)), None)] {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn answers_truncated_independently() {
+    test! {
+        program {
+            trait Foo { }
+            struct Z { }
+            struct S<T> { }
+            impl Foo for Z { }
+            impl<T> Foo for S<T> where T: Foo { }
+        }
+
+        // Only the answers grow, so limiting goals alone does not
+        // prevent the answers from being enumerated.
+        goal {
+            exists<T> { T: Foo }
+        } yields_first[slg(Some(TruncationLimit::Size(3)), None)] {
+            "substitution [?0 := Z], lifetime constraints []",
+            "substitution [?0 := S<Z>], lifetime constraints []",
+            "substitution [?0 := S<S<Z>>], lifetime constraints []",
+            "substitution [?0 := S<S<S<Z>>>], lifetime constraints []"
+        }
+
+        goal {
+            exists<T> { T: Foo }
+        } yields_first[slg(None, Some(TruncationLimit::Size(3)))] {
+            "substitution [?0 := Z], lifetime constraints []",
+            "substitution [?0 := S<Z>], lifetime constraints []",
+            "substitution [?0 := S<S<Z>>], lifetime constraints []",
+            "Floundered"
+        }
+    }
+}
+
+#[test]
+fn truncated_answers_are_reported() {
+    let program = "
+        trait Foo { }
+        struct Z { }
+        struct S<T> { }
+        impl Foo for S<S<S<Z>>> { }
+    ";
+    with_goal(program, "exists<T> { T: Foo }", |program, goal| {
+        let mut solver = slg(None, Some(TruncationLimit::Size(3))).into_solver();
+        assert!(!solver.solve(program, goal).unwrap().is_unique());
+
+        let overflows = solver.overflows();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(
+            overflows[0].limit,
+            OverflowLimit::Answer(TruncationLimit::Size(3))
+        );
+    });
+}
@@ -32,13 +32,27 @@ fn ill_formed_trait_decl() {
 }
 #[test]
 fn cyclic_traits() {
-    lowering_success! {
+    lowering_error! {
         program {
             trait A where Self: B { }
             trait B where Self: A { }
 
             impl<T> B for T { }
             impl<T> A for T { }
+        } error_msg {
+            "cycle in the supertraits of trait `A`: `A` -> `B` -> `A`"
+        }
+    }
+
+    // Where clauses on other types than `Self` may still form a cycle,
+    // which the coinductive WF goals go through.
+    lowering_success! {
+        program {
+            trait A<T> where T: B<Self> { }
+            trait B<T> where T: A<Self> { }
+
+            impl<T, U> B<T> for U { }
+            impl<T, U> A<T> for U { }
         }
     }
 
@@ -46,13 +60,13 @@ fn cyclic_traits() {
         program {
             trait Copy { }
 
-            trait A where Self: B, Self: Copy {}
-            trait B where Self: A { }
+            trait A<T> where T: B<Self>, Self: Copy { }
+            trait B<T> where T: A<Self> { }
 
             // This impl won't be able to prove that `T: Copy` holds.
-            impl<T> B for T {}
+            impl<T, U> B<T> for U { }
 
-            impl<T> A for T where T: B {}
+            impl<T, U> A<T> for U where T: B<U> { }
         } error_msg {
             "trait impl for `B` does not meet well-formedness requirements"
         }
@@ -62,11 +76,11 @@ fn cyclic_traits() {
         program {
             trait Copy { }
 
-            trait A where Self: B, Self: Copy {}
-            trait B where Self: A { }
+            trait A<T> where T: B<Self>, Self: Copy { }
+            trait B<T> where T: A<Self> { }
 
-            impl<T> B for T where T: Copy {}
-            impl<T> A for T where T: B {}
+            impl<T, U> B<T> for U where T: Copy { }
+            impl<T, U> A<T> for U where T: B<U> { }
         }
     }
 }
@@ -483,11 +497,23 @@ fn higher_ranked_trait_bound_on_gat() {
 // See `cyclic_traits`, this is essentially the same but with higher-ranked co-inductive WF goals.
 #[test]
 fn higher_ranked_cyclic_requirements() {
-    lowering_success! {
+    lowering_error! {
         program {
             trait Foo<T> where forall<U> Self: Bar<U> { }
             trait Bar<T> where forall<U> Self: Foo<T> { }
 
+            impl<T, U> Foo<T> for U { }
+            impl<T, U> Bar<T> for U { }
+        } error_msg {
+            "cycle in the supertraits of trait `Foo`: `Foo` -> `Bar` -> `Foo`"
+        }
+    }
+
+    lowering_success! {
+        program {
+            trait Foo<T> where forall<U> T: Bar<U> { }
+            trait Bar<T> where forall<U> Self: Foo<Self> { }
+
             impl<T, U> Foo<T> for U { }
             impl<T, U> Bar<T> for U { }
         }
@@ -496,11 +522,11 @@ fn higher_ranked_cyclic_requirements() {
     lowering_error! {
         program {
             trait Copy { }
-            trait Foo<T> where forall<U> Self: Bar<U>, Self: Copy { }
-            trait Bar<T> where forall<U> Self: Foo<T> { }
+            trait Foo<T> where forall<U> T: Bar<U>, T: Copy { }
+            trait Bar<T> where forall<U> Self: Foo<Self> { }
 
             impl<T, U> Foo<T> for U { }
-            impl<T, U> Bar<T> for U where U: Foo<T> { }
+            impl<T, U> Bar<T> for U where U: Foo<U> { }
         } error_msg {
             "trait impl for `Foo` does not meet well-formedness requirements"
         }
@@ -509,11 +535,11 @@ fn higher_ranked_cyclic_requirements() {
     lowering_success! {
         program {
             trait Copy { }
-            trait Foo<T> where forall<U> Self: Bar<U>, Self: Copy { }
-            trait Bar<T> where forall<U> Self: Foo<T> { }
+            trait Foo<T> where forall<U> T: Bar<U>, T: Copy { }
+            trait Bar<T> where forall<U> Self: Foo<Self> { }
 
-            impl<T, U> Foo<T> for U where U: Copy { }
-            impl<T, U> Bar<T> for U where U: Foo<T> { }
+            impl<T, U> Foo<T> for U where T: Copy { }
+            impl<T, U> Bar<T> for U where U: Foo<U> { }
         }
     }
 }
@@ -819,3 +845,159 @@ fn drop_constraints() {
         }
     }
 }
+
+#[test]
+fn trait_decl_wf() {
+    lowering_success! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            trait Foo<T> where T: Hash, Self: Bar<Set<T>> { }
+            trait Bar<T> { }
+
+            trait Baz {
+                type Item: Bar<Set<<Self as Baz>::Item>> where <Self as Baz>::Item: Hash;
+            }
+        }
+    }
+
+    // `Set<T>` in a supertrait is ill-formed unless `T: Hash`.
+    lowering_error! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            trait Foo<T> where Self: Bar<Set<T>> { }
+            trait Bar<T> { }
+        } error_msg {
+            "trait declaration `Foo` does not meet well-formedness requirements"
+        }
+    }
+
+    // The same goes for the bounds of an associated type.
+    lowering_error! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+            trait Bar<T> { }
+
+            trait Baz {
+                type Item: Bar<Set<<Self as Baz>::Item>>;
+            }
+        } error_msg {
+            "trait declaration `Baz` does not meet well-formedness requirements"
+        }
+    }
+}
+
+#[test]
+fn fn_def_wf() {
+    lowering_success! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            fn insert<T>(set: Set<T>, value: T) -> Set<T> where T: Hash;
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            fn insert<T>(set: Set<T>, value: T) -> Set<T>;
+        } error_msg {
+            "function definition `insert` does not meet well-formedness requirements"
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            fn empty<T>() -> Set<T>;
+        } error_msg {
+            "function definition `empty` does not meet well-formedness requirements"
+        }
+    }
+}
+
+#[test]
+fn opaque_ty_wf() {
+    lowering_success! {
+        program {
+            trait Clone { }
+            struct Vec<T> { }
+            impl<T> Clone for Vec<T> where T: Clone { }
+
+            opaque type Foo: Clone = Vec<u32>;
+            impl Clone for u32 { }
+        }
+    }
+
+    // The hidden type does not satisfy the bounds.
+    lowering_error! {
+        program {
+            trait Clone { }
+            struct Vec<T> { }
+            impl<T> Clone for Vec<T> where T: Clone { }
+
+            opaque type Foo<T>: Clone = Vec<T>;
+        } error_msg {
+            "opaque type `Foo` does not meet well-formedness requirements"
+        }
+    }
+
+    // The hidden type is ill-formed.
+    lowering_error! {
+        program {
+            trait Hash { }
+            trait Clone { }
+            struct Set<T> where T: Hash { }
+            impl<T> Clone for Set<T> { }
+
+            opaque type Foo<T>: Clone = Set<T>;
+        } error_msg {
+            "opaque type `Foo` does not meet well-formedness requirements"
+        }
+    }
+}
+
+#[test]
+fn wf_error_obligations() {
+    lowering_error! {
+        program {
+            trait Foo { }
+            struct OnlyFoo<T> where T: Foo { }
+
+            struct MyType { }
+
+            trait Bar {
+                type Value;
+            }
+
+            impl Bar for MyType {
+                type Value = OnlyFoo<MyType>;
+            }
+        } error_msg {
+            "trait impl for `Bar` does not meet well-formedness requirements: impl `ImplId(#4)` \
+             cannot prove `WellFormed(OnlyFoo<MyType>)` (required by associated type `(Bar::Value)`)"
+        }
+    }
+
+    lowering_error! {
+        program {
+            trait Hash { }
+            struct Set<T> where T: Hash { }
+
+            struct Foo<T> {
+                field: Set<T>
+            }
+        } error_msg {
+            "type declaration `Foo` does not meet well-formedness requirements: cannot prove `WellFormed(Set<^0.0>)` (required by field 0)"
+        }
+    }
+}
@@ -34,6 +34,28 @@ macro_rules! lowering_error {
     };
 }
 
+macro_rules! lowering_warnings {
+    (program $program:tt warning_msgs { $($expected:expr),* }) => {
+        let program_text = stringify!($program);
+        assert!(program_text.starts_with("{"));
+        assert!(program_text.ends_with("}"));
+        let mut db = chalk_integration::db::ChalkDatabase::with(
+            &program_text[1..program_text.len() - 1],
+            chalk_solve::SolverChoice::default(),
+        );
+        db.set_future_compat_warnings(true);
+        let coherence = db.coherence().unwrap();
+        let warnings: Vec<String> = db.with_program(|_| {
+            coherence.warnings.iter().map(|w| w.to_string()).collect()
+        });
+        let expected: Vec<String> = vec![$($expected.to_string()),*];
+        assert_eq!(warnings.len(), expected.len(), "warnings: {:?}", warnings);
+        for (warning, expected) in warnings.iter().zip(&expected) {
+            crate::test_util::assert_same(warning, expected);
+        }
+    };
+}
+
 pub fn assert_same(result: &str, expected: &str) {
     println!("expected:\n{}", expected);
     println!("actual:\n{}", result);